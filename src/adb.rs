@@ -1,8 +1,38 @@
-use std::net::{Ipv4Addr, SocketAddrV4};
+use std::{
+    io::{self, Read, Write},
+    mem,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpStream},
+    path::PathBuf,
+    sync::{
+        Arc, PoisonError, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 
-#[derive(Debug)]
+use adb_client::{ADBDeviceExt, ADBServerDevice};
+
+use crate::adb_direct::DirectDevice;
+
+/// How the app reaches the device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transport {
+    /// Through the adb server at [`AdbOptions::address`].
+    Server,
+    /// Straight to the adbd listening at `address`, authenticating with the RSA key at
+    /// `key_path`. Only shell services are available: logs, input, screenshots and the like.
+    Direct {
+        address: SocketAddr,
+        key_path: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone)]
 pub struct AdbOptions {
     pub address: SocketAddrV4,
+    pub transport: Transport,
+    /// Serial of the device picked on the DEVICE tab, shared by every clone of these options so
+    /// workers started afterwards follow the pick. `None` targets the only connected device.
+    serial: Arc<RwLock<Option<String>>>,
 }
 
 // Manual implementation of the Default trait
@@ -12,6 +42,257 @@ impl Default for AdbOptions {
         const ADB_DEFAULT_PORT: u16 = 5037;
         Self {
             address: SocketAddrV4::new(Ipv4Addr::LOCALHOST, ADB_DEFAULT_PORT),
+            transport: Transport::Server,
+            serial: Arc::default(),
+        }
+    }
+}
+
+impl AdbOptions {
+    /// A handle to the device these options point at. Each worker thread should create its own.
+    pub fn device(&self) -> Device {
+        match &self.transport {
+            Transport::Server => Device::Server(self.serial().map_or_else(
+                || ADBServerDevice::autodetect(Some(self.address)),
+                |serial| ADBServerDevice::new(serial, Some(self.address)),
+            )),
+            Transport::Direct {
+                address,
+                key_path,
+            } => Device::Direct(DirectDevice::new(*address, key_path.clone())),
+        }
+    }
+
+    /// A connection to the adb server, for the host services and `sync:` that have no
+    /// equivalent here when talking to adbd directly.
+    pub fn connect_server(&self) -> io::Result<TcpStream> {
+        match &self.transport {
+            Transport::Server => TcpStream::connect(self.address),
+            Transport::Direct {
+                address,
+                ..
+            } => Err(io::Error::other(format!("not available when connected straight to {address}"))),
+        }
+    }
+
+    /// Options for `serial` alone, unaffected by what is picked on the DEVICE tab.
+    pub fn for_serial(&self, serial: &str) -> Self {
+        Self {
+            address: self.address,
+            transport: self.transport.clone(),
+            serial: Arc::new(RwLock::new(Some(serial.to_string()))),
         }
     }
+
+    /// Options for the device these point at now, which keep pointing at it whatever is picked
+    /// on the DEVICE tab later. Falls back to the current pick if the device cannot be resolved.
+    pub fn pinned(&self) -> Self {
+        let serial = match self.transport {
+            Transport::Server => device_serial(self).ok().or_else(|| self.serial()),
+            Transport::Direct {
+                ..
+            } => self.serial(),
+        };
+        Self {
+            address: self.address,
+            transport: self.transport.clone(),
+            serial: Arc::new(RwLock::new(serial)),
+        }
+    }
+
+    pub fn serial(&self) -> Option<String> {
+        self.serial.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    pub fn select(&self, serial: Option<String>) {
+        *self.serial.write().unwrap_or_else(PoisonError::into_inner) = serial;
+    }
+
+    /// The host service `name` addressed to the target device, e.g. `get-serialno`.
+    pub fn host_service(&self, name: &str) -> String {
+        self.serial().map_or_else(|| format!("host:{name}"), |serial| format!("host-serial:{serial}:{name}"))
+    }
+
+    /// The service that switches a server connection over to the target device.
+    pub fn transport_service(&self) -> String {
+        self.serial().map_or_else(|| "host:transport-any".to_string(), |serial| format!("host:transport:{serial}"))
+    }
+}
+
+/// The device behind [`AdbOptions::device`].
+pub enum Device {
+    Server(ADBServerDevice),
+    Direct(DirectDevice),
+}
+
+impl Device {
+    /// Run `command` through the `shell:` service, streaming its stdout into `output`. Returns the
+    /// exit code where the transport reports one.
+    pub fn shell_command(&mut self, command: &[&str], output: &mut dyn Write) -> adb_client::Result<Option<u8>> {
+        match self {
+            Self::Server(device) => device.shell_command(command, output),
+            Self::Direct(device) => Ok(device.shell_command(command, output).map(|()| None)?),
+        }
+    }
+
+    /// Stream logcat into `output` until the device goes away.
+    pub fn get_logs(&mut self, output: impl Write) -> adb_client::Result<()> {
+        match self {
+            Self::Server(device) => device.get_logs(output),
+            Self::Direct(device) => Ok(device.get_logs(output)?),
+        }
+    }
+}
+
+/// Run `command` through the device's `shell:` service and return everything it wrote to stdout.
+pub fn shell_output(device: &mut Device, command: &[&str]) -> adb_client::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    device.shell_command(command, &mut output)?;
+    Ok(output)
+}
+
+/// Splits the output of a shell command into lines and hands each batch of complete ones to
+/// `on_lines`. The command is ended, by failing the write, once `stop` is set or `on_lines`
+/// returns false.
+pub struct LineSink<'a, F> {
+    stop: &'a AtomicBool,
+    on_lines: F,
+    buffer: Vec<u8>,
+}
+
+impl<'a, F: FnMut(Vec<String>) -> bool> LineSink<'a, F> {
+    pub const fn new(stop: &'a AtomicBool, on_lines: F) -> Self {
+        Self {
+            stop,
+            on_lines,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Hand over a last line that did not end with a newline, unless the sink was stopped.
+    pub fn finish(mut self) {
+        if !self.buffer.is_empty() && !self.stop.load(Ordering::Relaxed) {
+            let rest = mem::take(&mut self.buffer);
+            (self.on_lines)(split_lines(&rest));
+        }
+    }
+}
+
+impl<F: FnMut(Vec<String>) -> bool> Write for LineSink<'_, F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let stopped = || io::Error::new(io::ErrorKind::BrokenPipe, "shell output no longer wanted");
+        if self.stop.load(Ordering::Relaxed) {
+            // Returning an error is the only way to end the shell command.
+            return Err(stopped());
+        }
+        self.buffer.extend_from_slice(buf);
+        if let Some(end) = self.buffer.iter().rposition(|&b| b == b'\n') {
+            let lines: Vec<u8> = self.buffer.drain(..=end).collect();
+            if !(self.on_lines)(split_lines(&lines)) {
+                return Err(stopped());
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Lines of `bytes` without their `\n` or `\r\n` endings.
+fn split_lines(bytes: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(bytes).lines().map(ToString::to_string).collect()
+}
+
+/// Send `service` on a connection to the adb server and wait for it to be accepted. A refusal
+/// comes back as an error carrying the server's message.
+pub fn send_service(stream: &mut TcpStream, service: &str) -> io::Result<()> {
+    write!(stream, "{:04x}{service}", service.len())?;
+    let mut status = [0; 4];
+    stream.read_exact(&mut status)?;
+    if &status == b"OKAY" {
+        return Ok(());
+    }
+    let message = read_reply(stream)?;
+    Err(io::Error::other(format!("{service}: {message}")))
+}
+
+/// Read a reply prefixed with its length as four hex digits.
+pub fn read_reply(stream: &mut TcpStream) -> io::Result<String> {
+    let mut length = [0; 4];
+    stream.read_exact(&mut length)?;
+    let length = std::str::from_utf8(&length)
+        .ok()
+        .and_then(|hex| usize::from_str_radix(hex, 16).ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed adb reply length"))?;
+    let mut reply = vec![0; length];
+    stream.read_exact(&mut reply)?;
+    Ok(String::from_utf8_lossy(&reply).into_owned())
+}
+
+/// Run a host service that answers with a single reply, such as `host:get-serialno`.
+pub fn host_query(adb: &AdbOptions, service: &str) -> io::Result<String> {
+    let mut stream = adb.connect_server()?;
+    send_service(&mut stream, service)?;
+    read_reply(&mut stream)
+}
+
+/// Follow `host:track-devices`, calling `on_change` with the serial and state of every device the
+/// server knows each time that list changes. Returns only when the connection fails.
+pub fn track_devices(adb: &AdbOptions, mut on_change: impl FnMut(Vec<(String, String)>)) -> io::Result<()> {
+    let mut stream = adb.connect_server()?;
+    send_service(&mut stream, "host:track-devices")?;
+    loop {
+        let list = read_reply(&mut stream)?;
+        on_change(
+            list.lines()
+                .filter_map(|line| line.split_once('\t'))
+                .map(|(serial, state)| (serial.to_string(), state.to_string()))
+                .collect(),
+        );
+    }
+}
+
+/// The serial of the target device. A device reached directly goes by its address, as it would
+/// after `adb connect`.
+pub fn device_serial(adb: &AdbOptions) -> io::Result<String> {
+    match &adb.transport {
+        Transport::Server => host_query(adb, &adb.host_service("get-serialno")),
+        Transport::Direct {
+            address,
+            ..
+        } => Ok(address.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_sink_splits_across_writes() {
+        let stop = AtomicBool::new(false);
+        let mut batches = Vec::new();
+        let mut sink = LineSink::new(&stop, |lines| {
+            batches.push(lines);
+            true
+        });
+        sink.write_all(b"one\r\ntw").unwrap();
+        sink.write_all(b"o\nthree\nfo").unwrap();
+        sink.finish();
+        assert_eq!(batches, [vec!["one"], vec!["two", "three"], vec!["fo"]]);
+    }
+
+    #[test]
+    fn line_sink_ends_the_command() {
+        let stop = AtomicBool::new(false);
+        let mut sink = LineSink::new(&stop, |lines| lines != ["quit"]);
+        sink.write_all(b"go on\n").unwrap();
+        assert_eq!(sink.write_all(b"quit\n").unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+
+        let mut sink = LineSink::new(&stop, |_| true);
+        stop.store(true, Ordering::Relaxed);
+        assert_eq!(sink.write_all(b"late\n").unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+    }
 }
@@ -0,0 +1,211 @@
+//! Client for the adb `sync:` service, spoken directly over a connection to the adb server.
+//!
+//! After the switch to the device's transport and `sync:` are acknowledged, every request is a four-byte id, a
+//! little-endian `u32` length and a path. `LIST` answers with `DENT` entries up to `DONE`, `STAT`
+//! with mode, size and mtime, `RECV` with `DATA` chunks up to `DONE`, and `SEND` takes `DATA`
+//! chunks followed by `DONE` with the file's mtime. Errors come back as `FAIL` with a message.
+
+use std::{
+    io::{self, Read, Write},
+    net::TcpStream,
+};
+
+use crate::adb::{AdbOptions, send_service};
+
+/// Largest `DATA` chunk the protocol allows.
+const MAX_CHUNK: usize = 64 * 1024;
+const S_IFMT: u32 = 0o170_000;
+const S_IFDIR: u32 = 0o040_000;
+const S_IFLNK: u32 = 0o120_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String,
+    /// Unix mode bits, including the file type.
+    pub mode: u32,
+    pub size: u32,
+}
+
+impl DirEntry {
+    pub const fn is_dir(&self) -> bool {
+        self.mode & S_IFMT == S_IFDIR
+    }
+
+    /// Symlinks such as `/sdcard` are reported as links, not as what they point at.
+    pub const fn is_symlink(&self) -> bool {
+        self.mode & S_IFMT == S_IFLNK
+    }
+}
+
+/// `STAT` result. A `mode` of zero means the path does not exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stat {
+    pub mode: u32,
+    pub size: u32,
+}
+
+pub struct SyncConnection {
+    stream: TcpStream,
+}
+
+impl SyncConnection {
+    /// Connect to the adb server and switch to the sync service of the target device.
+    pub fn connect(adb: &AdbOptions) -> io::Result<Self> {
+        let mut stream = adb.connect_server()?;
+        send_service(&mut stream, &adb.transport_service())?;
+        send_service(&mut stream, "sync:")?;
+        Ok(Self {
+            stream,
+        })
+    }
+
+    fn request(&mut self, id: [u8; 4], payload: &[u8]) -> io::Result<()> {
+        let length = u32::try_from(payload.len()).map_err(|_| io::Error::other("sync request too long"))?;
+        let mut packet = Vec::with_capacity(8 + payload.len());
+        packet.extend_from_slice(&id);
+        packet.extend_from_slice(&length.to_le_bytes());
+        packet.extend_from_slice(payload);
+        self.stream.write_all(&packet)
+    }
+
+    fn read_id(&mut self) -> io::Result<[u8; 4]> {
+        let mut id = [0; 4];
+        self.stream.read_exact(&mut id)?;
+        Ok(id)
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        let mut value = [0; 4];
+        self.stream.read_exact(&mut value)?;
+        Ok(u32::from_le_bytes(value))
+    }
+
+    fn read_bytes(&mut self, length: u32) -> io::Result<Vec<u8>> {
+        let mut bytes = vec![0; length as usize];
+        self.stream.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Read the message following a `FAIL` and turn it into an error.
+    fn fail(&mut self, path: &str) -> io::Error {
+        let message = self.read_u32().and_then(|length| self.read_bytes(length));
+        match message {
+            Ok(message) => io::Error::other(format!("{path}: {}", String::from_utf8_lossy(&message))),
+            Err(err) => err,
+        }
+    }
+
+    fn unexpected(id: [u8; 4]) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unexpected sync response {:?}", String::from_utf8_lossy(&id)),
+        )
+    }
+
+    /// Entries of the directory at `path`, without `.` and `..`. A path that is not a directory
+    /// lists as empty.
+    pub fn list(&mut self, path: &str) -> io::Result<Vec<DirEntry>> {
+        self.request(*b"LIST", path.as_bytes())?;
+        let mut entries = Vec::new();
+        loop {
+            match &self.read_id()? {
+                b"DENT" => {
+                    let (mode, size, _mtime) = (self.read_u32()?, self.read_u32()?, self.read_u32()?);
+                    let length = self.read_u32()?;
+                    let name = String::from_utf8_lossy(&self.read_bytes(length)?).into_owned();
+                    if name != "." && name != ".." {
+                        entries.push(DirEntry {
+                            name,
+                            mode,
+                            size,
+                        });
+                    }
+                }
+                b"DONE" => {
+                    // DONE carries the same fields as DENT, all zero.
+                    self.read_bytes(16)?;
+                    return Ok(entries);
+                }
+                b"FAIL" => return Err(self.fail(path)),
+                &id => return Err(Self::unexpected(id)),
+            }
+        }
+    }
+
+    pub fn stat(&mut self, path: &str) -> io::Result<Stat> {
+        self.request(*b"STAT", path.as_bytes())?;
+        match &self.read_id()? {
+            b"STAT" => {
+                let (mode, size, _mtime) = (self.read_u32()?, self.read_u32()?, self.read_u32()?);
+                Ok(Stat {
+                    mode,
+                    size,
+                })
+            }
+            b"FAIL" => Err(self.fail(path)),
+            &id => Err(Self::unexpected(id)),
+        }
+    }
+
+    /// Download `path` into `output`, calling `progress` with the bytes received so far.
+    pub fn pull(&mut self, path: &str, output: &mut impl Write, mut progress: impl FnMut(u64)) -> io::Result<()> {
+        self.request(*b"RECV", path.as_bytes())?;
+        let mut received = 0;
+        loop {
+            match &self.read_id()? {
+                b"DATA" => {
+                    let length = self.read_u32()?;
+                    output.write_all(&self.read_bytes(length)?)?;
+                    received += u64::from(length);
+                    progress(received);
+                }
+                b"DONE" => {
+                    self.read_u32()?;
+                    return output.flush();
+                }
+                b"FAIL" => return Err(self.fail(path)),
+                &id => return Err(Self::unexpected(id)),
+            }
+        }
+    }
+
+    /// Upload `input` to `path` with the given permission bits and mtime, calling `progress` with
+    /// the bytes sent so far.
+    pub fn push(
+        &mut self,
+        input: &mut impl Read,
+        path: &str,
+        mode: u32,
+        mtime: u32,
+        mut progress: impl FnMut(u64),
+    ) -> io::Result<()> {
+        self.request(*b"SEND", format!("{path},{mode}").as_bytes())?;
+        let mut chunk = vec![0; MAX_CHUNK];
+        let mut sent = 0;
+        loop {
+            let length = input.read(&mut chunk)?;
+            if length == 0 {
+                break;
+            }
+            self.request(*b"DATA", &chunk[..length])?;
+            sent += length as u64;
+            progress(sent);
+        }
+        self.stream.write_all(b"DONE")?;
+        self.stream.write_all(&mtime.to_le_bytes())?;
+        match &self.read_id()? {
+            b"OKAY" => {
+                self.read_u32()?;
+                Ok(())
+            }
+            b"FAIL" => Err(self.fail(path)),
+            &id => Err(Self::unexpected(id)),
+        }
+    }
+}
+
+impl Drop for SyncConnection {
+    fn drop(&mut self) {
+        let _ = self.request(*b"QUIT", &[]);
+    }
+}
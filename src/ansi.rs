@@ -0,0 +1,95 @@
+//! Minimal ANSI escape handling for device output shown in the TUI.
+//!
+//! Only SGR sequences (`ESC [ … m`) are turned into styles: the basic, bright, 256 and true colors
+//! plus bold, dim, italic, underline and reverse. Every other CSI or OSC sequence is dropped so
+//! cursor movement from device tools cannot garble the layout.
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+
+/// Convert one line of text containing ANSI escapes into a styled [`Line`].
+pub fn ansi_line(text: &str) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut style = Style::default();
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\u{1b}' => match chars.next() {
+                Some('[') => {
+                    let mut params = String::new();
+                    let mut end = None;
+                    for c in chars.by_ref() {
+                        if ('\u{40}'..='\u{7e}').contains(&c) {
+                            end = Some(c);
+                            break;
+                        }
+                        params.push(c);
+                    }
+                    if end == Some('m') {
+                        if !current.is_empty() {
+                            spans.push(Span::styled(std::mem::take(&mut current), style));
+                        }
+                        style = apply_sgr(style, &params);
+                    }
+                }
+                // OSC runs until BEL or ST (`ESC \`).
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\u{7}' || (c == '\u{1b}' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            },
+            '\r' => {}
+            '\t' => current.push_str("    "),
+            c if c.is_control() => {}
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        spans.push(Span::styled(current, style));
+    }
+    Line::from(spans)
+}
+
+fn apply_sgr(mut style: Style, params: &str) -> Style {
+    let mut codes = params.split(';').map(|code| code.parse::<u8>().unwrap_or(0));
+    while let Some(code) = codes.next() {
+        style = match code {
+            0 => Style::default(),
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            7 => style.add_modifier(Modifier::REVERSED),
+            22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style.remove_modifier(Modifier::ITALIC),
+            24 => style.remove_modifier(Modifier::UNDERLINED),
+            27 => style.remove_modifier(Modifier::REVERSED),
+            30..=37 => style.fg(Color::Indexed(code - 30)),
+            38 => extended_color(&mut codes).map_or(style, |color| style.fg(color)),
+            39 => style.fg(Color::Reset),
+            40..=47 => style.bg(Color::Indexed(code - 40)),
+            48 => extended_color(&mut codes).map_or(style, |color| style.bg(color)),
+            49 => style.bg(Color::Reset),
+            90..=97 => style.fg(Color::Indexed(code - 90 + 8)),
+            100..=107 => style.bg(Color::Indexed(code - 100 + 8)),
+            _ => style,
+        };
+    }
+    style
+}
+
+/// The color after a `38` or `48`: `5;N` for the 256-color palette or `2;R;G;B`.
+fn extended_color(codes: &mut impl Iterator<Item = u8>) -> Option<Color> {
+    match codes.next()? {
+        5 => Some(Color::Indexed(codes.next()?)),
+        2 => Some(Color::Rgb(codes.next()?, codes.next()?, codes.next()?)),
+        _ => None,
+    }
+}
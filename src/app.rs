@@ -1,478 +1,633 @@
-use std::{
-    path::PathBuf,
-    sync::{
-        Arc,
-        atomic::{AtomicBool, Ordering},
-    },
-    thread,
-    time::Duration,
-};
+use std::{collections::BTreeSet, mem, time::Duration};
 
-use codex_ansi_escape::ansi_escape_line;
-use codex_core::{ConversationManager, config::Config, protocol::TokenUsage};
-use codex_login::AuthManager;
 use color_eyre::eyre::Result;
-use crossterm::{
-    event::{KeyCode, KeyEvent, KeyEventKind},
-    terminal::supports_keyboard_enhancement,
-};
-use ratatui::{style::Stylize, text::Line};
-use tokio::{
-    select,
-    sync::mpsc::{channel, unbounded_channel},
-};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::widgets::ScrollbarState;
+use tokio::{select, sync::mpsc::unbounded_channel};
 use tokio_stream::Stream;
 
-use crate::{app_backtrack::BacktrackState, app_event::AppEvent, app_event_sender::AppEventSender, tui, tui::TuiEvent};
-
-// use crate::{
-//     app_backtrack::BacktrackState, app_event::AppEvent, app_event_sender::AppEventSender, chatwidget::ChatWidget,
-//     file_search::FileSearchManager, pager_overlay::Overlay, tui, tui::TuiEvent,
-// };
-// use uuid::Uuid;
-
-pub(crate) struct App {
-    tick_rate: f64,
-    // pub(crate) server: Arc<ConversationManager>,
-    // pub(crate) app_event_tx: AppEventSender,
-    // pub(crate) chat_widget: ChatWidget,
-
-    // /// Config is stored here so we can recreate ChatWidgets as needed.
-    // pub(crate) config: Config,
-
-    // pub(crate) file_search: FileSearchManager,
-
-    // pub(crate) transcript_lines: Vec<Line<'static>>,
+use crate::{
+    adb::{AdbOptions, Transport},
+    app_event::AppEvent,
+    app_event_sender::AppEventSender,
+    career::RunStore,
+    cli::Cli,
+    device_view::DeviceView,
+    discovery, farm,
+    farm_view::FarmView,
+    files_view::FilesView,
+    incident::Incidents,
+    ocr_review::OcrReview,
+    perf,
+    perf_view::PerfView,
+    port_forward,
+    ports_view::PortsView,
+    run_compare::RunCompareView,
+    screen_view::ScreenView,
+    session::{LogLine, Session},
+    shell_view::ShellView,
+    stack_trace::{self, Trace},
+    thermal,
+    thermal_view::ThermalView,
+    tui,
+    tui::TuiEvent,
+    ui, watchdog,
+};
 
-    // // Pager overlay state (Transcript or Static like Diff)
-    // pub(crate) overlay: Option<Overlay>,
-    // pub(crate) deferred_history_lines: Vec<Line<'static>>,
+pub const LOGS_TAB: usize = 1;
+pub const RUNS_TAB: usize = 2;
+pub const SCREEN_TAB: usize = 3;
+pub const OCR_TAB: usize = 4;
+pub const SHELL_TAB: usize = 5;
+pub const FILES_TAB: usize = 6;
+pub const DEVICE_TAB: usize = 7;
+pub const PERF_TAB: usize = 8;
+pub const THERMAL_TAB: usize = 9;
+pub const PORTS_TAB: usize = 10;
+pub const FARM_TAB: usize = 11;
+
+pub struct TabsState<'a> {
+    pub titles: Vec<&'a str>,
+    pub index: usize,
+}
 
-    // pub(crate) enhanced_keys_supported: bool,
+impl<'a> TabsState<'a> {
+    pub const fn new(titles: Vec<&'a str>) -> Self {
+        Self {
+            titles,
+            index: 0,
+        }
+    }
 
-    // /// Controls the animation thread that sends CommitTick events.
-    // pub(crate) commit_anim_running: Arc<AtomicBool>,
+    pub fn next(&mut self) {
+        self.index = (self.index + 1) % self.titles.len();
+    }
+}
 
-    // // Esc-backtracking state grouped
-    // pub(crate) backtrack: crate::app_backtrack::BacktrackState,
+pub struct App {
+    tick_rate: f64,
+    pub(crate) tabs: TabsState<'static>,
+    pub(crate) runs: RunCompareView,
+    pub(crate) screen: ScreenView,
+    pub(crate) ocr: OcrReview,
+    pub(crate) shell: ShellView,
+    pub(crate) files: FilesView,
+    pub(crate) device: DeviceView,
+    pub(crate) perf: PerfView,
+    pub(crate) thermal: ThermalView,
+    pub(crate) ports: PortsView,
+    pub(crate) farm: FarmView,
+    /// Crashes and ANRs found in the log shown on the LOGS tab.
+    pub(crate) incidents: Incidents,
+    pub(crate) follow_tail: bool,
+    pub(crate) vertical_scroll_state: ScrollbarState,
+    pub(crate) vertical_scroll: usize,
+    /// Index into `logs_buffer` of the line the LOGS tab cursor is on.
+    pub(crate) log_cursor: usize,
+    pub(crate) logs_buffer: Vec<LogLine>,
+    /// Stack traces in `logs_buffer`, kept up to date as lines come and go.
+    pub(crate) traces: Vec<Trace>,
+    /// Header indices into `logs_buffer` of the stack traces unfolded on the LOGS tab.
+    pub(crate) expanded_traces: BTreeSet<usize>,
+    /// Recording of this session; `None` if its directory could not be created.
+    pub(crate) session: Option<Session>,
+    /// The session's own log and device, set aside while a farm instance is shown on the LOGS tab.
+    stashed_session: Option<(Vec<LogLine>, Option<String>)>,
 }
 
 impl App {
-    pub async fn run(tui: &mut tui::Tui, tick_rate: f64) -> Result<()> {
+    // --- Cap the buffer to prevent unbounded memory growth ---
+    const MAX_LOG_LINES: usize = 65536;
+
+    pub async fn run(tui: &mut tui::Tui, cli_args: &Cli) -> Result<()> {
         use tokio_stream::StreamExt;
         let (app_event_tx, mut app_event_rx) = unbounded_channel();
         let app_event_tx = AppEventSender::new(app_event_tx);
 
-        // let chat_widget = ChatWidget::new(
-        //     config.clone(),
-        //     conversation_manager.clone(),
-        //     tui.frame_requester(),
-        //     app_event_tx.clone(),
-        //     initial_prompt,
-        //     initial_images,
-        //     enhanced_keys_supported,
-        // );
-
-        let mut app: App = Self {
-            tick_rate,
+        let mut adb_options = AdbOptions::default();
+        if let Some(address) = cli_args.adbd {
+            adb_options.transport = Transport::Direct {
+                address,
+                key_path: cli_args.adb_key.clone().unwrap_or_else(|| cli_args.data_dir.join("adbkey")),
+            };
+        }
+        let session = Session::create(&cli_args.data_dir)
+            .inspect_err(|err| tracing::warn!("not recording this session: {err}"))
+            .ok();
+        // The session records one device, whatever is picked on the DEVICE tab afterwards.
+        let session_adb = adb_options.pinned();
+        if let Some(session) = &session {
+            session.spawn_logcat(session_adb.clone(), app_event_tx.clone());
+            if cli_args.timeline_interval > 0 {
+                session.spawn_capture(
+                    session_adb.clone(),
+                    app_event_tx.clone(),
+                    Duration::from_secs(cli_args.timeline_interval),
+                );
+            }
+        }
+        if cli_args.perf_interval > 0 {
+            perf::spawn_sampler(session_adb.clone(), app_event_tx.clone(), Duration::from_secs(cli_args.perf_interval));
+        }
+        if cli_args.thermal_interval > 0 {
+            thermal::spawn_sampler(session_adb, app_event_tx.clone(), Duration::from_secs(cli_args.thermal_interval));
+        }
+        // Saved port rules, emulator discovery and the farm are adb server features.
+        if adb_options.transport == Transport::Server {
+            port_forward::spawn_restorer(adb_options.clone(), app_event_tx.clone(), cli_args.data_dir.join("devices"));
+            farm::spawn_tracker(adb_options.clone(), app_event_tx.clone());
+            watchdog::spawn_ticker(app_event_tx.clone());
+            if cli_args.scan_interval > 0 {
+                discovery::spawn_scanner(
+                    adb_options.clone(),
+                    app_event_tx.clone(),
+                    cli_args.scan_ports.clone(),
+                    Some(Duration::from_secs(cli_args.scan_interval)),
+                );
+            }
+        }
+        let recordings_dir =
+            session.as_ref().map_or_else(|| cli_args.data_dir.join("recordings"), Session::recordings_dir);
+        let watchdog_dir = session.as_ref().map_or_else(|| cli_args.data_dir.join("watchdog"), Session::watchdog_dir);
+        let templates_dir = cli_args.templates_dir.clone().unwrap_or_else(|| cli_args.data_dir.join("templates"));
+        let mut app = Self {
+            tick_rate: cli_args.tick_rate,
+            tabs: TabsState::new(vec![
+                "TRAINING", "LOGS", "RUNS", "SCREEN", "OCR", "SHELL", "FILES", "DEVICE", "PERF", "THERMAL", "PORTS",
+                "FARM",
+            ]),
+            runs: RunCompareView::new(RunStore::new(&cli_args.data_dir), cli_args.data_dir.join("exports")),
+            screen: ScreenView::new(
+                adb_options.clone(),
+                app_event_tx.clone(),
+                templates_dir.clone(),
+                cli_args.data_dir.join("macros"),
+                cli_args.record.clone(),
+                recordings_dir,
+            ),
+            ocr: OcrReview::new(cli_args.data_dir.join("ocr_regressions")),
+            shell: ShellView::new(
+                adb_options.clone(),
+                app_event_tx.clone(),
+                cli_args.data_dir.join("shell_commands.json"),
+            ),
+            files: FilesView::new(adb_options.clone(), app_event_tx.clone(), cli_args.data_dir.join("device_files")),
+            device: DeviceView::new(
+                adb_options.clone(),
+                app_event_tx.clone(),
+                cli_args.data_dir.join("devices"),
+                templates_dir,
+                cli_args.scan_ports.clone(),
+            ),
+            perf: PerfView::new(),
+            thermal: ThermalView::new(cli_args.data_dir.join("thermal_thresholds.json")),
+            ports: PortsView::new(adb_options.clone(), app_event_tx.clone(), cli_args.data_dir.join("devices")),
+            farm: FarmView::new(adb_options, app_event_tx, cli_args.data_dir.join("watchdog.json"), watchdog_dir),
+            incidents: Incidents::default(),
+            follow_tail: true,
+            vertical_scroll_state: ScrollbarState::default(),
+            vertical_scroll: 0,
+            log_cursor: 0,
+            expanded_traces: BTreeSet::new(),
+            logs_buffer: Vec::new(),
+            traces: Vec::new(),
+            session,
+            stashed_session: None,
         };
 
+        // Cached per serial, so this is cheap after the first run and puts the device in the
+        // session header early.
+        app.device.load(false);
+
         let tui_events: std::pin::Pin<Box<dyn Stream<Item = TuiEvent> + Send + 'static>> = tui.event_stream();
         tokio::pin!(tui_events);
 
+        tui.frame_requester().schedule_frame();
+
         while select! {
             Some(event) = app_event_rx.recv() => {
-                app.handle_event(tui, event).await?
+                app.handle_event(tui, event)
             }
             Some(event) = tui_events.next() => {
-                app.handle_tui_event(tui, event).await?
+                app.handle_tui_event(tui, event)?
             }
         } {}
         tui.terminal.clear()?;
         Ok(())
     }
 
-    pub(crate) async fn handle_tui_event(&mut self, tui: &mut tui::Tui, event: TuiEvent) -> Result<bool> {
-        if self.overlay.is_some() {
-            let _ = self.handle_backtrack_overlay_event(tui, event).await?;
-        } else {
-            match event {
-                TuiEvent::Key(key_event) => {
-                    self.handle_key_event(tui, key_event).await;
-                }
-                TuiEvent::Paste(pasted) => {
-                    // Many terminals convert newlines to \r when pasting (e.g., iTerm2),
-                    // but tui-textarea expects \n. Normalize CR to LF.
-                    // [tui-textarea]: https://github.com/rhysd/tui-textarea/blob/4d18622eeac13b309e0ff6a55a46ac6706da68cf/src/textarea.rs#L782-L783
-                    // [iTerm2]: https://github.com/gnachman/iTerm2/blob/5d0c0d9f68523cbd0494dad5422998964a2ecd8d/sources/iTermPasteHelper.m#L206-L216
-                    let pasted = pasted.replace("\r", "\n");
-                    self.chat_widget.handle_paste(pasted);
+    pub(crate) fn handle_tui_event(&mut self, tui: &mut tui::Tui, event: TuiEvent) -> Result<bool> {
+        match event {
+            TuiEvent::Key(key_event) => return Ok(self.handle_key_event(tui, key_event)),
+            TuiEvent::Paste(pasted) => {
+                if self.tabs.index == SHELL_TAB {
+                    self.shell.handle_paste(tui, &pasted);
                 }
-                TuiEvent::Draw => {
-                    if self.chat_widget.handle_paste_burst_tick(tui.frame_requester()) {
-                        return Ok(true);
-                    }
-                    tui.draw(self.chat_widget.desired_height(tui.terminal.size()?.width), |frame| {
-                        frame.render_widget_ref(&self.chat_widget, frame.area());
-                        if let Some((x, y)) = self.chat_widget.cursor_pos(frame.area()) {
-                            frame.set_cursor_position((x, y));
-                        }
-                    })?;
+            }
+            TuiEvent::Mouse(mouse_event) => {
+                if self.tabs.index == SCREEN_TAB {
+                    self.screen.handle_mouse_event(tui, mouse_event);
                 }
-                TuiEvent::AttachImage {
-                    path,
-                    width,
-                    height,
-                    format_label,
-                } => {
-                    self.chat_widget.attach_image(path, width, height, format_label);
+            }
+            TuiEvent::Draw => {
+                let height = tui.terminal.size()?.height;
+                tui.draw(height, |frame| ui::render(frame, self))?;
+                if self.tick_rate > 0.0 {
+                    tui.frame_requester().schedule_frame_in(Duration::from_secs_f64(1.0 / self.tick_rate));
                 }
             }
         }
         Ok(true)
     }
 
-    async fn handle_event(&mut self, tui: &mut tui::Tui, event: AppEvent) -> Result<bool> {
+    fn handle_event(&mut self, tui: &tui::Tui, event: AppEvent) -> bool {
         match event {
-            AppEvent::NewSession => {
-                self.chat_widget = ChatWidget::new(
-                    self.config.clone(),
-                    self.server.clone(),
-                    tui.frame_requester(),
-                    self.app_event_tx.clone(),
-                    None,
-                    Vec::new(),
-                    self.enhanced_keys_supported,
-                );
+            AppEvent::ScreenCaptured(result) => {
+                self.screen.on_captured(result);
                 tui.frame_requester().schedule_frame();
             }
-            AppEvent::InsertHistoryLines(lines) => {
-                if let Some(Overlay::Transcript(t)) = &mut self.overlay {
-                    t.insert_lines(lines.clone());
-                    tui.frame_requester().schedule_frame();
-                }
-                self.transcript_lines.extend(lines.clone());
-                if self.overlay.is_some() {
-                    self.deferred_history_lines.extend(lines);
-                } else {
-                    tui.insert_history_lines(lines);
-                }
+            AppEvent::ScreenRefreshDue(generation) => {
+                self.screen.on_refresh_due(generation);
             }
-            AppEvent::InsertHistoryCell(cell) => {
-                let cell_transcript = cell.transcript_lines();
-                if let Some(Overlay::Transcript(t)) = &mut self.overlay {
-                    t.insert_lines(cell_transcript.clone());
-                    tui.frame_requester().schedule_frame();
-                }
-                self.transcript_lines.extend(cell_transcript.clone());
-                let display = cell.display_lines();
-                if !display.is_empty() {
-                    if self.overlay.is_some() {
-                        self.deferred_history_lines.extend(display);
-                    } else {
-                        tui.insert_history_lines(display);
-                    }
+            AppEvent::TemplateMatches {
+                screenshot_id,
+                result,
+            } => {
+                self.screen.on_matches(screenshot_id, result);
+                tui.frame_requester().schedule_frame();
+            }
+            AppEvent::InputSent {
+                action,
+                result,
+            } => {
+                self.screen.on_input_sent(&action, result);
+                tui.frame_requester().schedule_frame();
+            }
+            AppEvent::TouchRanges(ranges) => {
+                self.screen.on_touch_ranges(ranges);
+            }
+            AppEvent::InputEvents(events) => {
+                self.screen.on_input_events(tui, events);
+            }
+            AppEvent::MacroReplayed(result) => {
+                self.screen.on_macro_replayed(result);
+                tui.frame_requester().schedule_frame();
+            }
+            AppEvent::ScreenRecordSegment(result) => {
+                self.screen.on_video_segment(result);
+                tui.frame_requester().schedule_frame();
+            }
+            AppEvent::ScreenRecordFinished(result) => {
+                self.screen.on_video_finished(result);
+                tui.frame_requester().schedule_frame();
+            }
+            AppEvent::ShellOutput {
+                id,
+                lines,
+            } => {
+                self.shell.on_output(id, lines);
+                tui.frame_requester().schedule_frame();
+            }
+            AppEvent::ShellFinished {
+                id,
+                result,
+            } => {
+                self.shell.on_finished(id, result);
+                tui.frame_requester().schedule_frame();
+            }
+            AppEvent::RemoteDirListed {
+                path,
+                result,
+            } => {
+                self.files.on_dir_listed(path, result);
+                tui.frame_requester().schedule_frame();
+            }
+            AppEvent::FilePreview {
+                path,
+                result,
+            } => {
+                self.files.on_preview(path, result);
+                tui.frame_requester().schedule_frame();
+            }
+            AppEvent::FileTransferProgress {
+                done,
+                total,
+            } => {
+                self.files.on_transfer_progress(done, total);
+                tui.frame_requester().schedule_frame();
+            }
+            AppEvent::FileTransferFinished(result) => {
+                self.files.on_transfer_finished(result);
+                tui.frame_requester().schedule_frame();
+            }
+            AppEvent::DeviceInfo(result) => {
+                if let (Ok(info), Some(session)) = (&result, &self.session)
+                    && let Err(err) = session.record_device(info)
+                {
+                    tracing::warn!("failed to write the session device header: {err}");
                 }
+                self.device.on_info(result);
+                tui.frame_requester().schedule_frame();
             }
-            AppEvent::StartCommitAnimation => {
-                if self.commit_anim_running.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_ok()
+            AppEvent::PerfSampled(result) => {
+                if let (Ok(Some(sample)), Some(session)) = (&result, &self.session)
+                    && let Err(err) = session.record_perf(sample)
                 {
-                    let tx = self.app_event_tx.clone();
-                    let running = self.commit_anim_running.clone();
-                    thread::spawn(move || {
-                        while running.load(Ordering::Relaxed) {
-                            thread::sleep(Duration::from_millis(50));
-                            tx.send(AppEvent::CommitTick);
-                        }
-                    });
+                    tracing::warn!("failed to record a perf sample: {err}");
                 }
+                self.perf.on_sample(result);
+                tui.frame_requester().schedule_frame();
             }
-            AppEvent::StopCommitAnimation => {
-                self.commit_anim_running.store(false, Ordering::Release);
+            AppEvent::PortRules(result) => {
+                self.ports.on_rules(result);
+                tui.frame_requester().schedule_frame();
             }
-            AppEvent::CommitTick => {
-                self.chat_widget.on_commit_tick();
+            AppEvent::PortRulesRestored {
+                serial,
+                result,
+            } => {
+                self.ports.on_restored(&serial, result);
+                tui.frame_requester().schedule_frame();
             }
-            AppEvent::CodexEvent(event) => {
-                self.chat_widget.handle_codex_event(event);
+            AppEvent::DevicesScanned(result) => {
+                self.device.on_scan(result);
+                tui.frame_requester().schedule_frame();
             }
-            AppEvent::ConversationHistory(ev) => {
-                self.on_conversation_history_for_backtrack(tui, ev).await?;
+            AppEvent::ThermalSampled(result) => {
+                for event in self.thermal.on_sample(result) {
+                    self.record_event(&event);
+                }
+                tui.frame_requester().schedule_frame();
             }
-            AppEvent::ExitRequest => {
-                return Ok(false);
+            AppEvent::WatchdogTick => {
+                let events = self.farm.on_tick();
+                for event in &events {
+                    self.record_event(event);
+                }
+                if !events.is_empty() {
+                    tui.frame_requester().schedule_frame();
+                }
             }
-            AppEvent::CodexOp(op) => self.chat_widget.submit_op(op),
-            AppEvent::DiffResult(text) => {
-                // Clear the in-progress state in the bottom pane
-                self.chat_widget.on_diff_complete();
-                // Enter alternate screen using TUI helper and build pager lines
-                let _ = tui.enter_alt_screen();
-                let pager_lines: Vec<ratatui::text::Line<'static>> = if text.trim().is_empty() {
-                    vec!["No changes detected.".italic().into()]
-                } else {
-                    text.lines().map(ansi_escape_line).collect()
-                };
-                self.overlay = Some(Overlay::new_static_with_title(pager_lines, "D I F F".to_string()));
+            AppEvent::WatchdogAction {
+                serial,
+                result,
+            } => {
+                let event = self.farm.on_watchdog_action(&serial, result);
+                self.record_event(&event);
+                tui.frame_requester().schedule_frame();
+            }
+            AppEvent::WatchdogFinished(serial) => {
+                let event = self.farm.on_watchdog_finished(&serial);
+                self.record_event(&event);
                 tui.frame_requester().schedule_frame();
             }
-            AppEvent::StartFileSearch(query) => {
-                if !query.is_empty() {
-                    self.file_search.on_user_query(query);
+            AppEvent::LogLine(line) => {
+                // OCR crops and incidents belong to the session's device, even while a farm
+                // instance is shown.
+                self.ocr.on_log_line(&line);
+                let incident = self.incidents.on_log_line(&line);
+                if let Some((logs, _)) = &mut self.stashed_session {
+                    logs.push(line);
+                    if logs.len() > Self::MAX_LOG_LINES {
+                        logs.drain(..logs.len() - Self::MAX_LOG_LINES);
+                    }
+                    if incident {
+                        tui.frame_requester().schedule_frame();
+                    }
+                } else {
+                    self.push_log_line(line);
+                    tui.frame_requester().schedule_frame();
                 }
             }
-            AppEvent::FileSearchResult {
-                query,
-                matches,
+            AppEvent::FarmDevices(result) => {
+                self.farm.on_devices(result);
+                tui.frame_requester().schedule_frame();
+            }
+            AppEvent::FarmLogLine {
+                serial,
+                line,
             } => {
-                self.chat_widget.apply_file_search_result(query, matches);
+                let shown = self.farm.on_log_line(&serial, &line);
+                if shown {
+                    self.push_log_line(line);
+                }
+                if shown || self.tabs.index == FARM_TAB {
+                    tui.frame_requester().schedule_frame();
+                }
             }
-            AppEvent::UpdateReasoningEffort(effort) => {
-                self.chat_widget.set_reasoning_effort(effort);
+            AppEvent::FarmLogEnded {
+                serial,
+                error,
+            } => {
+                self.farm.on_log_ended(&serial, error);
+                tui.frame_requester().schedule_frame();
             }
-            AppEvent::UpdateModel(model) => {
-                self.chat_widget.set_model(model);
+            AppEvent::TimelineFrame(frame) => {
+                if let Some(session) = &mut self.session {
+                    session.push_frame(frame);
+                }
+                tui.frame_requester().schedule_frame();
             }
-            AppEvent::UpdateAskForApprovalPolicy(policy) => {
-                self.chat_widget.set_approval_policy(policy);
+        }
+        true
+    }
+
+    fn push_log_line(&mut self, line: LogLine) {
+        self.logs_buffer.push(line);
+        stack_trace::extend_traces(&mut self.traces, &self.logs_buffer, self.logs_buffer.len() - 1);
+        if self.logs_buffer.len() > Self::MAX_LOG_LINES {
+            let overflow = self.logs_buffer.len() - Self::MAX_LOG_LINES;
+            self.logs_buffer.drain(..overflow);
+            stack_trace::shift_traces(&mut self.traces, overflow);
+            self.vertical_scroll = self.vertical_scroll.saturating_sub(overflow);
+            self.log_cursor = self.log_cursor.saturating_sub(overflow);
+            self.expanded_traces = mem::take(&mut self.expanded_traces)
+                .into_iter()
+                .filter_map(|header| header.checked_sub(overflow))
+                .collect();
+        }
+        if self.follow_tail {
+            self.log_cursor = self.logs_buffer.len().saturating_sub(1);
+        }
+    }
+
+    /// Log a notable event and append it to the session's `events.log`.
+    fn record_event(&self, event: &str) {
+        tracing::warn!("{event}");
+        if let Some(session) = &self.session
+            && let Err(err) = session.record_event(event)
+        {
+            tracing::warn!("failed to record a session event: {err}");
+        }
+    }
+
+    /// Show the log of farm instance `serial` on the LOGS and TRAINING tabs, and make it the
+    /// device the other tabs talk to. `None` goes back to the session's own log and device.
+    fn show_farm_instance(&mut self, serial: Option<String>) {
+        let (logs, device) = if let Some(serial) = serial {
+            if self.stashed_session.is_none() {
+                self.stashed_session = Some((mem::take(&mut self.logs_buffer), self.device.serial()));
             }
-            AppEvent::UpdateSandboxPolicy(policy) => {
-                self.chat_widget.set_sandbox_policy(policy);
+            (self.farm.backlog(&serial), Some(serial))
+        } else if let Some(stashed) = self.stashed_session.take() {
+            stashed
+        } else {
+            return;
+        };
+        self.logs_buffer = logs;
+        self.traces = stack_trace::find_traces(&self.logs_buffer);
+        self.follow_tail = true;
+        self.log_cursor = self.logs_buffer.len().saturating_sub(1);
+        self.vertical_scroll = 0;
+        self.expanded_traces.clear();
+        self.device.select(device);
+    }
+
+    /// Move the log cursor by `delta` shown lines, skipping the frames of folded stack traces.
+    /// Moving stops following the tail until the cursor is brought back to the last line.
+    fn move_log_cursor(&mut self, delta: isize) {
+        let shown = stack_trace::visible_lines(self.logs_buffer.len(), &self.traces, &self.expanded_traces);
+        let last = shown.len().saturating_sub(1);
+        let row = shown.partition_point(|&index| index <= self.log_cursor).saturating_sub(1);
+        let row = row.saturating_add_signed(delta).min(last);
+        self.log_cursor = shown.get(row).copied().unwrap_or_default();
+        self.follow_tail = row == last;
+    }
+
+    /// Fold or unfold the stack trace the log cursor is on.
+    fn toggle_trace(&mut self) {
+        let Some(trace) = stack_trace::trace_at(&self.traces, self.log_cursor).cloned() else {
+            return;
+        };
+        if self.expanded_traces.remove(&trace.header) {
+            // The cursor must not stay on a frame that is now hidden.
+            if self.log_cursor > trace.frames.start {
+                self.log_cursor = trace.header;
+                self.follow_tail = false;
             }
+        } else {
+            self.expanded_traces.insert(trace.header);
         }
-        Ok(true)
     }
 
-    async fn handle_key_event(&mut self, tui: &mut tui::Tui, key_event: KeyEvent) {
+    fn handle_logs_key_event(&mut self, tui: &tui::Tui, key_event: KeyEvent) {
+        const PAGE: isize = 20;
+        if self.incidents.is_pager_open() {
+            self.incidents.handle_pager_key_event(tui, key_event);
+            return;
+        }
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => self.move_log_cursor(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_log_cursor(1),
+            KeyCode::PageUp => self.move_log_cursor(-PAGE),
+            KeyCode::PageDown => self.move_log_cursor(PAGE),
+            KeyCode::Home | KeyCode::Char('g') => self.move_log_cursor(isize::MIN),
+            KeyCode::End | KeyCode::Char('G') => self.move_log_cursor(isize::MAX),
+            KeyCode::Enter => self.toggle_trace(),
+            KeyCode::Char('i') => self.incidents.open_pager(),
+            KeyCode::Char('x') => self.incidents.dismiss(),
+            _ => return,
+        }
+        tui.frame_requester().schedule_frame();
+    }
+
+    /// Handle a key press. Returns `false` when the app should quit.
+    fn handle_key_event(&mut self, tui: &tui::Tui, key_event: KeyEvent) -> bool {
         match key_event {
             KeyEvent {
-                code: KeyCode::Char('t'),
-                modifiers: crossterm::event::KeyModifiers::CONTROL,
+                code: KeyCode::Char('q'),
+                modifiers: KeyModifiers::CONTROL,
+                kind: KeyEventKind::Press,
+                ..
+            } => return false,
+            KeyEvent {
+                code: KeyCode::Tab,
                 kind: KeyEventKind::Press,
                 ..
             } => {
-                // Enter alternate screen and set viewport to full size.
-                let _ = tui.enter_alt_screen();
-                self.overlay = Some(Overlay::new_transcript(self.transcript_lines.clone()));
+                self.tabs.next();
+                if self.tabs.index == SCREEN_TAB {
+                    self.screen.on_shown();
+                } else if self.tabs.index == FILES_TAB {
+                    self.files.on_shown();
+                } else if self.tabs.index == PORTS_TAB {
+                    self.ports.on_shown();
+                }
                 tui.frame_requester().schedule_frame();
             }
-            // Esc primes/advances backtracking only in normal (not working) mode
-            // with an empty composer. In any other state, forward Esc so the
-            // active UI (e.g. status indicator, modals, popups) handles it.
             KeyEvent {
-                code: KeyCode::Esc,
                 kind: KeyEventKind::Press | KeyEventKind::Repeat,
                 ..
-            } => {
-                if self.chat_widget.is_normal_backtrack_mode() && self.chat_widget.composer_is_empty() {
-                    self.handle_backtrack_esc_key(tui);
-                } else {
-                    self.chat_widget.handle_key_event(key_event);
-                }
+            } if self.tabs.index == LOGS_TAB => {
+                self.handle_logs_key_event(tui, key_event);
             }
-            // Enter confirms backtrack when primed + count > 0. Otherwise pass to widget.
             KeyEvent {
-                code: KeyCode::Enter,
-                kind: KeyEventKind::Press,
+                kind: KeyEventKind::Press | KeyEventKind::Repeat,
                 ..
-            } if self.backtrack.primed && self.backtrack.count > 0 && self.chat_widget.composer_is_empty() => {
-                // Delegate to helper for clarity; preserves behavior.
-                self.confirm_backtrack_from_main();
+            } if self.tabs.index == RUNS_TAB => {
+                self.runs.handle_key_event(tui, key_event);
             }
             KeyEvent {
                 kind: KeyEventKind::Press | KeyEventKind::Repeat,
                 ..
-            } => {
-                // Any non-Esc key press should cancel a primed backtrack.
-                // This avoids stale "Esc-primed" state after the user starts typing
-                // (even if they later backspace to empty).
-                if key_event.code != KeyCode::Esc && self.backtrack.primed {
-                    self.reset_backtrack_state();
+            } if self.tabs.index == SCREEN_TAB => {
+                self.screen.handle_key_event(tui, key_event);
+            }
+            KeyEvent {
+                kind: KeyEventKind::Press | KeyEventKind::Repeat,
+                ..
+            } if self.tabs.index == OCR_TAB => {
+                self.ocr.handle_key_event(tui, key_event, self.session.as_mut());
+            }
+            KeyEvent {
+                kind: KeyEventKind::Press | KeyEventKind::Repeat,
+                ..
+            } if self.tabs.index == SHELL_TAB => {
+                self.shell.handle_key_event(tui, key_event);
+            }
+            KeyEvent {
+                kind: KeyEventKind::Press | KeyEventKind::Repeat,
+                ..
+            } if self.tabs.index == FILES_TAB => {
+                self.files.handle_key_event(tui, key_event);
+            }
+            KeyEvent {
+                kind: KeyEventKind::Press | KeyEventKind::Repeat,
+                ..
+            } if self.tabs.index == DEVICE_TAB => {
+                self.device.handle_key_event(tui, key_event);
+            }
+            KeyEvent {
+                kind: KeyEventKind::Press | KeyEventKind::Repeat,
+                ..
+            } if self.tabs.index == PERF_TAB => {
+                self.perf.handle_key_event(tui, key_event);
+            }
+            KeyEvent {
+                kind: KeyEventKind::Press | KeyEventKind::Repeat,
+                ..
+            } if self.tabs.index == THERMAL_TAB => {
+                self.thermal.handle_key_event(tui, key_event);
+            }
+            KeyEvent {
+                kind: KeyEventKind::Press | KeyEventKind::Repeat,
+                ..
+            } if self.tabs.index == PORTS_TAB => {
+                self.ports.handle_key_event(tui, key_event);
+            }
+            KeyEvent {
+                kind: KeyEventKind::Press | KeyEventKind::Repeat,
+                ..
+            } if self.tabs.index == FARM_TAB => {
+                let changed = self.farm.handle_key_event(tui, key_event);
+                if changed {
+                    self.show_farm_instance(self.farm.shown().map(ToString::to_string));
                 }
-                self.chat_widget.handle_key_event(key_event);
             }
             _ => {
                 // Ignore Release key events.
             }
-        };
+        }
+        true
     }
 }
-
-// use std::{net::Ipv4Addr, sync::mpsc};
-
-// use adb_client::{ADBServer, ADBServerDevice};
-// use ratatui::{text::Line, widgets::ScrollbarState};
-
-// use crate::adb::AdbOptions;
-
-// // This struct will implement `std::io::Write`. It will take log data as bytes,
-// // buffer it into lines, and send each complete line over an MPSC channel.
-// struct ChannelWriter {
-//     sender: mpsc::Sender<String>,
-//     buffer: Vec<u8>,
-// }
-
-// impl ChannelWriter {
-//     fn new(sender: mpsc::Sender<String>) -> Self {
-//         Self {
-//             sender,
-//             buffer: Vec::new(),
-//         }
-//     }
-// }
-
-// impl std::io::Write for ChannelWriter {
-//     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-//         self.buffer.extend_from_slice(buf);
-//         // Process all complete lines found in the buffer.
-//         while let Some(i) = self.buffer.iter().position(|&b| b == b'\n') {
-//             let line_bytes = self.buffer.drain(..=i).collect::<Vec<u8>>();
-//             // Attempt to convert to string and send.
-//             if let Ok(line) = String::from_utf8(line_bytes) {
-//                 // If the receiver is dropped, the app is closing. Signal this by
-//                 // returning a BrokenPipe error, which will stop the `get_logs` call.
-//                 if self.sender.send(line.trim_end().to_string()).is_err() {
-//                     return Err(std::io::Error::new(
-//                         std::io::ErrorKind::BrokenPipe,
-//                         "Channel receiver has been dropped.",
-//                     ));
-//                 }
-//             }
-//             // Non-UTF8 lines are silently ignored.
-//         }
-//         Ok(buf.len())
-//     }
-
-//     fn flush(&mut self) -> std::io::Result<()> {
-//         // No-op, as we send data as soon as a full line is received.
-//         Ok(())
-//     }
-// }
-
-// pub struct TabsState<'a> {
-//     pub titles: Vec<&'a str>,
-//     pub index: usize,
-// }
-
-// impl<'a> TabsState<'a> {
-//     pub const fn new(titles: Vec<&'a str>) -> Self {
-//         Self {
-//             titles,
-//             index: 0,
-//         }
-//     }
-
-//     pub fn next(&mut self) {
-//         self.index = (self.index + 1) % self.titles.len();
-//     }
-// }
-
-// pub struct App<'a> {
-//     pub should_quit: bool,
-//     pub adb_options: AdbOptions,
-//     pub follow_tail: bool,
-//     pub tabs: TabsState<'a>,
-//     pub vertical_scroll_state: ScrollbarState,
-//     pub vertical_scroll: usize,
-//     // Buffer owns its data with a 'static lifetime.
-//     pub logs_buffer: Vec<Line<'static>>,
-//     log_receiver: Option<mpsc::Receiver<String>>,
-// }
-
-// impl App<'_> {
-//     // --- Cap the buffer to prevent unbounded memory growth ---
-//     const MAX_LOG_LINES: usize = 65536;
-
-//     pub fn new() -> Self {
-//         App {
-//             should_quit: false,
-//             adb_options: AdbOptions::default(),
-//             follow_tail: true,
-//             tabs: TabsState::new(vec!["TRAINING", "LOGS"]),
-//             vertical_scroll_state: ScrollbarState::default(),
-//             vertical_scroll: 0,
-//             logs_buffer: Vec::new(),
-//             log_receiver: None,
-//         }
-//     }
-
-//     pub fn setup_adb(&mut self) {
-//         let server_address_ip: &Ipv4Addr = self.adb_options.address.ip();
-//         if server_address_ip.is_loopback() || server_address_ip.is_unspecified() {
-//             ADBServer::start(&std::collections::HashMap::default(), &None);
-//         }
-
-//         // --- Spawn the dedicated log-fetching thread ---
-//         let (tx, rx) = mpsc::channel();
-//         self.log_receiver = Some(rx);
-
-//         let adb_addr = self.adb_options.address;
-
-//         std::thread::spawn(move || {
-//             // Create a new device instance for this thread to avoid sharing state.
-//             let mut log_device = ADBServerDevice::autodetect(Some(adb_addr));
-//             let writer = ChannelWriter::new(tx);
-
-//             // This call blocks until the device disconnects or the writer returns an error.
-//             if let Err(e) = log_device.get_logs(writer) {
-//                 // You can log this error to a file for debugging.
-//                 eprintln!("ADB logcat thread exited with error: {e:?}");
-//             }
-//         });
-//     }
-
-//     pub fn on_tick(&mut self) {
-//         // --- Process incoming logs from the channel ---
-//         // let mut number_received_lines: usize = 0;
-//         // let mut logs_buffer_len: usize = 0;
-//         // let mut logs_buffer_len: usize = self.logs_buffer.len();
-//         if let Some(rx) = &self.log_receiver {
-//             // Drain the channel of all pending messages without blocking.
-//             while let Ok(log_line) = rx.try_recv() {
-//                 self.logs_buffer.push(Line::from(log_line));
-//                 // number_received_lines = number_received_lines.saturating_add(1);
-//                 // self.vertical_scroll = self.vertical_scroll.saturating_add(1);
-//             }
-
-//             // logs_buffer_len = self.logs_buffer.len();
-//             // logs_buffer_len = logs_buffer_len.saturating_add(number_received_lines);
-
-//             // if self.logs_buffer.len() > Self::MAX_LOG_LINES {
-//             //     let overflow_to_remove = self.logs_buffer.len() - Self::MAX_LOG_LINES;
-//             //     self.logs_buffer.drain(0..overflow_to_remove);
-
-//             //     self.vertical_scroll = self.vertical_scroll.saturating_sub(overflow_to_remove) +
-//             // number_received_lines; }
-//         }
-
-//         // self.vertical_scroll = self.vertical_scroll.saturating_add(self.logs_buffer.len());
-//         // self.vertical_scroll_state = self.vertical_scroll_state.content_length(self.logs_buffer.len());
-//         // self.vertical_scroll_state = self
-//         // .vertical_scroll_state
-//         // .content_length(self.logs_buffer.len())
-//         // .viewport_content_length(6)
-//         // .position(self.vertical_scroll);
-//     }
-
-//     pub fn on_right(&mut self) {
-//         self.tabs.next();
-//     }
-
-//     pub fn scroll_down(&mut self) {
-//         self.vertical_scroll = self.vertical_scroll.saturating_add(1);
-//         self.vertical_scroll_state = self.vertical_scroll_state.position(self.vertical_scroll);
-//         self.follow_tail = false;
-//     }
-
-//     pub fn scroll_up(&mut self) {
-//         self.vertical_scroll = self.vertical_scroll.saturating_sub(1);
-//         self.vertical_scroll_state = self.vertical_scroll_state.position(self.vertical_scroll);
-//         self.follow_tail = false;
-//     }
-
-//     pub fn on_key(&mut self, c: char) {
-//         if c == 'q' {
-//             self.should_quit = true;
-//         }
-//     }
-// }
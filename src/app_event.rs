@@ -0,0 +1,144 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use crate::{
+    adb_sync::DirEntry,
+    device_info::DeviceInfo,
+    discovery::DeviceScan,
+    getevent::InputEvent,
+    input::InputAction,
+    matching::MatchResult,
+    perf::PerfSample,
+    port_forward::PortRules,
+    screen_record::RecordedSegment,
+    screenshot::Screenshot,
+    session::{LogLine, TimelineFrame},
+    thermal::ThermalSample,
+};
+
+/// Events produced by background workers and delivered to [`crate::app::App`] on the UI task.
+#[derive(Debug)]
+pub enum AppEvent {
+    /// A `screencap` request finished, successfully or not.
+    ScreenCaptured(Result<Screenshot, String>),
+
+    /// The auto-refresh timer armed for the given generation elapsed. Stale generations are
+    /// ignored so changing the interval does not leave old timers firing.
+    ScreenRefreshDue(u64),
+
+    /// Template matching against the screenshot with the given id finished.
+    TemplateMatches {
+        screenshot_id: u32,
+        result: Result<Vec<MatchResult>, String>,
+    },
+
+    /// An injected tap, swipe, key or text went through, or failed.
+    InputSent {
+        action: InputAction,
+        result: Result<(), String>,
+    },
+
+    /// Touch coordinate ranges per input device from `getevent -lp`, sent when a stream starts.
+    TouchRanges(HashMap<String, (u32, u32)>),
+
+    /// A batch of raw input events from a running `getevent` stream.
+    InputEvents(Vec<InputEvent>),
+
+    /// Replaying a touch macro finished, with the path of the macro on success.
+    MacroReplayed(Result<PathBuf, String>),
+
+    /// A screen recording segment was pulled into the session directory.
+    ScreenRecordSegment(Result<RecordedSegment, String>),
+
+    /// A screen recording ended, with the number of segments saved.
+    ScreenRecordFinished(Result<usize, String>),
+
+    /// Output lines of the SHELL tab command with the given id.
+    ShellOutput {
+        id: u64,
+        lines: Vec<String>,
+    },
+
+    /// The SHELL tab command with the given id exited, with its exit code if the device reported one.
+    ShellFinished {
+        id: u64,
+        result: Result<Option<u8>, String>,
+    },
+
+    /// A FILES tab listing finished.
+    RemoteDirListed {
+        path: String,
+        result: Result<Vec<DirEntry>, String>,
+    },
+
+    /// A device file requested for preview was read.
+    FilePreview {
+        path: String,
+        result: Result<Vec<u8>, String>,
+    },
+
+    /// Bytes copied so far by the running pull or push, out of `total`.
+    FileTransferProgress {
+        done: u64,
+        total: u64,
+    },
+
+    /// A pull or push finished, with a summary on success.
+    FileTransferFinished(Result<String, String>),
+
+    /// The device description was loaded, from the cache or the device.
+    DeviceInfo(Result<Box<DeviceInfo>, String>),
+
+    /// A performance sample of the game process; `None` when the game is not running.
+    PerfSampled(Result<Option<PerfSample>, String>),
+
+    /// A battery and thermal sample of the device.
+    ThermalSampled(Result<Box<ThermalSample>, String>),
+
+    /// The forward and reverse rules of the device, after a change if one was requested.
+    PortRules(Result<PortRules, String>),
+
+    /// Saved forward and reverse rules were set up again on a device that came online, with the
+    /// number of rules restored.
+    PortRulesRestored {
+        serial: String,
+        result: Result<usize, String>,
+    },
+
+    /// A device scan finished: the devices the adb server lists and the emulator instances found
+    /// on local ports.
+    DevicesScanned(Result<Box<DeviceScan>, String>),
+
+    /// The devices the adb server knows and their states, whenever that list changes.
+    FarmDevices(Result<Vec<(String, String)>, String>),
+
+    /// A logcat line arrived from one of the devices on the FARM tab.
+    FarmLogLine {
+        serial: String,
+        line: LogLine,
+    },
+
+    /// The log stream of a device on the FARM tab ended; it is restarted while the device is
+    /// online.
+    FarmLogEnded {
+        serial: String,
+        error: String,
+    },
+
+    /// Time to look for farm instances whose bot is stuck.
+    WatchdogTick,
+
+    /// A step of the watchdog's remediation chain ran on a farm instance.
+    WatchdogAction {
+        serial: String,
+        result: Result<String, String>,
+    },
+
+    /// The watchdog's remediation chain on a farm instance ended.
+    WatchdogFinished(String),
+
+    /// A logcat line arrived from the device.
+    LogLine(LogLine),
+
+    /// A periodic session screenshot was written to disk.
+    TimelineFrame(TimelineFrame),
+}
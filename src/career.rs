@@ -0,0 +1,183 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// The trainable stats plus skill points, as reported by the bot at the end of a turn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stats {
+    pub speed: u32,
+    pub stamina: u32,
+    pub power: u32,
+    pub guts: u32,
+    pub wit: u32,
+    #[serde(default)]
+    pub skill_points: u32,
+}
+
+impl Stats {
+    pub const fn get(&self, kind: StatKind) -> u32 {
+        match kind {
+            StatKind::Speed => self.speed,
+            StatKind::Stamina => self.stamina,
+            StatKind::Power => self.power,
+            StatKind::Guts => self.guts,
+            StatKind::Wit => self.wit,
+            StatKind::SkillPoints => self.skill_points,
+        }
+    }
+
+    /// Sum of the five trainable stats. Skill points are not included.
+    pub const fn total(&self) -> u32 {
+        self.speed + self.stamina + self.power + self.guts + self.wit
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatKind {
+    Speed,
+    Stamina,
+    Power,
+    Guts,
+    Wit,
+    SkillPoints,
+}
+
+impl StatKind {
+    pub const ALL: [Self; 6] = [Self::Speed, Self::Stamina, Self::Power, Self::Guts, Self::Wit, Self::SkillPoints];
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Speed => "Speed",
+            Self::Stamina => "Stamina",
+            Self::Power => "Power",
+            Self::Guts => "Guts",
+            Self::Wit => "Wit",
+            Self::SkillPoints => "Skill Pts",
+        }
+    }
+
+    /// Parse the field name used for the stat in logs and JSON, e.g. `skill_points`.
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "speed" => Some(Self::Speed),
+            "stamina" => Some(Self::Stamina),
+            "power" => Some(Self::Power),
+            "guts" => Some(Self::Guts),
+            "wit" => Some(Self::Wit),
+            "skill_points" => Some(Self::SkillPoints),
+            _ => None,
+        }
+    }
+
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Speed => Self::Stamina,
+            Self::Stamina => Self::Power,
+            Self::Power => Self::Guts,
+            Self::Guts => Self::Wit,
+            Self::Wit => Self::SkillPoints,
+            Self::SkillPoints => Self::Speed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Race {
+    pub name: String,
+    pub placement: u32,
+}
+
+/// A single career turn: what the bot decided and where the stats ended up.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Turn {
+    #[serde(rename = "turn")]
+    pub number: u32,
+    pub decision: String,
+    pub stats: Stats,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub race: Option<Race>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CareerRun {
+    pub id: String,
+    pub trainee: String,
+    /// Unix timestamp (seconds) of the first turn.
+    pub started_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bot_version: Option<String>,
+    pub turns: Vec<Turn>,
+}
+
+impl CareerRun {
+    pub fn final_stats(&self) -> Stats {
+        self.turns.last().map(|t| t.stats).unwrap_or_default()
+    }
+}
+
+/// Career runs persisted as one JSON document per run under `<data dir>/runs`.
+#[derive(Debug, Clone)]
+pub struct RunStore {
+    dir: PathBuf,
+}
+
+impl RunStore {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            dir: data_dir.join("runs"),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Load every run in the store, newest first.
+    ///
+    /// A missing store directory is treated as an empty history. Files that fail to parse are
+    /// skipped with a warning so one corrupt run does not hide the rest.
+    pub fn load_all(&self) -> io::Result<Vec<CareerRun>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        let mut runs = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            match Self::read(&path) {
+                Ok(run) => runs.push(run),
+                Err(err) => tracing::warn!("skipping career run {}: {err}", path.display()),
+            }
+        }
+        runs.sort_by(|a, b| b.started_at.cmp(&a.started_at).then_with(|| b.id.cmp(&a.id)));
+        Ok(runs)
+    }
+
+    /// Load the run with the given `id`. Runs are saved as `<id>.json`, but a renamed or copied
+    /// file still counts by the `id` inside it.
+    pub fn load(&self, id: &str) -> io::Result<CareerRun> {
+        if let Ok(run) = Self::read(&self.dir.join(format!("{id}.json")))
+            && run.id == id
+        {
+            return Ok(run);
+        }
+        self.load_all()?
+            .into_iter()
+            .find(|run| run.id == id)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no run with id {id}")))
+    }
+
+    fn read(path: &Path) -> io::Result<CareerRun> {
+        let file = fs::File::open(path)?;
+        serde_json::from_reader(io::BufReader::new(file)).map_err(io::Error::other)
+    }
+}
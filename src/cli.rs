@@ -1,4 +1,11 @@
-use clap::Parser;
+use std::{net::SocketAddr, path::PathBuf, str::FromStr};
+
+use clap::{Args, Parser, Subcommand};
+
+use crate::{
+    discovery::PortRanges, export::ExportFormat, matching::DEFAULT_THRESHOLD, terminal_graphics::GraphicsProtocol,
+    touch_macro::ReplayVia,
+};
 
 #[derive(Debug, Parser)]
 #[command(author, version = version(), about)]
@@ -6,6 +13,192 @@ pub struct Cli {
     /// Tick rate, i.e. number of ticks per second
     #[arg(short, long, value_name = "FLOAT", default_value_t = 4.0)]
     pub tick_rate: f64,
+
+    /// Directory holding recorded career runs and exports
+    #[arg(long, value_name = "PATH", default_value_os_t = default_data_dir())]
+    pub data_dir: PathBuf,
+
+    /// Terminal graphics protocol used to show device screenshots; detected from the environment
+    /// when omitted
+    #[arg(long, value_enum, value_name = "PROTOCOL")]
+    pub graphics: Option<GraphicsProtocol>,
+
+    /// Seconds between session screenshots shown next to the logs; 0 disables them
+    #[arg(long, value_name = "SECS", default_value_t = 5)]
+    pub timeline_interval: u64,
+
+    /// Seconds between CPU, memory and frame samples of the game shown on the PERF tab; 0
+    /// disables them
+    #[arg(long, value_name = "SECS", default_value_t = 10)]
+    pub perf_interval: u64,
+
+    /// Seconds between battery and temperature samples shown on the THERMAL tab; 0 disables them
+    #[arg(long, value_name = "SECS", default_value_t = 30)]
+    pub thermal_interval: u64,
+
+    /// Talk straight to the adbd listening at this `IP:PORT`, such as a device in `adb tcpip`
+    /// mode, instead of going through the adb server. Logs and shell work; file transfers, port
+    /// rules and the device picker need the server
+    #[arg(long, value_name = "ADDRESS")]
+    pub adbd: Option<SocketAddr>,
+
+    /// RSA key the adbd given with `--adbd` is authenticated with, created on first use; defaults
+    /// to `adbkey` in the data directory. `~/.android/adbkey` reuses the key of the regular adb
+    #[arg(long, value_name = "PATH")]
+    pub adb_key: Option<PathBuf>,
+
+    /// Local ports probed for emulator adb daemons, which are connected and offered in the
+    /// device picker
+    #[arg(
+        long,
+        value_name = "RANGES",
+        default_value = "5555-5595,7555,16384-16800,62001,62025-62060",
+        value_parser = PortRanges::from_str
+    )]
+    pub scan_ports: PortRanges,
+
+    /// Seconds between scans of those ports; 0 only scans when the device picker asks
+    #[arg(long, value_name = "SECS", default_value_t = 30)]
+    pub scan_interval: u64,
+
+    /// Directory templates cropped on the SCREEN tab are saved to; defaults to `templates` in the
+    /// data directory
+    #[arg(long, value_name = "PATH")]
+    pub templates_dir: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub record: RecordArgs,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Options of screen recordings started with `V` on the SCREEN tab.
+#[derive(Debug, Clone, Args)]
+#[command(next_help_heading = "Screen recording")]
+pub struct RecordArgs {
+    /// Video bit rate in bits per second; the device default when omitted
+    #[arg(long = "record-bit-rate", value_name = "BPS")]
+    pub bit_rate: Option<u32>,
+
+    /// Video size such as `1280x720`; the screen resolution when omitted
+    #[arg(long = "record-size", value_name = "WxH", value_parser = parse_size)]
+    pub size: Option<(u32, u32)>,
+
+    /// Length of each recorded segment in seconds; `screenrecord` stops on its own after 180, so
+    /// longer recordings are chained from several files
+    #[arg(
+        long = "record-segment",
+        value_name = "SECS",
+        default_value_t = 180,
+        value_parser = clap::value_parser!(u32).range(1..=180)
+    )]
+    pub segment_secs: u32,
+
+    /// Stop recording after this many seconds; runs until stopped when omitted
+    #[arg(long = "record-limit", value_name = "SECS")]
+    pub limit_secs: Option<u64>,
+}
+
+fn parse_size(text: &str) -> Result<(u32, u32), String> {
+    let parse = |text: Option<&str>| text.and_then(|n| n.trim().parse().ok()).filter(|&n| n > 0);
+    let mut parts = text.split(['x', 'X']);
+    match (parse(parts.next()), parse(parts.next()), parts.next()) {
+        (Some(width), Some(height), None) => Ok((width, height)),
+        _ => Err(format!("expected WIDTHxHEIGHT, got {text:?}")),
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Export recorded career runs to CSV or JSONL without starting the TUI
+    Export(ExportArgs),
+    /// Run a template set against screenshot PNGs and print the best match of every template as
+    /// JSON lines
+    Match(MatchArgs),
+    /// Record touch input from the device into a macro file, or replay one
+    Macro(MacroArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// Output format of the turns, races and events tables
+    #[arg(short, long, value_enum, default_value_t = ExportFormat::Csv)]
+    pub format: ExportFormat,
+
+    /// Directory the tables are written to
+    #[arg(short, long, value_name = "DIR")]
+    pub out: PathBuf,
+
+    /// Ids of the runs to export; exports every recorded run when omitted
+    #[arg(value_name = "RUN_ID")]
+    pub runs: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct MatchArgs {
+    /// Directory holding the template set (`NAME.png` plus optional `NAME.json`)
+    #[arg(short, long, value_name = "DIR")]
+    pub templates: PathBuf,
+
+    /// Score at or above which a template counts as matched
+    #[arg(long, value_name = "SCORE", default_value_t = DEFAULT_THRESHOLD)]
+    pub threshold: f32,
+
+    /// Screenshots to match against, e.g. from a recorded session
+    #[arg(value_name = "PNG", required = true)]
+    pub screenshots: Vec<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct MacroArgs {
+    #[command(subcommand)]
+    pub command: MacroCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MacroCommand {
+    /// Record `getevent` input until Enter is pressed or the duration elapses
+    Record {
+        /// Macro file to write
+        #[arg(value_name = "FILE")]
+        out: PathBuf,
+
+        /// Stop after this many seconds instead of waiting for Enter
+        #[arg(long, value_name = "SECS")]
+        duration: Option<u64>,
+    },
+    /// Replay a recorded macro on the device
+    Replay {
+        /// Macro file to replay
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Playback speed relative to the recording, e.g. 2 for twice as fast
+        #[arg(long, value_name = "FACTOR", default_value_t = 1.0)]
+        speed: f64,
+
+        /// How to inject the recorded input
+        #[arg(long, value_enum, default_value_t = ReplayVia::Sendevent)]
+        via: ReplayVia,
+    },
+}
+
+/// `$XDG_DATA_HOME/uma-auto-debug`, falling back to `~/.local/share/uma-auto-debug` (or
+/// `%APPDATA%\uma-auto-debug` on Windows) and finally to the current directory.
+fn default_data_dir() -> PathBuf {
+    const APP_DIR: &str = "uma-auto-debug";
+    if let Some(dir) = std::env::var_os("XDG_DATA_HOME").filter(|d| !d.is_empty()) {
+        return PathBuf::from(dir).join(APP_DIR);
+    }
+    #[cfg(windows)]
+    if let Some(dir) = std::env::var_os("APPDATA") {
+        return PathBuf::from(dir).join(APP_DIR);
+    }
+    if let Some(home) = std::env::var_os("HOME") {
+        return PathBuf::from(home).join(".local").join("share").join(APP_DIR);
+    }
+    PathBuf::from(APP_DIR)
 }
 
 const VERSION_MESSAGE: &str =
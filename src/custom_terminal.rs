@@ -30,6 +30,8 @@ use ratatui::{
     widgets::{StatefulWidget, StatefulWidgetRef, Widget, WidgetRef},
 };
 
+use crate::terminal_graphics::{self, FrameImage, GraphicsProtocol, ImageUpdates};
+
 #[derive(Debug, Hash)]
 pub struct Frame<'a> {
     /// Where should the cursor be after drawing this frame?
@@ -46,6 +48,9 @@ pub struct Frame<'a> {
 
     /// The frame count indicating the sequence number of this frame.
     pub(crate) count: usize,
+
+    /// Images placed with [`Frame::render_image`], emitted after the cell diff has been flushed.
+    pub(crate) images: Vec<FrameImage>,
 }
 
 #[allow(dead_code)]
@@ -126,6 +131,24 @@ impl Frame<'_> {
         self.cursor_position = Some(position.into());
     }
 
+    /// Place an image over `image.area` using the session's terminal graphics protocol.
+    ///
+    /// The covered cells are blanked. Unless the protocol draws on a separate layer (Kitty), they
+    /// are also marked `skip` so the cell diff leaves the image pixels alone.
+    pub fn render_image(&mut self, image: FrameImage) {
+        let area = image.area.intersection(self.buffer.area);
+        let skip = terminal_graphics::protocol() != GraphicsProtocol::Kitty;
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                if let Some(cell) = self.buffer.cell_mut((x, y)) {
+                    cell.reset();
+                    cell.set_skip(skip);
+                }
+            }
+        }
+        self.images.push(image);
+    }
+
     /// Gets the buffer that this `Frame` draws into as a mutable reference.
     pub fn buffer_mut(&mut self) -> &mut Buffer {
         self.buffer
@@ -172,6 +195,12 @@ where
     pub last_known_cursor_pos: Position,
     /// Number of frames rendered up until current time.
     frame_count: usize,
+    /// Images the terminal is currently showing.
+    displayed_images: Vec<FrameImage>,
+    /// Images placed by the last drawn frame, not yet emitted.
+    pending_images: Vec<FrameImage>,
+    /// Set when the terminal may show images we no longer track (clear, resize, screen switch).
+    purge_images: bool,
 }
 
 impl<B> Drop for Terminal<B>
@@ -206,6 +235,9 @@ where
             last_known_screen_size: screen_size,
             last_known_cursor_pos: cursor_pos,
             frame_count: 0,
+            displayed_images: Vec::new(),
+            pending_images: Vec::new(),
+            purge_images: false,
         })
     }
 
@@ -217,6 +249,7 @@ where
             viewport_area: self.viewport_area,
             buffer: self.current_buffer_mut(),
             count,
+            images: Vec::new(),
         }
     }
 
@@ -225,11 +258,6 @@ where
         &mut self.buffers[self.current]
     }
 
-    /// Gets the backend
-    pub const fn backend(&self) -> &B {
-        &self.backend
-    }
-
     /// Gets the backend as a mutable reference
     pub fn backend_mut(&mut self) -> &mut B {
         &mut self.backend
@@ -254,9 +282,9 @@ where
     ///
     /// Requested area will be saved to remain consistent when rendering. This leads to a full clear
     /// of the screen.
-    pub fn resize(&mut self, screen_size: Size) -> io::Result<()> {
+    pub fn resize(&mut self, screen_size: Size) {
         self.last_known_screen_size = screen_size;
-        Ok(())
+        self.invalidate_images();
     }
 
     /// Sets the viewport area.
@@ -270,7 +298,7 @@ where
     pub fn autoresize(&mut self) -> io::Result<()> {
         let screen_size = self.size()?;
         if screen_size != self.last_known_screen_size {
-            self.resize(screen_size)?;
+            self.resize(screen_size);
         }
         Ok(())
     }
@@ -360,6 +388,8 @@ where
         // stdout first. But we also can't keep the frame around, since it holds a &mut to
         // Buffer. Thus, we're taking the important data out of the Frame and dropping it.
         let cursor_position = frame.cursor_position;
+        let images = std::mem::take(&mut frame.images);
+        self.pending_images = images;
 
         // Draw to stdout
         self.flush()?;
@@ -422,9 +452,29 @@ where
         self.backend.clear_region(ClearType::AfterCursor)?;
         // Reset the back buffer to make sure the next update will redraw everything.
         self.buffers[1 - self.current].reset();
+        self.invalidate_images();
         Ok(())
     }
 
+    /// Forget which images are on screen so the next frame purges and re-emits all of them.
+    pub fn invalidate_images(&mut self) {
+        self.displayed_images.clear();
+        self.purge_images = true;
+    }
+
+    /// Compare the images of the last drawn frame with what is on screen and record them as
+    /// displayed. The caller is expected to emit the returned updates right away.
+    pub fn take_image_updates(&mut self) -> ImageUpdates {
+        let pending = std::mem::take(&mut self.pending_images);
+        let updates = ImageUpdates {
+            purge: std::mem::take(&mut self.purge_images),
+            removed: self.displayed_images.iter().filter(|image| !pending.contains(image)).cloned().collect(),
+            added: pending.iter().filter(|image| !self.displayed_images.contains(image)).cloned().collect(),
+        };
+        self.displayed_images = pending;
+        updates
+    }
+
     /// Clears the inactive buffer and swaps it with the current buffer
     pub fn swap_buffers(&mut self) {
         self.buffers[1 - self.current].reset();
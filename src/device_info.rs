@@ -0,0 +1,229 @@
+//! Device properties that decide whether templates and coordinates fit: resolution, density,
+//! Android version, ABI, the installed game version and the emulator in use.
+//!
+//! Queries take several shell round trips, so results are cached per serial as
+//! `<data dir>/devices/<serial>.json` and only re-queried on request.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+    thread,
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    adb::{AdbOptions, device_serial, shell_output},
+    app_event::AppEvent,
+    app_event_sender::AppEventSender,
+    session::unix_millis,
+};
+
+/// Package names of the game's regional releases.
+pub const GAME_PACKAGES: &[&str] =
+    &["jp.co.cygames.umamusume", "com.cygames.umamusume", "com.komoe.kmumamusumegp", "com.kakaogames.umamusume"];
+
+/// Property keys or values that give an emulator away, checked in order.
+const EMULATOR_MARKERS: &[(&str, &str)] = &[
+    ("bluestacks", "BlueStacks"),
+    ("bst.", "BlueStacks"),
+    ("mumu", "MuMu Player"),
+    ("netease", "MuMu Player"),
+    ("ldplayer", "LDPlayer"),
+    ("ldinit", "LDPlayer"),
+    ("microvirt", "MEmu"),
+    ("nox", "NoxPlayer"),
+    ("genymotion", "Genymotion"),
+    ("vbox86", "VirtualBox based emulator"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GamePackage {
+    pub package: String,
+    pub version_name: String,
+    pub version_code: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    pub serial: String,
+    /// Unix timestamp (milliseconds) of the query.
+    pub queried_at: u64,
+    pub model: String,
+    pub android_version: String,
+    pub sdk: String,
+    pub abi: String,
+    pub physical_size: Option<(u32, u32)>,
+    /// Set by `wm size WxH`; the screen is rendered, and captured, at this size instead.
+    pub override_size: Option<(u32, u32)>,
+    pub physical_density: Option<u32>,
+    pub override_density: Option<u32>,
+    pub game: Option<GamePackage>,
+    /// Best guess from the properties; `None` for real hardware.
+    pub emulator: Option<String>,
+    pub properties: BTreeMap<String, String>,
+}
+
+impl DeviceInfo {
+    /// Query everything from the device. Blocks, so call it from a worker thread.
+    pub fn query(adb: &AdbOptions, serial: String) -> io::Result<Self> {
+        let mut device = adb.device();
+        let mut shell = |command: &str| {
+            shell_output(&mut device, &[command])
+                .map(|output| String::from_utf8_lossy(&output).into_owned())
+                .map_err(|err| io::Error::other(format!("{command}: {err}")))
+        };
+        let properties = parse_getprop(&shell("getprop")?);
+        let size = shell("wm size")?;
+        let density = shell("wm density")?;
+        let mut script = String::new();
+        for package in GAME_PACKAGES {
+            let _ = write!(
+                script,
+                "echo package={package}; dumpsys package {package} | grep -E 'versionName=|versionCode=';"
+            );
+        }
+        let game = parse_game_package(&shell(&script)?);
+
+        let prop = |key: &str| properties.get(key).cloned().unwrap_or_default();
+        Ok(Self {
+            serial,
+            queried_at: unix_millis(SystemTime::now()),
+            model: format!("{} {}", prop("ro.product.manufacturer"), prop("ro.product.model")).trim().to_string(),
+            android_version: prop("ro.build.version.release"),
+            sdk: prop("ro.build.version.sdk"),
+            abi: prop("ro.product.cpu.abi"),
+            physical_size: wm_value(&size, "Physical size:").and_then(parse_size),
+            override_size: wm_value(&size, "Override size:").and_then(parse_size),
+            physical_density: wm_value(&density, "Physical density:").and_then(|v| v.parse().ok()),
+            override_density: wm_value(&density, "Override density:").and_then(|v| v.parse().ok()),
+            game,
+            emulator: detect_emulator(&properties),
+            properties,
+        })
+    }
+
+    /// The size the screen is rendered at.
+    pub fn screen_size(&self) -> Option<(u32, u32)> {
+        self.override_size.or(self.physical_size)
+    }
+
+    pub fn density(&self) -> Option<u32> {
+        self.override_density.or(self.physical_density)
+    }
+
+    /// One-line description for headers.
+    pub fn summary(&self) -> String {
+        let mut summary = format!("{} · Android {}", self.model, self.android_version);
+        if let Some((width, height)) = self.screen_size() {
+            let _ = write!(summary, " · {width}x{height}");
+        }
+        if let Some(density) = self.density() {
+            let _ = write!(summary, " @{density}dpi");
+        }
+        if let Some(game) = &self.game {
+            let _ = write!(summary, " · game {}", game.version_name);
+        }
+        summary
+    }
+}
+
+/// Whether a frame of `size` fits a screen of `screen`, in either orientation.
+pub fn same_resolution(size: (u32, u32), screen: (u32, u32)) -> bool {
+    size == screen || size == (screen.1, screen.0)
+}
+
+/// Parse `[key]: [value]` lines.
+fn parse_getprop(text: &str) -> BTreeMap<String, String> {
+    text.lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim().strip_prefix('[')?.split_once("]: [")?;
+            Some((key.to_string(), value.strip_suffix(']')?.to_string()))
+        })
+        .collect()
+}
+
+fn wm_value<'a>(text: &'a str, label: &str) -> Option<&'a str> {
+    text.lines().find_map(|line| line.trim().strip_prefix(label)).map(str::trim)
+}
+
+fn parse_size(text: &str) -> Option<(u32, u32)> {
+    let (width, height) = text.split_once('x')?;
+    Some((width.trim().parse().ok()?, height.trim().parse().ok()?))
+}
+
+/// The first installed package of the `package=NAME` sections printed by the query script.
+fn parse_game_package(text: &str) -> Option<GamePackage> {
+    let mut packages = text.split("package=").skip(1);
+    packages.find_map(|section| {
+        let package = section.lines().next()?.trim().to_string();
+        let field =
+            |name: &str| section.split_whitespace().find_map(|word| word.strip_prefix(name)).map(ToString::to_string);
+        Some(GamePackage {
+            package,
+            version_name: field("versionName=")?,
+            version_code: field("versionCode=").unwrap_or_default(),
+        })
+    })
+}
+
+fn detect_emulator(properties: &BTreeMap<String, String>) -> Option<String> {
+    let haystack: Vec<String> =
+        properties.iter().map(|(key, value)| format!("{key}={value}").to_ascii_lowercase()).collect();
+    EMULATOR_MARKERS
+        .iter()
+        .find(|(marker, _)| haystack.iter().any(|entry| entry.contains(marker)))
+        .map(|(_, name)| (*name).to_string())
+        .or_else(|| {
+            let qemu =
+                ["ro.kernel.qemu", "ro.boot.qemu"].iter().any(|key| properties.get(*key).is_some_and(|v| v == "1"));
+            qemu.then(|| "Android Emulator".to_string())
+        })
+}
+
+/// `<dir>/<serial>.<extension>`, with characters that are awkward in file names replaced.
+pub fn serial_path(dir: &Path, serial: &str, extension: &str) -> PathBuf {
+    let stem: String = serial
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    dir.join(format!("{stem}.{extension}"))
+}
+
+fn load_cached(dir: &Path, serial: &str) -> Option<DeviceInfo> {
+    let json = fs::read(serial_path(dir, serial, "json")).ok()?;
+    serde_json::from_slice(&json).ok()
+}
+
+fn save_cached(dir: &Path, info: &DeviceInfo) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let json = serde_json::to_vec_pretty(info).map_err(io::Error::other)?;
+    fs::write(serial_path(dir, &info.serial, "json"), json)
+}
+
+/// Look up the device on a worker thread, from the cache in `cache_dir` unless `refresh` is set,
+/// and deliver it as [`AppEvent::DeviceInfo`].
+pub fn spawn_load(adb: AdbOptions, app_event_tx: AppEventSender, cache_dir: PathBuf, refresh: bool) {
+    thread::spawn(move || {
+        let result = device_serial(&adb).and_then(|serial| {
+            if !refresh && let Some(info) = load_cached(&cache_dir, &serial) {
+                return Ok(info);
+            }
+            let info = DeviceInfo::query(&adb, serial)?;
+            if let Err(err) = save_cached(&cache_dir, &info) {
+                tracing::warn!("failed to cache device info: {err}");
+            }
+            Ok(info)
+        });
+        app_event_tx.send(AppEvent::DeviceInfo(result.map(Box::new).map_err(|err| err.to_string())));
+    });
+}
@@ -0,0 +1,33 @@
+//! Settings kept per device across sessions, as `<data dir>/devices/<serial>.profile.json` next to
+//! the cached [`DeviceInfo`](crate::device_info::DeviceInfo). Unlike that cache, the profile is
+//! only written by the user's own changes.
+
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{device_info::serial_path, port_forward::PortRule};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceProfile {
+    /// Forward and reverse rules set up again whenever the device comes online.
+    pub port_rules: Vec<PortRule>,
+}
+
+impl DeviceProfile {
+    /// The profile of `serial` in `dir`, or an empty one if there is none yet.
+    pub fn load(dir: &Path, serial: &str) -> io::Result<Self> {
+        match fs::read(serial_path(dir, serial, "profile.json")) {
+            Ok(json) => serde_json::from_slice(&json).map_err(io::Error::other),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn save(&self, dir: &Path, serial: &str) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let json = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+        fs::write(serial_path(dir, serial, "profile.json"), json)
+    }
+}
@@ -0,0 +1,466 @@
+//! DEVICE tab: what the connected device reports about itself, with the settings that break
+//! template matching called out, and the picker choosing which device the app talks to.
+
+use std::{
+    collections::BTreeSet,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Clear, List, ListItem, ListState, Paragraph},
+};
+
+use crate::{
+    adb::AdbOptions,
+    app_event_sender::AppEventSender,
+    custom_terminal::Frame,
+    device_info::{self, DeviceInfo, same_resolution},
+    discovery::{self, DeviceScan, PortRanges, is_local_port},
+    template, tui,
+};
+
+pub struct DeviceView {
+    adb: AdbOptions,
+    app_event_tx: AppEventSender,
+    cache_dir: PathBuf,
+    templates_dir: PathBuf,
+    info: Option<DeviceInfo>,
+    loading: bool,
+    error: Option<String>,
+    /// Source resolutions of every template set, by set name.
+    template_resolutions: Vec<(String, BTreeSet<(u32, u32)>)>,
+    /// Substring the property list is narrowed to, and whether it is being typed.
+    filter: String,
+    editing_filter: bool,
+    scroll: usize,
+    /// Ports scanned for emulator instances when a scan is started from the picker.
+    scan_ranges: PortRanges,
+    scan: Option<DeviceScan>,
+    scanning: bool,
+    scan_error: Option<String>,
+    /// Selection in the device picker while it is open. Row 0 stands for "whichever device is
+    /// connected", the rest for `scan.devices`.
+    picker: Option<ListState>,
+}
+
+impl DeviceView {
+    pub fn new(
+        adb: AdbOptions,
+        app_event_tx: AppEventSender,
+        cache_dir: PathBuf,
+        templates_dir: PathBuf,
+        scan_ranges: PortRanges,
+    ) -> Self {
+        Self {
+            adb,
+            app_event_tx,
+            cache_dir,
+            templates_dir,
+            info: None,
+            loading: false,
+            error: None,
+            template_resolutions: Vec::new(),
+            filter: String::new(),
+            editing_filter: false,
+            scroll: 0,
+            scan_ranges,
+            scan: None,
+            scanning: false,
+            scan_error: None,
+            picker: None,
+        }
+    }
+
+    fn start_scan(&mut self) {
+        if self.scanning {
+            return;
+        }
+        self.scanning = true;
+        discovery::spawn_scanner(self.adb.clone(), self.app_event_tx.clone(), self.scan_ranges.clone(), None);
+    }
+
+    /// A device scan finished, either one started here or a periodic one.
+    pub fn on_scan(&mut self, result: Result<Box<DeviceScan>, String>) {
+        self.scanning = false;
+        match result {
+            Ok(scan) => {
+                self.scan = Some(*scan);
+                self.scan_error = None;
+            }
+            Err(err) => self.scan_error = Some(err),
+        }
+        if let (Some(picker), Some(scan)) = (&mut self.picker, &self.scan) {
+            let last = scan.devices.len();
+            picker.select(Some(picker.selected().unwrap_or_default().min(last)));
+        }
+    }
+
+    /// The device the app talks to, or `None` for whichever is connected.
+    pub fn serial(&self) -> Option<String> {
+        self.adb.serial()
+    }
+
+    /// Make `serial` the device the app talks to, or whichever is connected for `None`.
+    pub fn select(&mut self, serial: Option<String>) {
+        if serial == self.adb.serial() {
+            return;
+        }
+        // Every clone of the options follows, so new workers talk to this device.
+        self.adb.select(serial);
+        self.info = None;
+        self.error = None;
+        self.load(false);
+    }
+
+    fn open_picker(&mut self) {
+        let serial = self.adb.serial();
+        let current = self.scan.as_ref().and_then(|scan| {
+            scan.devices.iter().position(|device| Some(&device.serial) == serial.as_ref()).map(|index| index + 1)
+        });
+        self.picker = Some(ListState::default().with_selected(Some(current.unwrap_or_default())));
+        self.start_scan();
+    }
+
+    fn handle_picker_key(&mut self, key_event: KeyEvent) {
+        let Some(picker) = &mut self.picker else {
+            return;
+        };
+        let rows = 1 + self.scan.as_ref().map_or(0, |scan| scan.devices.len());
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Char('p') => self.picker = None,
+            KeyCode::Up | KeyCode::Char('k') => picker.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') if picker.selected().is_some_and(|index| index + 1 < rows) => {
+                picker.select_next();
+            }
+            KeyCode::Char('s') => self.start_scan(),
+            KeyCode::Enter => {
+                let index = picker.selected().unwrap_or_default();
+                let serial = index
+                    .checked_sub(1)
+                    .and_then(|index| self.scan.as_ref()?.devices.get(index))
+                    .map(|device| device.serial.clone());
+                self.picker = None;
+                self.select(serial);
+            }
+            _ => {}
+        }
+    }
+
+    /// Load the device description, from the cache unless `refresh` is set.
+    pub fn load(&mut self, refresh: bool) {
+        if self.loading {
+            return;
+        }
+        self.loading = true;
+        device_info::spawn_load(self.adb.clone(), self.app_event_tx.clone(), self.cache_dir.clone(), refresh);
+    }
+
+    pub fn on_info(&mut self, result: Result<Box<DeviceInfo>, String>) {
+        self.loading = false;
+        match result {
+            Ok(info) => {
+                self.info = Some(*info);
+                self.error = None;
+                self.load_template_resolutions();
+            }
+            Err(err) => self.error = Some(err),
+        }
+    }
+
+    fn load_template_resolutions(&mut self) {
+        let sets = template::list_sets(&self.templates_dir).unwrap_or_default();
+        self.template_resolutions = sets
+            .iter()
+            .filter_map(|set| {
+                let name = set.strip_prefix(&self.templates_dir).unwrap_or(set).display().to_string();
+                let name = if name.is_empty() {
+                    "(root)".to_string()
+                } else {
+                    name
+                };
+                Some((name, template::set_resolutions(set).ok()?))
+            })
+            .collect();
+    }
+
+    /// Template sets cut at a resolution the device is not running at.
+    fn mismatched_sets(&self) -> Vec<&str> {
+        let Some(screen) = self.info.as_ref().and_then(DeviceInfo::screen_size) else {
+            return Vec::new();
+        };
+        self.template_resolutions
+            .iter()
+            .filter(|(_, sizes)| sizes.iter().any(|&size| !same_resolution(size, screen)))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Short device line for the tab bar, red when something is likely to break matching.
+    pub fn header_line(&self) -> Line<'static> {
+        let Some(info) = &self.info else {
+            return Line::from(if self.loading {
+                "querying device…"
+            } else {
+                "no device info"
+            })
+            .dim();
+        };
+        let line = Line::from(format!("{} {}", info.serial, info.summary()));
+        if info.override_size.is_some() || info.override_density.is_some() || !self.mismatched_sets().is_empty() {
+            line.red()
+        } else {
+            line.dim()
+        }
+    }
+
+    pub fn handle_key_event(&mut self, tui: &tui::Tui, key_event: KeyEvent) {
+        if self.picker.is_some() {
+            self.handle_picker_key(key_event);
+            tui.frame_requester().schedule_frame();
+            return;
+        }
+        if self.editing_filter {
+            match key_event.code {
+                KeyCode::Enter | KeyCode::Esc => self.editing_filter = false,
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Char(c) => self.filter.push(c),
+                _ => return,
+            }
+            self.scroll = 0;
+            tui.frame_requester().schedule_frame();
+            return;
+        }
+        match key_event.code {
+            KeyCode::Char('r') => self.load(true),
+            KeyCode::Char('p') => self.open_picker(),
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::Esc => self.filter.clear(),
+            KeyCode::Up | KeyCode::Char('k') => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.scroll = self.scroll.saturating_add(1),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(20),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_add(20),
+            _ => return,
+        }
+        tui.frame_requester().schedule_frame();
+    }
+
+    fn status_line(&self) -> Line<'static> {
+        if self.editing_filter {
+            return Line::from(vec![
+                Span::from(" filter properties: ").bold(),
+                Span::from(format!("{}▏", self.filter)),
+                Span::from("  enter: done").dim(),
+            ]);
+        }
+        if self.picker.is_some() {
+            let mut line =
+                Line::from(format!(" enter: use device  s: scan ports {} for emulators  esc: close", self.scan_ranges))
+                    .dim();
+            if self.scanning {
+                line.push_span(Span::from(" · scanning…").yellow());
+            }
+            if let Some(err) = &self.scan_error {
+                line.push_span(Span::from(format!(" · {err}")).red());
+            }
+            return line;
+        }
+        let mut line = Line::from(" r: query again  p: pick device  /: filter properties  j/k: scroll").dim();
+        if self.loading {
+            line.push_span(Span::from(" · querying…").yellow());
+        } else if let Some(info) = &self.info {
+            let age = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .saturating_sub(Duration::from_millis(info.queried_at));
+            line.push_span(Span::from(format!(" · queried {} ago", format_age(age))));
+        }
+        if let Some(err) = &self.error {
+            line.push_span(Span::from(format!(" · {err}")).red());
+        }
+        line
+    }
+
+    fn summary_lines(&self, info: &DeviceInfo) -> Vec<Line<'static>> {
+        let size = |size: Option<(u32, u32)>| size.map_or_else(|| "?".to_string(), |(w, h)| format!("{w}x{h}"));
+        let mut lines = vec![
+            Line::from(format!("serial      {}", info.serial)),
+            Line::from(format!("model       {}", info.model)),
+            Line::from(format!("android     {} (sdk {})", info.android_version, info.sdk)),
+            Line::from(format!("abi         {}", info.abi)),
+            Line::from(format!("emulator    {}", info.emulator.as_deref().unwrap_or("no"))),
+            Line::from(info.game.as_ref().map_or_else(
+                || "game        not installed".to_string(),
+                |game| format!("game        {} {} ({})", game.package, game.version_name, game.version_code),
+            )),
+            Line::from(""),
+            Line::from(format!("screen      {}", size(info.physical_size))),
+        ];
+        if info.override_size.is_some() {
+            lines.push(Line::from(format!("  override  {} (wm size)", size(info.override_size))).red());
+        }
+        lines.push(Line::from(format!(
+            "density     {}",
+            info.physical_density.map_or_else(|| "?".to_string(), |d| d.to_string())
+        )));
+        if let Some(density) = info.override_density {
+            lines.push(Line::from(format!("  override  {density} (wm density)")).red());
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from("template sets").bold());
+        if self.template_resolutions.is_empty() {
+            lines.push(Line::from("  none").italic());
+        }
+        let screen = info.screen_size();
+        for (name, sizes) in &self.template_resolutions {
+            let sizes_text: Vec<String> = sizes.iter().map(|(w, h)| format!("{w}x{h}")).collect();
+            let sizes_text = if sizes_text.is_empty() {
+                "no sidecars".to_string()
+            } else {
+                sizes_text.join(", ")
+            };
+            let fits = screen.is_none_or(|screen| sizes.iter().all(|&size| same_resolution(size, screen)));
+            let line = Line::from(format!("  {name:<16} {sizes_text}"));
+            lines.push(if fits {
+                line
+            } else {
+                line.red()
+            });
+        }
+        lines
+    }
+
+    fn picker_items(&self) -> Vec<ListItem<'static>> {
+        let selected = self.adb.serial();
+        let marker = |serial: Option<&String>| {
+            if serial == selected.as_ref() {
+                "● "
+            } else {
+                "  "
+            }
+        };
+        let mut items = vec![ListItem::new(format!("{}any: the only connected device", marker(None)))];
+        let Some(scan) = &self.scan else {
+            return items;
+        };
+        for device in &scan.devices {
+            let instance = scan.instances.iter().find(|instance| is_local_port(&device.serial, instance.port));
+            let mut line = Line::from(format!(
+                "{}{:<22} {:<12} {}",
+                marker(Some(&device.serial)),
+                device.serial,
+                device.state,
+                device.model.as_deref().unwrap_or("")
+            ));
+            if let Some((family, index)) = instance.and_then(|instance| Some((instance.family?, instance.index?))) {
+                line.push_span(Span::from(format!("  {family} #{index}")).cyan());
+            }
+            items.push(ListItem::new(if device.state == "device" {
+                line
+            } else {
+                line.dim()
+            }));
+        }
+        items
+    }
+
+    /// Instances found by the scan that the adb server could not connect to.
+    fn failed_instances(&self) -> Vec<Line<'static>> {
+        self.scan
+            .iter()
+            .flat_map(|scan| &scan.instances)
+            .filter_map(|instance| {
+                let err = instance.status.as_ref().err()?;
+                Some(Line::from(format!("  127.0.0.1:{}  {err}", instance.port)).red())
+            })
+            .collect()
+    }
+
+    fn render_picker(&mut self, frame: &mut Frame, area: Rect) {
+        let failed = self.failed_instances();
+        let items = self.picker_items();
+        let height = u16::try_from(items.len() + failed.len()).unwrap_or(u16::MAX).saturating_add(2).min(area.height);
+        let width = area.width.saturating_sub(4).min(90);
+        let popup = Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height);
+        frame.render_widget(Clear, popup);
+        let block = Block::bordered().title(" Devices ");
+        let inner = block.inner(popup);
+        frame.render_widget(block, popup);
+        let failed_height = u16::try_from(failed.len()).unwrap_or(u16::MAX);
+        let [list_area, failed_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(failed_height)]).areas(inner);
+        if let Some(picker) = &mut self.picker {
+            let list = List::new(items).highlight_style(Style::default().reversed());
+            frame.render_stateful_widget(list, list_area, picker);
+        }
+        frame.render_widget(Paragraph::new(failed), failed_area);
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect) {
+        let [status_area, body] = Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+        frame.render_widget(self.status_line(), status_area);
+        if self.picker.is_some() {
+            self.render_details(frame, body);
+            self.render_picker(frame, body);
+            return;
+        }
+        self.render_details(frame, body);
+    }
+
+    fn render_details(&mut self, frame: &mut Frame, body: Rect) {
+        let [summary_area, props_area] =
+            Layout::horizontal([Constraint::Percentage(45), Constraint::Min(0)]).areas(body);
+
+        let Some(info) = &self.info else {
+            let block = Block::bordered().title(" Device ");
+            let message = if self.loading {
+                "Querying the device…"
+            } else {
+                "No device information"
+            };
+            frame.render_widget(Paragraph::new(Line::from(message).italic()).block(block), body);
+            return;
+        };
+        frame.render_widget(
+            Paragraph::new(self.summary_lines(info)).block(Block::bordered().title(" Device ")),
+            summary_area,
+        );
+
+        let filter = self.filter.to_ascii_lowercase();
+        let props: Vec<Line> = info
+            .properties
+            .iter()
+            .filter(|(key, value)| {
+                filter.is_empty()
+                    || key.to_ascii_lowercase().contains(&filter)
+                    || value.to_ascii_lowercase().contains(&filter)
+            })
+            .map(|(key, value)| Line::from(vec![Span::from(key.clone()).cyan(), Span::from(format!(" = {value}"))]))
+            .collect();
+        let height = usize::from(props_area.height.saturating_sub(2));
+        self.scroll = self.scroll.min(props.len().saturating_sub(height));
+        let title = if filter.is_empty() {
+            format!(" getprop ({}) ", props.len())
+        } else {
+            format!(" getprop /{} ({}) ", self.filter, props.len())
+        };
+        let visible: Vec<Line> = props.into_iter().skip(self.scroll).take(height).collect();
+        frame.render_widget(Paragraph::new(visible).block(Block::bordered().title(title)), props_area);
+    }
+}
+
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86_400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86_400),
+    }
+}
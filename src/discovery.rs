@@ -0,0 +1,193 @@
+//! Discovery of emulator instances listening for adb on local ports.
+//!
+//! Emulators such as `LDPlayer`, `MuMu`, `BlueStacks` and Nox run an adbd per instance on a localhost
+//! TCP port, but only show up in the adb server once something runs `adb connect`. A scan tries
+//! every port of the configured ranges: a port that answers an adb `CNXN` packet with `CNXN` or
+//! `AUTH` is an adbd, and is handed to the server with `host:connect`. The instance index is
+//! derived from the port with the numbering scheme of the emulator family using that range.
+
+use std::{
+    fmt, io,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpStream},
+    str::FromStr,
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    adb::{AdbOptions, host_query},
+    adb_direct::{A_AUTH, A_CNXN, A_VERSION, MAX_PAYLOAD, read_message, write_message},
+    app_event::AppEvent,
+    app_event_sender::AppEventSender,
+};
+
+/// How long a port may take to accept the connection and to answer the probe.
+const PROBE_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// Port numbering of emulator families: instance `n` listens on `base + n * stride`.
+const PORT_SCHEMES: &[(&str, u16, u16)] =
+    &[("MuMu", 16384, 32), ("MuMu", 7555, 1), ("Nox", 62024, 1), ("Nox", 62001, 1), ("LDPlayer/emulator", 5555, 2)];
+
+/// Inclusive port ranges, written as `5555-5585,7555,16384-16700`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortRanges(pub Vec<(u16, u16)>);
+
+impl FromStr for PortRanges {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let port = |text: &str| text.trim().parse::<u16>().map_err(|err| format!("{text:?}: {err}"));
+        text.split(',')
+            .map(|range| {
+                let (start, end) = range.split_once('-').unwrap_or((range, range));
+                let (start, end) = (port(start)?, port(end)?);
+                if start > end {
+                    return Err(format!("{range:?} ends before it starts"));
+                }
+                Ok((start, end))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl fmt::Display for PortRanges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (start, end)) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_str(",")?;
+            }
+            if start == end {
+                write!(f, "{start}")?;
+            } else {
+                write!(f, "{start}-{end}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A device the adb server knows about, from `host:devices-l`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttachedDevice {
+    pub serial: String,
+    /// `device`, `offline`, `unauthorized`, …
+    pub state: String,
+    pub model: Option<String>,
+}
+
+/// An adbd found on a local port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instance {
+    pub port: u16,
+    /// Emulator family and instance index guessed from the port.
+    pub family: Option<&'static str>,
+    pub index: Option<u16>,
+    /// The server's answer to `host:connect`, or why connecting failed.
+    pub status: Result<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DeviceScan {
+    pub devices: Vec<AttachedDevice>,
+    pub instances: Vec<Instance>,
+}
+
+/// The emulator family and instance index `port` belongs to, by the first scheme it fits.
+pub fn instance_index(port: u16) -> Option<(&'static str, u16)> {
+    PORT_SCHEMES.iter().find_map(|&(family, base, stride)| {
+        let offset = port.checked_sub(base)?;
+        let index = offset / stride;
+        // Schemes with a stride of one only cover a handful of ports.
+        (offset % stride == 0 && (stride > 1 || index < 64)).then_some((family, index))
+    })
+}
+
+/// Whether an adbd answers on `port`.
+fn probe(port: u16) -> io::Result<bool> {
+    let address = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, port));
+    let mut stream = TcpStream::connect_timeout(&address, PROBE_TIMEOUT)?;
+    stream.set_read_timeout(Some(PROBE_TIMEOUT))?;
+    write_message(&mut stream, A_CNXN, A_VERSION, MAX_PAYLOAD, b"host::\0")?;
+    let command = read_message(&mut stream)?.command;
+    Ok(command == A_CNXN || command == A_AUTH)
+}
+
+/// Parse `host:devices-l` lines such as `emulator-5554 device product:x model:Pixel_7 …`.
+fn parse_devices(text: &str) -> Vec<AttachedDevice> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let serial = fields.next()?.to_string();
+            let state = fields.next()?.to_string();
+            let model = fields.find_map(|field| field.strip_prefix("model:")).map(ToString::to_string);
+            Some(AttachedDevice {
+                serial,
+                state,
+                model,
+            })
+        })
+        .collect()
+}
+
+/// Whether `serial` already names the adbd on local `port`.
+pub fn is_local_port(serial: &str, port: u16) -> bool {
+    serial == format!("127.0.0.1:{port}")
+        || serial == format!("localhost:{port}")
+        // Emulators registered by the server itself are named after their console port.
+        || port.checked_sub(1).is_some_and(|console| serial == format!("emulator-{console}"))
+}
+
+/// Ask the server to connect to the adbd on local `port`. The server accepts the request either
+/// way and says in its reply whether it worked.
+fn connect(adb: &AdbOptions, port: u16) -> Result<String, String> {
+    let reply = host_query(adb, &format!("host:connect:127.0.0.1:{port}")).map_err(|err| err.to_string())?;
+    if reply.starts_with("connected") || reply.starts_with("already connected") {
+        Ok(reply)
+    } else {
+        Err(reply)
+    }
+}
+
+/// Probe every port in `ranges`, connect the adb server to the adbds found and list devices.
+pub fn scan(adb: &AdbOptions, ranges: &PortRanges) -> io::Result<DeviceScan> {
+    let attached = parse_devices(&host_query(adb, "host:devices-l")?);
+    let mut instances = Vec::new();
+    for port in ranges.0.iter().flat_map(|&(start, end)| start..=end) {
+        // Skip ports the server already talks to; probing them again would only add noise.
+        let known = attached.iter().find(|device| is_local_port(&device.serial, port));
+        let status = match known {
+            Some(device) => Ok(format!("attached as {}", device.serial)),
+            None if probe(port).unwrap_or(false) => connect(adb, port),
+            None => continue,
+        };
+        let (family, index) = instance_index(port).unzip();
+        instances.push(Instance {
+            port,
+            family,
+            index,
+            status,
+        });
+    }
+    // Listed again so newly connected instances are included.
+    let devices = parse_devices(&host_query(adb, "host:devices-l")?);
+    Ok(DeviceScan {
+        devices,
+        instances,
+    })
+}
+
+/// Scan every `interval` until the app shuts down, or once if it is `None`, delivering
+/// [`AppEvent::DevicesScanned`].
+pub fn spawn_scanner(adb: AdbOptions, app_event_tx: AppEventSender, ranges: PortRanges, interval: Option<Duration>) {
+    thread::spawn(move || {
+        while !app_event_tx.app_event_tx.is_closed() {
+            let result = scan(&adb, &ranges).map(Box::new).map_err(|err| err.to_string());
+            app_event_tx.send(AppEvent::DevicesScanned(result));
+            let Some(interval) = interval else {
+                break;
+            };
+            thread::sleep(interval);
+        }
+    });
+}
@@ -0,0 +1,219 @@
+//! Export of career runs into flat tables for spreadsheets and notebooks.
+//!
+//! Every export writes three tables, one file each, covering all exported runs:
+//!
+//! | file          | columns                                                                                 |
+//! |---------------|-----------------------------------------------------------------------------------------|
+//! | `turns.*`     | `run_id, trainee, turn, decision, speed, stamina, power, guts, wit, skill_points, total` |
+//! | `races.*`     | `run_id, turn, race, placement`                                                         |
+//! | `events.*`    | `run_id, turn, seq, event`                                                              |
+//!
+//! `seq` is the position of the event within its turn, starting at 0. CSV files have a header row
+//! and use RFC 4180 quoting; JSONL files hold one object per row with the same keys in the same
+//! order. Columns are only ever appended, never renamed or reordered.
+
+use std::{
+    fs,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use color_eyre::eyre::WrapErr;
+use serde::Serialize;
+
+use crate::{
+    career::{CareerRun, RunStore},
+    cli::ExportArgs,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Jsonl,
+}
+
+impl ExportFormat {
+    const fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Jsonl => "jsonl",
+        }
+    }
+}
+
+/// A row type with a fixed, documented column order.
+trait Record: Serialize {
+    const TABLE: &'static str;
+    const COLUMNS: &'static [&'static str];
+}
+
+#[derive(Debug, Serialize)]
+struct TurnRecord<'a> {
+    run_id: &'a str,
+    trainee: &'a str,
+    turn: u32,
+    decision: &'a str,
+    speed: u32,
+    stamina: u32,
+    power: u32,
+    guts: u32,
+    wit: u32,
+    skill_points: u32,
+    total: u32,
+}
+
+impl Record for TurnRecord<'_> {
+    const COLUMNS: &'static [&'static str] =
+        &["run_id", "trainee", "turn", "decision", "speed", "stamina", "power", "guts", "wit", "skill_points", "total"];
+    const TABLE: &'static str = "turns";
+}
+
+#[derive(Debug, Serialize)]
+struct RaceRecord<'a> {
+    run_id: &'a str,
+    turn: u32,
+    race: &'a str,
+    placement: u32,
+}
+
+impl Record for RaceRecord<'_> {
+    const COLUMNS: &'static [&'static str] = &["run_id", "turn", "race", "placement"];
+    const TABLE: &'static str = "races";
+}
+
+#[derive(Debug, Serialize)]
+struct EventRecord<'a> {
+    run_id: &'a str,
+    turn: u32,
+    seq: usize,
+    event: &'a str,
+}
+
+impl Record for EventRecord<'_> {
+    const COLUMNS: &'static [&'static str] = &["run_id", "turn", "seq", "event"];
+    const TABLE: &'static str = "events";
+}
+
+/// Write the turn, race and event tables for `runs` into `out_dir`, creating it if needed.
+/// Returns the paths of the written files.
+pub fn export_runs<'a>(
+    runs: impl IntoIterator<Item = &'a CareerRun> + Clone,
+    format: ExportFormat,
+    out_dir: &Path,
+) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(out_dir)?;
+
+    let turns = runs.clone().into_iter().flat_map(|run| {
+        run.turns.iter().map(|t| TurnRecord {
+            run_id: &run.id,
+            trainee: &run.trainee,
+            turn: t.number,
+            decision: &t.decision,
+            speed: t.stats.speed,
+            stamina: t.stats.stamina,
+            power: t.stats.power,
+            guts: t.stats.guts,
+            wit: t.stats.wit,
+            skill_points: t.stats.skill_points,
+            total: t.stats.total(),
+        })
+    });
+    let races = runs.clone().into_iter().flat_map(|run| {
+        run.turns.iter().filter_map(|t| {
+            t.race.as_ref().map(|race| RaceRecord {
+                run_id: &run.id,
+                turn: t.number,
+                race: &race.name,
+                placement: race.placement,
+            })
+        })
+    });
+    let events = runs.into_iter().flat_map(|run| {
+        run.turns.iter().flat_map(|t| {
+            t.events.iter().enumerate().map(|(seq, event)| EventRecord {
+                run_id: &run.id,
+                turn: t.number,
+                seq,
+                event,
+            })
+        })
+    });
+
+    Ok(vec![
+        write_table(out_dir, format, turns)?,
+        write_table(out_dir, format, races)?,
+        write_table(out_dir, format, events)?,
+    ])
+}
+
+fn write_table<R: Record>(out_dir: &Path, format: ExportFormat, rows: impl Iterator<Item = R>) -> io::Result<PathBuf> {
+    let path = out_dir.join(format!("{}.{}", R::TABLE, format.extension()));
+    let mut writer = BufWriter::new(fs::File::create(&path)?);
+    match format {
+        ExportFormat::Csv => {
+            write_csv_row(&mut writer, R::COLUMNS.iter().map(|c| (*c).to_string()))?;
+            for row in rows {
+                write_csv_row(&mut writer, record_fields::<R>(&row)?)?;
+            }
+        }
+        ExportFormat::Jsonl => {
+            for row in rows {
+                serde_json::to_writer(&mut writer, &row)?;
+                writer.write_all(b"\n")?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(path)
+}
+
+/// Serialize `row` and return its fields as strings, in `R::COLUMNS` order.
+fn record_fields<R: Record>(row: &R) -> io::Result<impl Iterator<Item = String>> {
+    let serde_json::Value::Object(mut object) = serde_json::to_value(row)? else {
+        return Err(io::Error::other(format!("{} record did not serialize to an object", R::TABLE)));
+    };
+    let fields: Vec<String> = R::COLUMNS
+        .iter()
+        .map(|column| match object.remove(*column) {
+            Some(serde_json::Value::String(s)) => s,
+            Some(serde_json::Value::Null) | None => String::new(),
+            Some(other) => other.to_string(),
+        })
+        .collect();
+    Ok(fields.into_iter())
+}
+
+fn write_csv_row(writer: &mut impl Write, fields: impl Iterator<Item = String>) -> io::Result<()> {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            writer.write_all(b",")?;
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            write!(writer, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            writer.write_all(field.as_bytes())?;
+        }
+    }
+    writer.write_all(b"\r\n")
+}
+
+/// Entry point for `uma-auto-debug export`: export runs from the store without starting the TUI.
+pub fn run_headless(data_dir: &Path, args: &ExportArgs) -> color_eyre::Result<()> {
+    let store = RunStore::new(data_dir);
+    let runs = if args.runs.is_empty() {
+        store.load_all()?
+    } else {
+        args.runs
+            .iter()
+            .map(|id| store.load(id).wrap_err_with(|| format!("failed to load run {id}")))
+            .collect::<color_eyre::Result<Vec<_>>>()?
+    };
+    if runs.is_empty() {
+        color_eyre::eyre::bail!("no career runs found in {}", store.dir().display());
+    }
+    for path in export_runs(&runs, args.format, &args.out)? {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,124 @@
+//! Monitoring of many devices at once, for farms of emulator instances each running the bot.
+//!
+//! The adb server's device list is followed with `host:track-devices`, and every device that is
+//! online gets a logcat stream of its own. Streams start at the newest line, so log rates only
+//! count lines as they happen. The bot's progress is read off its log lines: it announces every
+//! transition of its state machine as `STATE <name>`, OCR lines (see [`crate::ocr_review`]) carry
+//! the career turn, and error and fatal logcat lines are kept as the last error.
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    adb::{AdbOptions, LineSink, track_devices},
+    app_event::AppEvent,
+    app_event_sender::AppEventSender,
+    ocr_review::OcrReading,
+    session::LogLine,
+};
+
+/// Marker in front of the state name on the bot's state transition lines.
+const STATE_MARKER: &str = "STATE ";
+/// Wait before following the device list or a log stream again after it ended.
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// The state the bot entered, if `text` is a state transition line.
+pub fn bot_state(text: &str) -> Option<&str> {
+    let start = text.find(STATE_MARKER)? + STATE_MARKER.len();
+    text[start..].split_whitespace().next()
+}
+
+/// The career turn an OCR line was read on.
+pub fn career_turn(text: &str) -> Option<u32> {
+    OcrReading::parse(text).map(|reading| reading.turn)
+}
+
+/// Split a logcat line into its priority letter and the part from the tag on. Understands the
+/// default `threadtime` format (`MM-DD HH:MM:SS.mmm PID TID P TAG: message`) and `brief`
+/// (`P/TAG(PID): message`).
+pub fn split_priority(text: &str) -> Option<(char, &str)> {
+    let is_priority = |c: char| "VDIWEF".contains(c);
+    let mut chars = text.chars();
+    if let (Some(priority), Some('/')) = (chars.next(), chars.next())
+        && is_priority(priority)
+    {
+        return Some((priority, &text[2..]));
+    }
+    let mut rest = text;
+    for _ in 0..4 {
+        rest = rest.trim_start();
+        rest = &rest[rest.find(char::is_whitespace)?..];
+    }
+    let rest = rest.trim_start();
+    let mut chars = rest.chars();
+    match (chars.next(), chars.next()) {
+        (Some(priority), Some(' ')) if is_priority(priority) => Some((priority, rest[1..].trim_start())),
+        _ => None,
+    }
+}
+
+/// Priority, tag and message of a logcat line. Lines that are not in a known logcat format are
+/// all message.
+pub fn split_logcat(text: &str) -> (Option<char>, &str, &str) {
+    let Some((priority, rest)) = split_priority(text) else {
+        return (None, "", text.trim());
+    };
+    let Some((tag, message)) = rest.split_once(": ") else {
+        return (Some(priority), "", rest.trim());
+    };
+    // `brief` puts the PID after the tag: `Tag( 1234)`.
+    let tag = tag.split_once('(').map_or(tag, |(tag, _)| tag);
+    (Some(priority), tag.trim(), message.trim())
+}
+
+/// Follow the devices the adb server knows, delivering [`AppEvent::FarmDevices`] on every change
+/// until the app shuts down.
+pub fn spawn_tracker(adb: AdbOptions, app_event_tx: AppEventSender) {
+    thread::spawn(move || {
+        while !app_event_tx.app_event_tx.is_closed() {
+            let result = track_devices(&adb, |devices| app_event_tx.send(AppEvent::FarmDevices(Ok(devices))));
+            if let Err(err) = result {
+                app_event_tx.send(AppEvent::FarmDevices(Err(err.to_string())));
+            }
+            thread::sleep(RETRY_DELAY);
+        }
+    });
+}
+
+/// Stream the logcat of `serial` as [`AppEvent::FarmLogLine`]s until `stop` is set, starting over
+/// when the stream ends. Every end is reported as [`AppEvent::FarmLogEnded`].
+pub fn spawn_logcat(adb: &AdbOptions, serial: String, app_event_tx: AppEventSender, stop: Arc<AtomicBool>) {
+    let adb = adb.for_serial(&serial);
+    thread::spawn(move || {
+        while !stop.load(Ordering::Relaxed) && !app_event_tx.app_event_tx.is_closed() {
+            let mut sink = LineSink::new(&stop, |lines| {
+                let received_at = SystemTime::now();
+                for text in lines {
+                    app_event_tx.send(AppEvent::FarmLogLine {
+                        serial: serial.clone(),
+                        line: LogLine {
+                            received_at,
+                            text,
+                        },
+                    });
+                }
+                !app_event_tx.app_event_tx.is_closed()
+            });
+            let result = adb.device().shell_command(&["logcat", "-T", "1"], &mut sink);
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            app_event_tx.send(AppEvent::FarmLogEnded {
+                serial: serial.clone(),
+                error: result.err().map_or_else(|| "log stream ended".to_string(), |err| err.to_string()),
+            });
+            thread::sleep(RETRY_DELAY);
+        }
+    });
+}
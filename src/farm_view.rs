@@ -0,0 +1,457 @@
+//! FARM tab: a tile per device the adb server knows, with what its bot is up to and what the
+//! [`crate::watchdog`] did about it getting stuck.
+
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Paragraph},
+};
+
+use crate::{
+    adb::{AdbOptions, Transport},
+    app_event::AppEvent,
+    app_event_sender::AppEventSender,
+    custom_terminal::Frame,
+    discovery,
+    farm::{self, bot_state, career_turn, split_priority},
+    screen_view::format_age,
+    session::LogLine,
+    tui,
+    watchdog::{self, Watchdog, WatchdogConfig},
+};
+
+/// Lines kept per instance, so the LOGS tab has history when switched to it.
+const MAX_BACKLOG: usize = 8192;
+/// Window the log rate is measured over.
+const RATE_WINDOW: Duration = Duration::from_mins(1);
+const TILE_WIDTH: u16 = 42;
+const TILE_HEIGHT: u16 = 8;
+
+struct Instance {
+    serial: String,
+    /// Emulator family and instance index, for instances on local ports.
+    label: Option<String>,
+    /// State reported by the adb server, or `gone` once it stopped listing the device.
+    state: String,
+    online_since: Option<Instant>,
+    /// Stop flag of the log stream, if one is running.
+    streaming: Option<Arc<AtomicBool>>,
+    stream_error: Option<String>,
+    bot_state: Option<String>,
+    turn: Option<u32>,
+    last_error: Option<(SystemTime, String)>,
+    /// Arrival of every line within the rate window.
+    arrivals: VecDeque<Instant>,
+    backlog: VecDeque<LogLine>,
+    watchdog: Watchdog,
+    /// The watchdog's last action, and whether it failed.
+    watchdog_note: Option<(SystemTime, String, bool)>,
+}
+
+impl Instance {
+    fn new(serial: String) -> Self {
+        Self {
+            label: instance_label(&serial),
+            serial,
+            state: String::new(),
+            online_since: None,
+            streaming: None,
+            stream_error: None,
+            bot_state: None,
+            turn: None,
+            last_error: None,
+            arrivals: VecDeque::new(),
+            backlog: VecDeque::new(),
+            watchdog: Watchdog::default(),
+            watchdog_note: None,
+        }
+    }
+
+    fn stop_streaming(&mut self) {
+        if let Some(stop) = self.streaming.take() {
+            stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn lines_per_minute(&self) -> usize {
+        let now = Instant::now();
+        self.arrivals.iter().filter(|&&at| now.duration_since(at) < RATE_WINDOW).count()
+    }
+}
+
+/// `MuMu #2` for the third `MuMu` instance, from the port in the serial.
+fn instance_label(serial: &str) -> Option<String> {
+    let port = match serial.strip_prefix("emulator-") {
+        // Emulators are named after their console port; adbd listens on the next one.
+        Some(console) => console.parse::<u16>().ok()?.checked_add(1)?,
+        None => serial.rsplit_once(':')?.1.parse().ok()?,
+    };
+    let (family, index) = discovery::instance_index(port)?;
+    Some(format!("{family} #{index}"))
+}
+
+fn format_uptime(uptime: Duration) -> String {
+    let secs = uptime.as_secs();
+    match secs {
+        0..3600 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+pub struct FarmView {
+    adb: AdbOptions,
+    app_event_tx: AppEventSender,
+    /// Ordered by serial so tiles keep their place.
+    instances: Vec<Instance>,
+    selected: usize,
+    /// Serial of the instance the LOGS and TRAINING tabs show; `None` for the session's device.
+    shown: Option<String>,
+    error: Option<String>,
+    /// Tiles per row at the last render, for moving up and down.
+    columns: usize,
+    watchdog_path: PathBuf,
+    watchdog_config: WatchdogConfig,
+    watchdog_error: Option<String>,
+    /// Where remediation snapshots go.
+    snapshot_dir: PathBuf,
+}
+
+impl FarmView {
+    pub fn new(adb: AdbOptions, app_event_tx: AppEventSender, watchdog_path: PathBuf, snapshot_dir: PathBuf) -> Self {
+        let (watchdog_config, watchdog_error) = match WatchdogConfig::load(&watchdog_path) {
+            Ok(config) => (config, None),
+            Err(err) => (WatchdogConfig::default(), Some(format!("{}: {err}", watchdog_path.display()))),
+        };
+        // Devices are only tracked through the adb server, see `App::run`.
+        let error = (adb.transport != Transport::Server).then(|| "needs the adb server, not --adbd".to_string());
+        Self {
+            adb,
+            app_event_tx,
+            instances: Vec::new(),
+            selected: 0,
+            shown: None,
+            error,
+            columns: 1,
+            watchdog_path,
+            watchdog_config,
+            watchdog_error,
+            snapshot_dir,
+        }
+    }
+
+    pub fn shown(&self) -> Option<&str> {
+        self.shown.as_deref()
+    }
+
+    /// The lines kept for `serial`, oldest first.
+    pub fn backlog(&self, serial: &str) -> Vec<LogLine> {
+        self.instances
+            .iter()
+            .find(|instance| instance.serial == serial)
+            .map(|instance| instance.backlog.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// The server's device list changed: start streaming logs of devices that came online and
+    /// stop those that went away. Devices that disappear keep their tile.
+    pub fn on_devices(&mut self, result: Result<Vec<(String, String)>, String>) {
+        let devices = match result {
+            Ok(devices) => devices,
+            Err(err) => {
+                self.error = Some(err);
+                return;
+            }
+        };
+        self.error = None;
+        for instance in &mut self.instances {
+            if !devices.iter().any(|(serial, _)| *serial == instance.serial) {
+                instance.state = "gone".to_string();
+                instance.online_since = None;
+                instance.stop_streaming();
+            }
+        }
+        for (serial, state) in devices {
+            let index = match self.instances.binary_search_by(|instance| instance.serial.cmp(&serial)) {
+                Ok(index) => index,
+                Err(index) => {
+                    self.instances.insert(index, Instance::new(serial.clone()));
+                    if self.selected >= index && self.instances.len() > 1 {
+                        self.selected += 1;
+                    }
+                    index
+                }
+            };
+            let instance = &mut self.instances[index];
+            if state == "device" {
+                if instance.online_since.is_none() {
+                    instance.online_since = Some(Instant::now());
+                    instance.watchdog.reset();
+                }
+                if instance.streaming.is_none() {
+                    let stop = Arc::new(AtomicBool::new(false));
+                    farm::spawn_logcat(&self.adb, serial, self.app_event_tx.clone(), stop.clone());
+                    instance.streaming = Some(stop);
+                    instance.stream_error = None;
+                }
+            } else {
+                instance.online_since = None;
+                instance.stop_streaming();
+            }
+            instance.state = state;
+        }
+    }
+
+    /// Take in a line of `serial`'s log. Returns whether it belongs on the LOGS tab.
+    pub fn on_log_line(&mut self, serial: &str, line: &LogLine) -> bool {
+        let Some(instance) = self.instances.iter_mut().find(|instance| instance.serial == serial) else {
+            return false;
+        };
+        instance.stream_error = None;
+        let now = Instant::now();
+        instance.arrivals.push_back(now);
+        while instance.arrivals.front().is_some_and(|&at| now.duration_since(at) >= RATE_WINDOW) {
+            instance.arrivals.pop_front();
+        }
+        if let Some(state) = bot_state(&line.text) {
+            instance.watchdog.on_state(state);
+            instance.bot_state = Some(state.to_string());
+        }
+        if let Some(turn) = career_turn(&line.text) {
+            instance.watchdog.on_turn(turn);
+            instance.turn = Some(turn);
+        }
+        if let Some(('E' | 'F', message)) = split_priority(&line.text) {
+            instance.last_error = Some((line.received_at, message.to_string()));
+        }
+        instance.backlog.push_back(line.clone());
+        if instance.backlog.len() > MAX_BACKLOG {
+            instance.backlog.pop_front();
+        }
+        self.shown.as_deref() == Some(serial)
+    }
+
+    pub fn on_log_ended(&mut self, serial: &str, error: String) {
+        if let Some(instance) = self.instances.iter_mut().find(|instance| instance.serial == serial) {
+            instance.stream_error = Some(error);
+        }
+    }
+
+    /// Start the remediation chain on online instances whose bot is stuck. Returns the events to
+    /// record in the session.
+    pub fn on_tick(&mut self) -> Vec<String> {
+        let mut events = Vec::new();
+        for instance in &mut self.instances {
+            if instance.online_since.is_none() {
+                continue;
+            }
+            let Some(reason) = instance.watchdog.check(&self.watchdog_config) else {
+                continue;
+            };
+            let steps = self.watchdog_config.remediation.iter().map(ToString::to_string).collect::<Vec<_>>();
+            if steps.is_empty() {
+                events.push(format!("watchdog: {} stuck, {reason}; no remediation configured", instance.serial));
+                instance.watchdog.finish_remediation();
+                continue;
+            }
+            events.push(format!("watchdog: {} stuck, {reason}; running {}", instance.serial, steps.join(", ")));
+            instance.watchdog_note = Some((SystemTime::now(), format!("stuck, {reason}"), false));
+            let progressed = instance.watchdog.start_remediation();
+            watchdog::spawn_remediation(
+                &self.adb,
+                self.app_event_tx.clone(),
+                instance.serial.clone(),
+                self.watchdog_config.clone(),
+                self.snapshot_dir.clone(),
+                instance.backlog.iter().cloned().collect(),
+                progressed,
+            );
+        }
+        events
+    }
+
+    /// A remediation step ran on `serial`. Returns the event to record in the session.
+    pub fn on_watchdog_action(&mut self, serial: &str, result: Result<String, String>) -> String {
+        let failed = result.is_err();
+        let message = result.unwrap_or_else(|err| err);
+        if let Some(instance) = self.instances.iter_mut().find(|instance| instance.serial == serial) {
+            instance.watchdog_note = Some((SystemTime::now(), message.clone(), failed));
+        }
+        format!("watchdog: {serial} {message}")
+    }
+
+    /// The remediation chain on `serial` ended. Returns the event to record in the session.
+    pub fn on_watchdog_finished(&mut self, serial: &str) -> String {
+        if let Some(instance) = self.instances.iter_mut().find(|instance| instance.serial == serial) {
+            instance.watchdog.finish_remediation();
+        }
+        format!("watchdog: {serial} remediation finished")
+    }
+
+    fn reload_watchdog(&mut self) {
+        match WatchdogConfig::load(&self.watchdog_path) {
+            Ok(config) => {
+                self.watchdog_config = config;
+                self.watchdog_error = None;
+            }
+            Err(err) => self.watchdog_error = Some(format!("{}: {err}", self.watchdog_path.display())),
+        }
+    }
+
+    /// Handle a key on the FARM tab. Returns whether the instance shown on the LOGS and TRAINING
+    /// tabs changed, see [`Self::shown`].
+    pub fn handle_key_event(&mut self, tui: &tui::Tui, key_event: KeyEvent) -> bool {
+        let last = self.instances.len().saturating_sub(1);
+        match key_event.code {
+            KeyCode::Left | KeyCode::Char('h') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Right | KeyCode::Char('l') => self.selected = (self.selected + 1).min(last),
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(self.columns),
+            KeyCode::Down | KeyCode::Char('j') => self.selected = (self.selected + self.columns).min(last),
+            KeyCode::Char('R') => {
+                let Some(instance) = self.instances.get_mut(self.selected) else {
+                    return false;
+                };
+                instance.watchdog.request();
+                self.app_event_tx.send(AppEvent::WatchdogTick);
+            }
+            KeyCode::Char('w') => self.reload_watchdog(),
+            KeyCode::Enter => {
+                let Some(instance) = self.instances.get(self.selected) else {
+                    return false;
+                };
+                self.shown = Some(instance.serial.clone());
+                tui.frame_requester().schedule_frame();
+                return true;
+            }
+            // Back to the session's own device.
+            KeyCode::Esc if self.shown.is_some() => {
+                self.shown = None;
+                tui.frame_requester().schedule_frame();
+                return true;
+            }
+            _ => return false,
+        }
+        tui.frame_requester().schedule_frame();
+        false
+    }
+
+    fn status_line(&self) -> Line<'static> {
+        let online = self.instances.iter().filter(|instance| instance.state == "device").count();
+        let mut line = Line::from(format!(
+            " {online}/{} online  arrows/hjkl: move  enter: show in LOGS and TRAINING  R: remediate  w: reload watchdog",
+            self.instances.len()
+        ))
+        .dim();
+        if self.shown.is_some() {
+            line.push_span(Span::from("  esc: back to the session"));
+        }
+        let remediating = self.instances.iter().filter(|instance| instance.watchdog.is_remediating()).count();
+        if remediating > 0 {
+            line.push_span(Span::from(format!(" · {remediating} stuck")).yellow());
+        }
+        for err in [&self.error, &self.watchdog_error].into_iter().flatten() {
+            line.push_span(Span::from(format!(" · {err}")).red());
+        }
+        line
+    }
+
+    fn tile_lines(instance: &Instance) -> Vec<Line<'static>> {
+        let connection = match (&instance.online_since, instance.state.as_str()) {
+            (Some(since), _) => Span::from(format!("online {}", format_uptime(since.elapsed()))).green(),
+            (None, "gone") => Span::from("disconnected").dim(),
+            (None, state) => Span::from(state.to_string()).red(),
+        };
+        let mut first = Line::from(vec![connection]);
+        if let Some(err) = &instance.stream_error {
+            first.push_span(Span::from(format!(" · no logs: {err}")).red());
+        }
+        let field = |name: &str, value: Option<String>| {
+            Line::from(vec![
+                Span::from(format!("{name:<7}")).dim(),
+                value.map_or_else(|| Span::from("–").dim(), Span::from),
+            ])
+        };
+        let mut error = Line::from(Span::from(format!("{:<7}", "error")).dim());
+        match &instance.last_error {
+            Some((at, message)) => {
+                error.push_span(Span::from(format!("{} ", format_age(*at))).dim());
+                error.push_span(Span::from(message.clone()).red());
+            }
+            None => error.push_span(Span::from("–").dim()),
+        }
+        let mut watchdog = Line::from(Span::from(format!("{:<7}", "watch")).dim());
+        match &instance.watchdog_note {
+            Some((at, note, failed)) => {
+                watchdog.push_span(Span::from(format!("{} ", format_age(*at))).dim());
+                watchdog.push_span(match (failed, instance.watchdog.is_remediating()) {
+                    (true, _) => Span::from(note.clone()).red(),
+                    (false, true) => Span::from(note.clone()).yellow(),
+                    (false, false) => Span::from(note.clone()),
+                });
+            }
+            None => watchdog.push_span(Span::from("–").dim()),
+        }
+        vec![
+            first,
+            field("state", instance.bot_state.clone()),
+            field("turn", instance.turn.map(|turn| turn.to_string())),
+            field("logs", Some(format!("{}/min", instance.lines_per_minute()))),
+            error,
+            watchdog,
+        ]
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect) {
+        let [status_area, grid_area] = Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+        frame.render_widget(self.status_line(), status_area);
+        if self.instances.is_empty() {
+            let message = if self.error.is_some() {
+                "Cannot follow the adb server's devices"
+            } else {
+                "Waiting for devices; emulator instances found by the DEVICE tab's scan show up here"
+            };
+            frame.render_widget(Paragraph::new(Line::from(message).italic()).block(Block::bordered()), grid_area);
+            return;
+        }
+        self.columns = usize::from((grid_area.width / TILE_WIDTH).max(1));
+        let rows = usize::from((grid_area.height / TILE_HEIGHT).max(1));
+        // Scrolled by whole rows so the selected tile stays visible.
+        let first_row = (self.selected / self.columns).saturating_sub(rows - 1);
+        let tile_width = grid_area.width / u16::try_from(self.columns).unwrap_or(u16::MAX);
+        for (index, instance) in
+            self.instances.iter().enumerate().skip(first_row * self.columns).take(rows * self.columns)
+        {
+            let (row, column) = (index / self.columns - first_row, index % self.columns);
+            let tile_area = Rect::new(
+                grid_area.x + u16::try_from(column).unwrap_or(u16::MAX) * tile_width,
+                grid_area.y + u16::try_from(row).unwrap_or(u16::MAX) * TILE_HEIGHT,
+                tile_width,
+                TILE_HEIGHT,
+            )
+            .intersection(grid_area);
+            let mut title = Line::from(format!(" {} ", instance.serial));
+            if let Some(label) = &instance.label {
+                title.push_span(Span::from(format!("{label} ")).cyan());
+            }
+            if self.shown.as_deref() == Some(instance.serial.as_str()) {
+                title.push_span(Span::from("● ").green());
+            }
+            let mut block = Block::bordered().title(title);
+            if index == self.selected {
+                block = block.border_style(Style::default().yellow());
+            }
+            frame.render_widget(Paragraph::new(Self::tile_lines(instance)).block(block), tile_area);
+        }
+    }
+}
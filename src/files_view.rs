@@ -0,0 +1,537 @@
+//! FILES tab: browse the device file system through the sync service, preview text files and
+//! copy files or whole directories between the device and `<data dir>/device_files`.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant, UNIX_EPOCH},
+};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Gauge, List, ListItem, ListState, Paragraph},
+};
+
+use crate::{
+    adb::AdbOptions,
+    adb_sync::{DirEntry, SyncConnection},
+    ansi::ansi_line,
+    app_event::AppEvent,
+    app_event_sender::AppEventSender,
+    custom_terminal::Frame,
+    pager_overlay::StaticOverlay,
+    tui,
+};
+
+/// Where browsing starts, and where `~` goes back to.
+const HOME: &str = "/sdcard";
+/// Files larger than this are not loaded for preview.
+const PREVIEW_LIMIT: u32 = 1024 * 1024;
+/// Minimum time between progress updates sent to the UI.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A running pull or push.
+struct Transfer {
+    label: String,
+    /// Pushes refresh the listing when they finish.
+    push: bool,
+    done: u64,
+    total: u64,
+}
+
+pub struct FilesView {
+    adb: AdbOptions,
+    app_event_tx: AppEventSender,
+    local_dir: PathBuf,
+    /// Directory whose entries are shown.
+    cwd: String,
+    entries: Vec<DirEntry>,
+    list_state: ListState,
+    /// Path of the listing or preview requested last; other responses are stale.
+    loading: Option<String>,
+    /// Entry to select once the pending listing arrives, when going back up.
+    reselect: Option<String>,
+    transfer: Option<Transfer>,
+    pager: Option<StaticOverlay>,
+    /// Local path being typed for a push.
+    push_prompt: Option<String>,
+    notice: Option<String>,
+    error: Option<String>,
+}
+
+impl FilesView {
+    pub fn new(adb: AdbOptions, app_event_tx: AppEventSender, local_dir: PathBuf) -> Self {
+        Self {
+            adb,
+            app_event_tx,
+            local_dir,
+            cwd: HOME.to_string(),
+            entries: Vec::new(),
+            list_state: ListState::default(),
+            loading: None,
+            reselect: None,
+            transfer: None,
+            pager: None,
+            push_prompt: None,
+            notice: None,
+            error: None,
+        }
+    }
+
+    /// List the starting directory the first time the tab is shown.
+    pub fn on_shown(&mut self) {
+        if self.entries.is_empty() && self.loading.is_none() && self.error.is_none() {
+            self.open(self.cwd.clone());
+        }
+    }
+
+    /// List `path` if it is a directory, or preview it otherwise.
+    fn open(&mut self, path: String) {
+        self.loading = Some(path.clone());
+        self.notice = None;
+        spawn_open(self.adb.clone(), self.app_event_tx.clone(), path);
+    }
+
+    fn selected(&self) -> Option<&DirEntry> {
+        self.entries.get(self.list_state.selected()?)
+    }
+
+    fn selected_path(&self) -> Option<String> {
+        self.selected().map(|entry| join(&self.cwd, &entry.name))
+    }
+
+    pub fn on_dir_listed(&mut self, path: String, result: Result<Vec<DirEntry>, String>) {
+        if self.loading.as_ref() != Some(&path) {
+            return;
+        }
+        self.loading = None;
+        match result {
+            Ok(mut entries) => {
+                entries.sort_by(|a, b| b.is_dir().cmp(&a.is_dir()).then_with(|| a.name.cmp(&b.name)));
+                let reselect = self.reselect.take();
+                let index = reselect.and_then(|name| entries.iter().position(|e| e.name == name));
+                self.list_state.select(index.or_else(|| (!entries.is_empty()).then_some(0)));
+                self.entries = entries;
+                self.cwd = path;
+                self.error = None;
+            }
+            Err(err) => self.error = Some(err),
+        }
+    }
+
+    pub fn on_preview(&mut self, path: String, result: Result<Vec<u8>, String>) {
+        if self.loading.as_ref() != Some(&path) {
+            return;
+        }
+        self.loading = None;
+        match result {
+            Ok(bytes) if bytes.contains(&0) => self.notice = Some(format!("{path} is binary, pull it instead")),
+            Ok(bytes) => {
+                let lines = String::from_utf8_lossy(&bytes).lines().map(ansi_line).collect();
+                self.pager = Some(StaticOverlay::with_title(lines, path));
+            }
+            Err(err) => self.notice = Some(err),
+        }
+    }
+
+    pub fn on_transfer_progress(&mut self, done: u64, total: u64) {
+        if let Some(transfer) = &mut self.transfer {
+            transfer.done = done;
+            transfer.total = total;
+        }
+    }
+
+    pub fn on_transfer_finished(&mut self, result: Result<String, String>) {
+        let pushed = self.transfer.take().is_some_and(|transfer| transfer.push);
+        self.notice = Some(result.unwrap_or_else(|err| format!("transfer failed: {err}")));
+        if pushed {
+            self.reselect = self.selected().map(|entry| entry.name.clone());
+            self.open(self.cwd.clone());
+        }
+    }
+
+    fn start_transfer(&mut self, label: String, push: bool) -> bool {
+        if self.transfer.is_some() {
+            self.notice = Some("a transfer is already running".to_string());
+            return false;
+        }
+        self.transfer = Some(Transfer {
+            label,
+            push,
+            done: 0,
+            total: 0,
+        });
+        true
+    }
+
+    fn pull_selected(&mut self) {
+        let Some(remote) = self.selected_path() else {
+            return;
+        };
+        let name = self.selected().map(|entry| entry.name.clone()).unwrap_or_default();
+        let local = self.local_dir.join(&name);
+        if self.start_transfer(format!("pull {remote}"), false) {
+            spawn_pull(self.adb.clone(), self.app_event_tx.clone(), remote, local);
+        }
+    }
+
+    fn push(&mut self, local: &str) {
+        let local = local.trim();
+        // Joining nothing would name the whole local directory.
+        if local.is_empty() {
+            self.notice = Some("nothing to push".to_string());
+            return;
+        }
+        let local = self.local_dir.join(local);
+        let Some(name) = local.file_name().map(|name| name.to_string_lossy().into_owned()) else {
+            self.notice = Some("nothing to push".to_string());
+            return;
+        };
+        let remote = join(&self.cwd, &name);
+        if self.start_transfer(format!("push {}", local.display()), true) {
+            spawn_push(self.adb.clone(), self.app_event_tx.clone(), local, remote);
+        }
+    }
+
+    fn go_up(&mut self) {
+        let (parent, name) = match self.cwd.trim_end_matches('/').rsplit_once('/') {
+            Some(("", name)) => ("/".to_string(), name.to_string()),
+            Some((parent, name)) => (parent.to_string(), name.to_string()),
+            None => return,
+        };
+        if name.is_empty() {
+            return;
+        }
+        self.reselect = Some(name);
+        self.open(parent);
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let last = self.entries.len().saturating_sub(1);
+        let index = self.list_state.selected().unwrap_or_default().saturating_add_signed(delta).min(last);
+        self.list_state.select((!self.entries.is_empty()).then_some(index));
+    }
+
+    fn handle_push_prompt_key(&mut self, key_event: KeyEvent) {
+        let Some(input) = &mut self.push_prompt else {
+            return;
+        };
+        match key_event.code {
+            KeyCode::Enter => {
+                let input = std::mem::take(input);
+                self.push_prompt = None;
+                self.push(&input);
+            }
+            KeyCode::Esc => self.push_prompt = None,
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            _ => {}
+        }
+    }
+
+    pub fn handle_key_event(&mut self, tui: &tui::Tui, key_event: KeyEvent) {
+        const PAGE: isize = 20;
+        if let Some(pager) = &mut self.pager {
+            pager.handle_key_event(tui, key_event);
+            if pager.is_done() {
+                self.pager = None;
+            }
+            tui.frame_requester().schedule_frame();
+            return;
+        }
+        if self.push_prompt.is_some() {
+            self.handle_push_prompt_key(key_event);
+            tui.frame_requester().schedule_frame();
+            return;
+        }
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-PAGE),
+            KeyCode::PageDown => self.move_selection(PAGE),
+            KeyCode::Home | KeyCode::Char('g') => self.move_selection(isize::MIN),
+            KeyCode::End | KeyCode::Char('G') => self.move_selection(isize::MAX),
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => {
+                if let Some(path) = self.selected_path() {
+                    self.open(path);
+                }
+            }
+            KeyCode::Backspace | KeyCode::Left | KeyCode::Char('h') => self.go_up(),
+            KeyCode::Char('~') => self.open(HOME.to_string()),
+            KeyCode::Char('r') => {
+                self.reselect = self.selected().map(|entry| entry.name.clone());
+                self.open(self.cwd.clone());
+            }
+            KeyCode::Char('p') => self.pull_selected(),
+            KeyCode::Char('u') => self.push_prompt = Some(String::new()),
+            _ => return,
+        }
+        tui.frame_requester().schedule_frame();
+    }
+
+    fn status_line(&self) -> Line<'static> {
+        if let Some(input) = &self.push_prompt {
+            return Line::from(vec![
+                Span::from(format!(" push from {}/", self.local_dir.display())).bold(),
+                Span::from(format!("{input}▏")),
+                Span::from(format!("  enter: push into {}  esc: cancel", self.cwd)).dim(),
+            ]);
+        }
+        let mut line = Line::from(format!(
+            " enter: open  backspace: up  ~: {HOME}  r: refresh  p: pull to {}  u: push",
+            self.local_dir.display()
+        ))
+        .dim();
+        if let Some(path) = &self.loading {
+            line.push_span(Span::from(format!(" · loading {path}…")).yellow());
+        }
+        if let Some(notice) = &self.notice {
+            line.push_span(Span::from(format!(" · {notice}")).green());
+        }
+        line
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect) {
+        if let Some(pager) = &mut self.pager {
+            pager.render(area, frame.buffer_mut());
+            return;
+        }
+        let transfer_height = u16::from(self.transfer.is_some());
+        let [status_area, list_area, transfer_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0), Constraint::Length(transfer_height)])
+                .areas(area);
+        frame.render_widget(self.status_line(), status_area);
+
+        let block = Block::bordered().title(format!(" {} ", self.cwd));
+        if let Some(error) = &self.error {
+            let inner = block.inner(list_area);
+            frame.render_widget(block, list_area);
+            frame.render_widget(Paragraph::new(Line::from(error.clone()).red()), inner);
+        } else if self.entries.is_empty() {
+            let inner = block.inner(list_area);
+            frame.render_widget(block, list_area);
+            frame.render_widget(Paragraph::new(Line::from("Empty directory").italic()), inner);
+        } else {
+            let items: Vec<ListItem> = self
+                .entries
+                .iter()
+                .map(|entry| {
+                    let (suffix, size) = if entry.is_dir() {
+                        ("/", String::new())
+                    } else if entry.is_symlink() {
+                        ("@", String::new())
+                    } else {
+                        ("", human_size(u64::from(entry.size)))
+                    };
+                    let name = Span::from(format!("{}{suffix}", entry.name));
+                    let name = if entry.is_dir() {
+                        name.blue().bold()
+                    } else if entry.is_symlink() {
+                        name.cyan()
+                    } else {
+                        name
+                    };
+                    ListItem::new(Line::from(vec![Span::from(format!("{size:>9}  ")).dim(), name]))
+                })
+                .collect();
+            let list = List::new(items).block(block).highlight_style(Style::default().reversed());
+            frame.render_stateful_widget(list, list_area, &mut self.list_state);
+        }
+
+        if let Some(transfer) = &self.transfer {
+            #[allow(clippy::cast_precision_loss)]
+            let ratio = if transfer.total == 0 {
+                0.0
+            } else {
+                (transfer.done as f64 / transfer.total as f64).clamp(0.0, 1.0)
+            };
+            let label = format!("{} · {} / {}", transfer.label, human_size(transfer.done), human_size(transfer.total));
+            frame.render_widget(Gauge::default().ratio(ratio).label(label).green(), transfer_area);
+        }
+    }
+}
+
+/// `dir/name` with a single separator.
+fn join(dir: &str, name: &str) -> String {
+    format!("{}/{name}", dir.trim_end_matches('/'))
+}
+
+fn human_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "K", "M", "G"];
+    #[allow(clippy::cast_precision_loss)]
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes}B")
+    } else {
+        format!("{size:.1}{}", UNITS[unit])
+    }
+}
+
+/// Sends throttled [`AppEvent::FileTransferProgress`] updates for a transfer of `total` bytes.
+struct Progress {
+    app_event_tx: AppEventSender,
+    total: u64,
+    /// Bytes of the files already finished.
+    finished: u64,
+    last_sent: Option<Instant>,
+}
+
+impl Progress {
+    fn update(&mut self, file_done: u64) {
+        if self.last_sent.is_some_and(|at| at.elapsed() < PROGRESS_INTERVAL) {
+            return;
+        }
+        self.last_sent = Some(Instant::now());
+        self.app_event_tx.send(AppEvent::FileTransferProgress {
+            done: self.finished + file_done,
+            total: self.total,
+        });
+    }
+}
+
+/// List `path` as [`AppEvent::RemoteDirListed`] if it is a directory (or a link to one), or load
+/// it for preview as [`AppEvent::FilePreview`].
+fn spawn_open(adb: AdbOptions, app_event_tx: AppEventSender, path: String) {
+    thread::spawn(move || {
+        let mut sync = match SyncConnection::connect(&adb) {
+            Ok(sync) => sync,
+            Err(err) => {
+                app_event_tx.send(AppEvent::RemoteDirListed {
+                    path,
+                    result: Err(err.to_string()),
+                });
+                return;
+            }
+        };
+        // The trailing slash makes `STAT` resolve symlinks, which it otherwise reports as is.
+        let is_dir = path == "/" || sync.stat(&format!("{path}/")).is_ok_and(|stat| stat.mode != 0);
+        let event = if is_dir {
+            AppEvent::RemoteDirListed {
+                result: sync.list(&path).map_err(|err| err.to_string()),
+                path,
+            }
+        } else {
+            AppEvent::FilePreview {
+                result: preview(&mut sync, &path).map_err(|err| err.to_string()),
+                path,
+            }
+        };
+        app_event_tx.send(event);
+    });
+}
+
+fn preview(sync: &mut SyncConnection, path: &str) -> io::Result<Vec<u8>> {
+    let size = sync.stat(path)?.size;
+    if size > PREVIEW_LIMIT {
+        return Err(io::Error::other(format!("{path} is too large to preview, pull it instead")));
+    }
+    let mut bytes = Vec::with_capacity(size as usize);
+    sync.pull(path, &mut bytes, |_| {})?;
+    Ok(bytes)
+}
+
+/// Every regular file under `remote`, or `remote` itself if it is a file, with its local path.
+fn remote_files(sync: &mut SyncConnection, remote: &str, local: &Path) -> io::Result<Vec<(String, PathBuf, u64)>> {
+    let stat = sync.stat(&format!("{remote}/"))?;
+    if stat.mode == 0 {
+        let size = sync.stat(remote)?.size;
+        return Ok(vec![(remote.to_string(), local.to_path_buf(), u64::from(size))]);
+    }
+    let mut files = Vec::new();
+    for entry in sync.list(remote)? {
+        let (remote, local) = (join(remote, &entry.name), local.join(&entry.name));
+        if entry.is_dir() {
+            files.extend(remote_files(sync, &remote, &local)?);
+        } else if !entry.is_symlink() {
+            files.push((remote, local, u64::from(entry.size)));
+        }
+    }
+    Ok(files)
+}
+
+/// Every regular file under `local`, or `local` itself if it is a file, with its remote path.
+fn local_files(local: &Path, remote: &str) -> io::Result<Vec<(PathBuf, String, u64)>> {
+    let metadata = fs::metadata(local)?;
+    if metadata.is_file() {
+        return Ok(vec![(local.to_path_buf(), remote.to_string(), metadata.len())]);
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(local)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            files.extend(local_files(&entry.path(), &join(remote, &name))?);
+        } else if file_type.is_file() {
+            files.push((entry.path(), join(remote, &name), entry.metadata()?.len()));
+        }
+    }
+    Ok(files)
+}
+
+/// Pull `remote`, a file or a directory tree, to `local` on a worker thread.
+fn spawn_pull(adb: AdbOptions, app_event_tx: AppEventSender, remote: String, local: PathBuf) {
+    thread::spawn(move || {
+        let result = SyncConnection::connect(&adb).and_then(|mut sync| {
+            let files = remote_files(&mut sync, &remote, &local)?;
+            let mut progress = Progress {
+                app_event_tx: app_event_tx.clone(),
+                total: files.iter().map(|(_, _, size)| size).sum(),
+                finished: 0,
+                last_sent: None,
+            };
+            for (remote, local, size) in &files {
+                if let Some(dir) = local.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                let mut file = io::BufWriter::new(fs::File::create(local)?);
+                sync.pull(remote, &mut file, |done| progress.update(done))?;
+                progress.finished += size;
+            }
+            Ok(format!("pulled {} file(s) to {}", files.len(), local.display()))
+        });
+        app_event_tx.send(AppEvent::FileTransferFinished(result.map_err(|err| err.to_string())));
+    });
+}
+
+/// Push `local`, a file or a directory tree, to `remote` on a worker thread. Missing remote
+/// directories are created by the device.
+fn spawn_push(adb: AdbOptions, app_event_tx: AppEventSender, local: PathBuf, remote: String) {
+    thread::spawn(move || {
+        let result = local_files(&local, &remote).and_then(|files| {
+            let mut sync = SyncConnection::connect(&adb)?;
+            let mut progress = Progress {
+                app_event_tx: app_event_tx.clone(),
+                total: files.iter().map(|(_, _, size)| size).sum(),
+                finished: 0,
+                last_sent: None,
+            };
+            for (local, remote, size) in &files {
+                let mut file = fs::File::open(local)?;
+                let mtime = file
+                    .metadata()?
+                    .modified()?
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs()
+                    .try_into()
+                    .unwrap_or(u32::MAX);
+                sync.push(&mut file, remote, 0o100_644, mtime, |done| progress.update(done))?;
+                progress.finished += size;
+            }
+            Ok(format!("pushed {} file(s) to {remote}", files.len()))
+        });
+        app_event_tx.send(AppEvent::FileTransferFinished(result.map_err(|err| err.to_string())));
+    });
+}
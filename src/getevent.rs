@@ -0,0 +1,185 @@
+//! Raw input events read from the device with `getevent -qlt`.
+//!
+//! Every event line looks like
+//!
+//! ```text
+//! [   74.361408] /dev/input/event1: EV_ABS       ABS_MT_POSITION_X    00000227
+//! ```
+//!
+//! with the kernel timestamp in seconds, the input device, and the symbolic event type, code and
+//! value. Codes without a symbolic name are printed as hex.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, atomic::AtomicBool},
+    thread,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    adb::{AdbOptions, LineSink, shell_output},
+    app_event::AppEvent,
+    app_event_sender::AppEventSender,
+};
+
+/// Event type and code names `getevent -l` prints for touchscreens and keys, with their values
+/// from `linux/input-event-codes.h`. Anything else is printed, and stored, as hex.
+const EVENT_TYPES: &[(&str, u16)] = &[("EV_SYN", 0x00), ("EV_KEY", 0x01), ("EV_REL", 0x02), ("EV_ABS", 0x03)];
+const EVENT_CODES: &[(&str, u16)] = &[
+    ("SYN_REPORT", 0x00),
+    ("SYN_MT_REPORT", 0x02),
+    ("ABS_X", 0x00),
+    ("ABS_Y", 0x01),
+    ("ABS_PRESSURE", 0x18),
+    ("ABS_MT_SLOT", 0x2f),
+    ("ABS_MT_TOUCH_MAJOR", 0x30),
+    ("ABS_MT_TOUCH_MINOR", 0x31),
+    ("ABS_MT_WIDTH_MAJOR", 0x32),
+    ("ABS_MT_WIDTH_MINOR", 0x33),
+    ("ABS_MT_ORIENTATION", 0x34),
+    ("ABS_MT_POSITION_X", 0x35),
+    ("ABS_MT_POSITION_Y", 0x36),
+    ("ABS_MT_TOOL_TYPE", 0x37),
+    ("ABS_MT_TRACKING_ID", 0x39),
+    ("ABS_MT_PRESSURE", 0x3a),
+    ("BTN_TOOL_FINGER", 0x145),
+    ("BTN_TOUCH", 0x14a),
+    ("KEY_BACK", 0x9e),
+    ("KEY_HOMEPAGE", 0xac),
+    ("KEY_VOLUMEDOWN", 0x72),
+    ("KEY_VOLUMEUP", 0x73),
+    ("KEY_POWER", 0x74),
+];
+
+fn lookup(table: &[(&str, u16)], name: &str) -> Option<u16> {
+    table
+        .iter()
+        .find(|(label, _)| *label == name)
+        .map(|&(_, value)| value)
+        .or_else(|| u16::from_str_radix(name, 16).ok())
+}
+
+/// One input event as reported by the kernel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputEvent {
+    /// Kernel timestamp in seconds. Only differences between events are meaningful.
+    pub time: f64,
+    pub device: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub code: String,
+    pub value: i32,
+}
+
+impl InputEvent {
+    /// Parse one line of `getevent -lt` output. Device listings and other noise yield `None`.
+    pub fn parse(line: &str) -> Option<Self> {
+        let (time, rest) = line.trim().strip_prefix('[')?.split_once(']')?;
+        let (device, rest) = rest.trim().split_once(": ")?;
+        let mut fields = rest.split_whitespace();
+        let (kind, code, value) = (fields.next()?, fields.next()?, fields.next()?);
+        let value = match value {
+            "DOWN" => 1,
+            "UP" => 0,
+            // Negative values such as a released tracking id (-1) come out as 32-bit hex.
+            #[allow(clippy::cast_possible_wrap)]
+            hex => u32::from_str_radix(hex, 16).ok()? as i32,
+        };
+        Some(Self {
+            time: time.trim().parse().ok()?,
+            device: device.to_string(),
+            kind: kind.to_string(),
+            code: code.to_string(),
+            value,
+        })
+    }
+
+    /// Numeric type and code for `sendevent`.
+    pub fn numeric(&self) -> Option<(u16, u16)> {
+        Some((lookup(EVENT_TYPES, &self.kind)?, lookup(EVENT_CODES, &self.code)?))
+    }
+
+    pub fn is(&self, kind: &str, code: &str) -> bool {
+        self.kind == kind && self.code == code
+    }
+}
+
+/// Run `getevent -qlt` on the device, handing every batch of parsed events to `on_events` until
+/// it returns `false` or `stop` is set. Blocks, so call it from a worker thread. `stop` is only
+/// noticed when the next event arrives.
+pub fn stream(
+    adb: &AdbOptions,
+    stop: &AtomicBool,
+    mut on_events: impl FnMut(Vec<InputEvent>) -> bool,
+) -> adb_client::Result<()> {
+    let mut sink = LineSink::new(stop, |lines| {
+        let events: Vec<InputEvent> = lines.iter().map(String::as_str).filter_map(InputEvent::parse).collect();
+        events.is_empty() || on_events(events)
+    });
+    adb.device().shell_command(&["getevent", "-qlt"], &mut sink).map(|_| ())
+}
+
+/// Largest `ABS_MT_POSITION_X`/`Y` (or `ABS_X`/`Y`) value of every touch device, from
+/// `getevent -lp`. Touch panels do not necessarily report screen pixels.
+pub fn touch_ranges(adb: &AdbOptions) -> HashMap<String, (u32, u32)> {
+    match shell_output(&mut adb.device(), &["getevent", "-lp"]) {
+        Ok(output) => parse_ranges(&String::from_utf8_lossy(&output)),
+        Err(err) => {
+            tracing::debug!("getevent -lp failed: {err}");
+            HashMap::new()
+        }
+    }
+}
+
+/// Parse device listings such as
+///
+/// ```text
+/// add device 2: /dev/input/event2
+///   events:
+///     ABS (0003): ABS_MT_POSITION_X     : value 0, min 0, max 1079, fuzz 0, flat 0, resolution 0
+///                 ABS_MT_POSITION_Y     : value 0, min 0, max 1919, fuzz 0, flat 0, resolution 0
+/// ```
+fn parse_ranges(text: &str) -> HashMap<String, (u32, u32)> {
+    let mut ranges = HashMap::new();
+    let mut device = None;
+    for line in text.lines() {
+        if let Some(rest) = line.strip_prefix("add device ") {
+            device = rest.split_once(": ").map(|(_, path)| path.trim().to_string());
+            continue;
+        }
+        let Some(device) = &device else {
+            continue;
+        };
+        let max = || {
+            line.split(", ").find_map(|field| field.trim().strip_prefix("max ")).and_then(|max| max.trim().parse().ok())
+        };
+        let entry = ranges.entry(device.clone());
+        if line.contains("ABS_MT_POSITION_X ") || line.contains("ABS_X ") {
+            if let Some(max) = max() {
+                entry.or_insert((0, 0)).0 = max;
+            }
+        } else if (line.contains("ABS_MT_POSITION_Y ") || line.contains("ABS_Y "))
+            && let Some(max) = max()
+        {
+            entry.or_insert((0, 0)).1 = max;
+        }
+    }
+    ranges.retain(|_, &mut (x, y)| x > 0 && y > 0);
+    ranges
+}
+
+/// Stream on a worker thread for the UI: first [`AppEvent::TouchRanges`], then every batch of
+/// events as [`AppEvent::InputEvents`], until `stop` is set.
+pub fn spawn_stream(adb: AdbOptions, app_event_tx: AppEventSender, stop: Arc<AtomicBool>) {
+    thread::spawn(move || {
+        app_event_tx.send(AppEvent::TouchRanges(touch_ranges(&adb)));
+        let result = stream(&adb, &stop, |events| {
+            app_event_tx.send(AppEvent::InputEvents(events));
+            !app_event_tx.app_event_tx.is_closed()
+        });
+        if let Err(err) = result {
+            tracing::debug!("getevent stream ended: {err}");
+        }
+    });
+}
@@ -0,0 +1,340 @@
+//! Crash and ANR detection in the incoming log stream.
+//!
+//! A report starts at a header line: `FATAL EXCEPTION` or any error from `AndroidRuntime` for Java
+//! crashes, the `*** *** ***` line debuggerd opens a tombstone with for native crashes, a
+//! `…Exception` logged by `Unity`, and `ANR in` from the activity manager. Every such report is a
+//! single log call split into lines sharing tag and priority, so the lines after the header that
+//! match both and arrive within [`REPORT_GAP`] are grouped into the same incident. Unity closes
+//! its reports with a `(Filename: …)` line.
+
+use std::time::{Duration, SystemTime};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::Rect,
+    style::Stylize,
+    text::{Line, Span},
+};
+
+use crate::{
+    custom_terminal::Frame, farm::split_logcat, pager_overlay::StaticOverlay, screen_view::format_age,
+    session::LogLine, tui,
+};
+
+/// Longest pause between two lines of the same report.
+const REPORT_GAP: Duration = Duration::from_secs(2);
+/// Incidents kept, oldest dropped first.
+const MAX_INCIDENTS: usize = 200;
+/// Lines kept per incident; long tombstones are cut here.
+const MAX_REPORT_LINES: usize = 400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IncidentKind {
+    JavaCrash,
+    NativeCrash,
+    UnityException,
+    Anr,
+}
+
+impl IncidentKind {
+    const fn label(self) -> &'static str {
+        match self {
+            Self::JavaCrash => "crash",
+            Self::NativeCrash => "native crash",
+            Self::UnityException => "Unity exception",
+            Self::Anr => "ANR",
+        }
+    }
+}
+
+fn header_kind(priority: Option<char>, tag: &str, message: &str) -> Option<IncidentKind> {
+    let first_word = message.split_whitespace().next().unwrap_or_default();
+    if message.starts_with("FATAL EXCEPTION") || (tag == "AndroidRuntime" && priority == Some('E')) {
+        Some(IncidentKind::JavaCrash)
+    } else if message.starts_with("*** *** ***") {
+        Some(IncidentKind::NativeCrash)
+    } else if tag == "Unity" && first_word.trim_end_matches(':').ends_with("Exception") {
+        Some(IncidentKind::UnityException)
+    } else if message.starts_with("ANR in ") {
+        Some(IncidentKind::Anr)
+    } else {
+        None
+    }
+}
+
+/// One crash or ANR report.
+#[derive(Debug, Clone)]
+struct Incident {
+    kind: IncidentKind,
+    lines: Vec<LogLine>,
+    priority: Option<char>,
+    tag: String,
+    /// No further lines are taken in.
+    closed: bool,
+}
+
+impl Incident {
+    fn at(&self) -> SystemTime {
+        self.lines[0].received_at
+    }
+
+    fn messages(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().map(|line| split_logcat(&line.text).2)
+    }
+
+    /// Whether `line` continues this report.
+    fn continues(&self, line: &LogLine, priority: Option<char>, tag: &str) -> bool {
+        let last = self.lines.last().map_or(line.received_at, |last| last.received_at);
+        !self.closed
+            && priority == self.priority
+            && tag == self.tag
+            && line.received_at.duration_since(last).unwrap_or_default() <= REPORT_GAP
+    }
+
+    /// The line that says what happened: the exception, the signal, the ANR reason.
+    fn summary(&self) -> String {
+        let header = self.messages().next().unwrap_or_default().to_string();
+        match self.kind {
+            IncidentKind::JavaCrash => self
+                .messages()
+                .find(|message| {
+                    !message.starts_with("FATAL EXCEPTION")
+                        && !message.starts_with("Process:")
+                        && !message.starts_with("at ")
+                        && (message.contains("Exception") || message.contains("Error"))
+                })
+                .map_or(header, ToString::to_string),
+            IncidentKind::NativeCrash => {
+                let process = self.messages().find_map(|message| {
+                    let (_, rest) = message.split_once(">>> ")?;
+                    Some(rest.split_once(" <<<")?.0)
+                });
+                let signal = self.messages().find(|message| message.starts_with("signal "));
+                match (process, signal) {
+                    (Some(process), Some(signal)) => format!("{process}: {signal}"),
+                    (Some(text), None) | (None, Some(text)) => text.to_string(),
+                    (None, None) => header,
+                }
+            }
+            IncidentKind::UnityException => header,
+            IncidentKind::Anr => match self.messages().find_map(|message| message.strip_prefix("Reason: ")) {
+                Some(reason) => format!("{header} · {reason}"),
+                None => header,
+            },
+        }
+    }
+}
+
+/// Incidents found in the log so far, the alert banner for new ones and the pager listing them.
+#[derive(Default)]
+pub struct Incidents {
+    reports: Vec<Incident>,
+    /// Number of incidents the user has already seen.
+    seen: usize,
+    pager: Option<StaticOverlay>,
+}
+
+impl Incidents {
+    /// Take in a line of the log. Returns whether it started or extended an incident.
+    pub fn on_log_line(&mut self, line: &LogLine) -> bool {
+        let (priority, tag, message) = split_logcat(&line.text);
+        let kind = header_kind(priority, tag, message);
+        if let Some(incident) = self.reports.last_mut()
+            && incident.continues(line, priority, tag)
+            // A crash in another thread right after the first one is its own report.
+            && !(kind == Some(IncidentKind::JavaCrash) && message.starts_with("FATAL EXCEPTION"))
+        {
+            if incident.lines.len() < MAX_REPORT_LINES {
+                incident.lines.push(line.clone());
+            }
+            incident.closed = incident.kind == IncidentKind::UnityException && message.starts_with("(Filename:");
+            return true;
+        }
+        let Some(kind) = kind else {
+            return false;
+        };
+        if let Some(incident) = self.reports.last_mut() {
+            incident.closed = true;
+        }
+        self.reports.push(Incident {
+            kind,
+            lines: vec![line.clone()],
+            priority,
+            tag: tag.to_string(),
+            closed: false,
+        });
+        if self.reports.len() > MAX_INCIDENTS {
+            self.reports.remove(0);
+            self.seen = self.seen.saturating_sub(1);
+        }
+        true
+    }
+
+    /// Alert for incidents the user has not seen yet, if there are any.
+    pub fn banner_line(&self) -> Option<Line<'static>> {
+        let unseen = self.reports.len() - self.seen;
+        let latest = self.reports.last().filter(|_| unseen > 0)?;
+        let mut line = Line::from(vec![
+            Span::from(format!(" ⚠ {} {}: ", latest.kind.label().to_uppercase(), format_age(latest.at()))).bold(),
+            Span::from(latest.summary()),
+        ]);
+        if unseen > 1 {
+            line.push_span(Span::from(format!(" (+{} more)", unseen - 1)).bold());
+        }
+        line.push_span(Span::from("  LOGS tab i: incidents  x: dismiss "));
+        Some(line.white().on_red())
+    }
+
+    pub const fn is_pager_open(&self) -> bool {
+        self.pager.is_some()
+    }
+
+    /// Stop showing the banner for the incidents so far.
+    pub const fn dismiss(&mut self) {
+        self.seen = self.reports.len();
+    }
+
+    /// List every incident in the pager, newest first.
+    pub fn open_pager(&mut self) {
+        self.dismiss();
+        let mut lines = Vec::new();
+        for incident in self.reports.iter().rev() {
+            lines.push(Line::from(vec![
+                Span::from(incident.kind.label()).red().bold(),
+                Span::from(format!(" {} ", format_age(incident.at()))).dim(),
+                Span::from(incident.summary()).bold(),
+            ]));
+            lines.extend(incident.lines.iter().map(|line| Line::from(format!("  {}", line.text)).dim()));
+            lines.push(Line::default());
+        }
+        if lines.is_empty() {
+            lines.push(Line::from("No crashes or ANRs so far").italic());
+        }
+        let title = format!("I N C I D E N T S ({})", self.reports.len());
+        self.pager = Some(StaticOverlay::with_title(lines, title));
+    }
+
+    /// Pass a key to the open pager, closing it when it is done.
+    pub fn handle_pager_key_event(&mut self, tui: &tui::Tui, key_event: KeyEvent) {
+        if let Some(pager) = &mut self.pager {
+            pager.handle_key_event(tui, key_event);
+            if pager.is_done() || key_event.code == KeyCode::Char('i') {
+                self.pager = None;
+            }
+        }
+        tui.frame_requester().schedule_frame();
+    }
+
+    pub fn render_pager(&mut self, frame: &mut Frame, area: Rect) {
+        if let Some(pager) = &mut self.pager {
+            pager.render(area, frame.buffer_mut());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(millis: u64, text: &str) -> LogLine {
+        LogLine {
+            received_at: SystemTime::UNIX_EPOCH + Duration::from_millis(millis),
+            text: text.to_string(),
+        }
+    }
+
+    /// Incidents found in `lines`, given as milliseconds since the start of the log and text.
+    fn feed(lines: &[(u64, &str)]) -> Incidents {
+        let mut incidents = Incidents::default();
+        for &(millis, text) in lines {
+            incidents.on_log_line(&line(millis, text));
+        }
+        incidents
+    }
+
+    #[test]
+    fn java_crash_takes_its_whole_report() {
+        let incidents = feed(&[
+            (0, "10-18 12:00:00.000  100  100 I Bot: STATE race"),
+            (1, "10-18 12:00:00.001  100  100 E AndroidRuntime: FATAL EXCEPTION: main"),
+            (1, "10-18 12:00:00.001  100  100 E AndroidRuntime: Process: com.example.bot, PID: 100"),
+            (1, "10-18 12:00:00.001  100  100 E AndroidRuntime: java.lang.IllegalStateException: boom"),
+            (1, "10-18 12:00:00.001  100  100 E AndroidRuntime: \tat com.example.bot.Race.run(Race.java:12)"),
+            (2, "10-18 12:00:00.002  100  100 I Bot: restarting"),
+        ]);
+        let [crash] = incidents.reports.as_slice() else {
+            panic!("expected one incident, got {}", incidents.reports.len());
+        };
+        assert_eq!(crash.kind, IncidentKind::JavaCrash);
+        assert_eq!(crash.lines.len(), 4);
+        assert_eq!(crash.summary(), "java.lang.IllegalStateException: boom");
+    }
+
+    #[test]
+    fn fatal_exceptions_back_to_back_are_separate_incidents() {
+        let incidents = feed(&[
+            (0, "10-18 12:00:00.000  100  100 E AndroidRuntime: FATAL EXCEPTION: main"),
+            (0, "10-18 12:00:00.000  100  100 E AndroidRuntime: java.lang.NullPointerException"),
+            (0, "10-18 12:00:00.000  100  130 E AndroidRuntime: FATAL EXCEPTION: RenderThread"),
+            (0, "10-18 12:00:00.000  100  130 E AndroidRuntime: java.lang.OutOfMemoryError"),
+        ]);
+        let summaries = incidents.reports.iter().map(Incident::summary).collect::<Vec<_>>();
+        assert_eq!(summaries, ["java.lang.NullPointerException", "java.lang.OutOfMemoryError"]);
+        assert!(incidents.reports[0].closed);
+    }
+
+    #[test]
+    fn report_continues_with_its_tag_and_priority_within_the_gap() {
+        let mut incidents = feed(&[
+            (0, "10-18 12:00:00.000  200  200 F DEBUG: *** *** *** *** *** *** *** *** *** *** *** *** ***"),
+            (10, "10-18 12:00:00.010  200  200 F DEBUG: Build fingerprint: 'google/sdk/emu:14/UE1A/1:user'"),
+            (20, "10-18 12:00:00.020  300  300 I Bot: STATE training"),
+            (30, "10-18 12:00:00.030  200  200 I DEBUG: not part of the tombstone"),
+            (
+                40,
+                "10-18 12:00:00.040  200  200 F DEBUG: pid: 4321, tid: 4321, name: UnityMain  >>> com.example.game <<<",
+            ),
+            (1500, "10-18 12:00:01.500  200  200 F DEBUG: signal 11 (SIGSEGV), code 1 (SEGV_MAPERR), fault addr 0x0"),
+        ]);
+        let late = line(4000, "10-18 12:00:04.000  200  200 F DEBUG: backtrace:");
+        assert!(!incidents.on_log_line(&late));
+        let [tombstone] = incidents.reports.as_slice() else {
+            panic!("expected one incident, got {}", incidents.reports.len());
+        };
+        assert_eq!(tombstone.kind, IncidentKind::NativeCrash);
+        assert_eq!(tombstone.lines.len(), 4);
+        assert_eq!(tombstone.summary(), "com.example.game: signal 11 (SIGSEGV), code 1 (SEGV_MAPERR), fault addr 0x0");
+    }
+
+    #[test]
+    fn unity_exception_ends_at_its_filename_line() {
+        let mut incidents = feed(&[
+            (0, "10-18 12:00:00.000  400  420 E Unity: NullReferenceException: Object reference not set"),
+            (0, "10-18 12:00:00.000  400  420 E Unity:   at Game.Career.Update () [0x00000] in <00000>:0"),
+            (0, "10-18 12:00:00.000  400  420 E Unity: (Filename: <00000> Line: 0)"),
+        ]);
+        let after = line(1, "10-18 12:00:00.001  400  420 E Unity: Failed to load asset bundle");
+        assert!(!incidents.on_log_line(&after));
+        let [exception] = incidents.reports.as_slice() else {
+            panic!("expected one incident, got {}", incidents.reports.len());
+        };
+        assert_eq!(exception.kind, IncidentKind::UnityException);
+        assert_eq!(exception.lines.len(), 3);
+        assert!(exception.closed);
+        assert_eq!(exception.summary(), "NullReferenceException: Object reference not set");
+    }
+
+    #[test]
+    fn anr_summary_gives_the_reason() {
+        let incidents = feed(&[
+            (0, "10-18 12:00:00.000  500  520 E ActivityManager: ANR in com.example.game (com.example.game/.Main)"),
+            (0, "10-18 12:00:00.000  500  520 E ActivityManager: PID: 4321"),
+            (0, "10-18 12:00:00.000  500  520 E ActivityManager: Reason: Input dispatching timed out"),
+        ]);
+        let [anr] = incidents.reports.as_slice() else {
+            panic!("expected one incident, got {}", incidents.reports.len());
+        };
+        assert_eq!(anr.kind, IncidentKind::Anr);
+        assert_eq!(anr.summary(), "ANR in com.example.game (com.example.game/.Main) · Input dispatching timed out");
+    }
+}
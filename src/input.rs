@@ -0,0 +1,182 @@
+//! Touch and key injection through the device's `input` command.
+
+use std::{fmt, thread, time::Duration};
+
+use crate::{
+    adb::{AdbOptions, Device, shell_output},
+    app_event::AppEvent,
+    app_event_sender::AppEventSender,
+};
+
+/// How long to wait after injecting before reporting back, so the capture that follows shows the
+/// result rather than the screen mid-transition.
+const SETTLE: Duration = Duration::from_millis(300);
+/// Swipe duration used by the prompt when none is given.
+const DEFAULT_SWIPE: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputAction {
+    Tap {
+        x: u32,
+        y: u32,
+    },
+    Swipe {
+        from: (u32, u32),
+        to: (u32, u32),
+        duration: Duration,
+    },
+    /// An Android key code, either numeric or a `KEYCODE_*` name.
+    Key(String),
+    Text(String),
+}
+
+impl InputAction {
+    /// Parse a prompt command: `tap X Y`, `swipe X1 Y1 X2 Y2 [MS]`, `key NAME|CODE` (e.g. `key back`)
+    /// or `text STRING`.
+    pub fn parse(command: &str) -> Result<Self, String> {
+        let command = command.trim();
+        let (verb, rest) = command.split_once(' ').unwrap_or((command, ""));
+        let numbers = || {
+            rest.split_whitespace()
+                .map(|n| n.parse::<u32>().map_err(|_| format!("{n:?} is not a number")))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        match verb {
+            "tap" => match numbers()?[..] {
+                [x, y] => Ok(Self::Tap {
+                    x,
+                    y,
+                }),
+                _ => Err("usage: tap X Y".to_string()),
+            },
+            "swipe" => {
+                let (from, to, duration) = match numbers()?[..] {
+                    [x1, y1, x2, y2] => ((x1, y1), (x2, y2), DEFAULT_SWIPE),
+                    [x1, y1, x2, y2, ms] => ((x1, y1), (x2, y2), Duration::from_millis(ms.into())),
+                    _ => return Err("usage: swipe X1 Y1 X2 Y2 [MS]".to_string()),
+                };
+                Ok(Self::Swipe {
+                    from,
+                    to,
+                    duration,
+                })
+            }
+            "key" => key_code(rest.trim()).map(Self::Key),
+            "text" if !rest.is_empty() => Ok(Self::Text(rest.to_string())),
+            "text" => Err("usage: text STRING".to_string()),
+            _ => Err(format!("unknown command {verb:?}: use tap, swipe, key or text")),
+        }
+    }
+
+    /// Arguments for the device shell. Text is escaped for the shell `input` runs under.
+    fn shell_args(&self) -> Vec<String> {
+        let mut args = vec!["input".to_string()];
+        match self {
+            Self::Tap {
+                x,
+                y,
+            } => args.extend(["tap".to_string(), x.to_string(), y.to_string()]),
+            Self::Swipe {
+                from,
+                to,
+                duration,
+            } => args.extend([
+                "swipe".to_string(),
+                from.0.to_string(),
+                from.1.to_string(),
+                to.0.to_string(),
+                to.1.to_string(),
+                duration.as_millis().to_string(),
+            ]),
+            Self::Key(code) => args.extend(["keyevent".to_string(), code.clone()]),
+            Self::Text(text) => args.extend(["text".to_string(), escape_text(text)]),
+        }
+        args
+    }
+}
+
+impl fmt::Display for InputAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tap {
+                x,
+                y,
+            } => write!(f, "tap {x},{y}"),
+            Self::Swipe {
+                from,
+                to,
+                duration,
+            } => {
+                write!(f, "swipe {},{} → {},{} in {}ms", from.0, from.1, to.0, to.1, duration.as_millis())
+            }
+            Self::Key(code) => write!(f, "key {code}"),
+            Self::Text(text) => write!(f, "text {text:?}"),
+        }
+    }
+}
+
+/// Normalize `back`, `KEYCODE_BACK` or `4` to what `input keyevent` accepts.
+fn key_code(name: &str) -> Result<String, String> {
+    if name.is_empty() {
+        return Err("usage: key NAME|CODE, e.g. key back".to_string());
+    }
+    if name.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(name.to_string());
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("invalid key name {name:?}"));
+    }
+    let upper = name.to_ascii_uppercase();
+    Ok(if upper.starts_with("KEYCODE_") {
+        upper
+    } else {
+        format!("KEYCODE_{upper}")
+    })
+}
+
+/// `input text` takes a single argument with `%s` for spaces, and the device shell sees the
+/// command line unquoted, so its metacharacters need a backslash.
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            ' ' => escaped.push_str("%s"),
+            '\\' | '"' | '\'' | '`' | '$' | '&' | '|' | ';' | '<' | '>' | '(' | ')' | '*' | '?' | '~' | '#' | '!'
+            | '[' | ']' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Inject `action` and wait for `input` to finish. Blocks, so call it from a worker thread.
+pub fn send(device: &mut Device, action: &InputAction) -> Result<(), String> {
+    let args = action.shell_args();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match shell_output(device, &args) {
+        // `input` is silent on success; anything it prints is a usage message or exception.
+        Ok(output) if output.iter().all(u8::is_ascii_whitespace) => Ok(()),
+        Ok(output) => Err(String::from_utf8_lossy(&output).trim().lines().next().unwrap_or_default().to_string()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Inject `action` on a worker thread. The outcome arrives as [`AppEvent::InputSent`] once the
+/// screen had a moment to react.
+pub fn spawn_send(adb: &AdbOptions, app_event_tx: &AppEventSender, action: InputAction) {
+    let adb = adb.clone();
+    let tx = app_event_tx.clone();
+    thread::spawn(move || {
+        let result = send(&mut adb.device(), &action);
+        if result.is_ok() {
+            thread::sleep(SETTLE);
+        }
+        tx.send(AppEvent::InputSent {
+            action,
+            result,
+        });
+    });
+}
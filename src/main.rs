@@ -1,15 +1,52 @@
 mod adb;
+mod adb_direct;
+mod adb_sync;
+mod ansi;
 mod app;
+mod app_event;
+mod app_event_sender;
+mod career;
 mod cli;
-pub mod custom_terminal;
-pub mod insert_history;
+mod custom_terminal;
+mod device_info;
+mod device_profile;
+mod device_view;
+mod discovery;
+mod export;
+mod farm;
+mod farm_view;
+mod files_view;
+mod getevent;
+mod incident;
+mod input;
+mod matching;
+mod ocr_review;
 mod pager_overlay;
+mod perf;
+mod perf_view;
+mod pixel_inspector;
+mod port_forward;
+mod ports_view;
+mod run_compare;
+mod screen_record;
+mod screen_view;
+mod screenshot;
+mod session;
+mod shell_view;
+mod stack_trace;
+mod template;
+mod terminal_graphics;
+mod thermal;
+mod thermal_view;
+mod touch_macro;
+mod touch_trace;
 mod tui;
 mod ui;
+mod watchdog;
 
 use app::App;
 use clap::Parser;
-use cli::Cli;
+use cli::{Cli, Command};
 use tui::Tui;
 
 fn restore() {
@@ -21,12 +58,30 @@ fn restore() {
 #[tokio::main]
 async fn main() -> color_eyre::Result<()> {
     let cli_args: Cli = Cli::parse();
+    match &cli_args.command {
+        Some(Command::Export(args)) => {
+            color_eyre::install()?;
+            return export::run_headless(&cli_args.data_dir, args);
+        }
+        Some(Command::Match(args)) => {
+            color_eyre::install()?;
+            return matching::run_headless(args);
+        }
+        Some(Command::Macro(args)) => {
+            color_eyre::install()?;
+            return touch_macro::run_headless(args);
+        }
+        None => {}
+    }
     run_tui(cli_args).await?;
     Ok(())
 }
 
 async fn run_tui(cli_args: Cli) -> color_eyre::Result<()> {
     color_eyre::install()?;
+    if let Some(protocol) = cli_args.graphics {
+        terminal_graphics::set_protocol(protocol);
+    }
 
     // Forward panic reports through tracing so they appear in the UI status
     // line, but do not swallow the default/color-eyre panic handler.
@@ -42,7 +97,7 @@ async fn run_tui(cli_args: Cli) -> color_eyre::Result<()> {
 
     let mut tui = Tui::new(terminal);
 
-    let app_result = App::run(&mut tui, cli_args.tick_rate).await;
+    let app_result = App::run(&mut tui, &cli_args).await;
     restore();
 
     app_result
@@ -0,0 +1,305 @@
+//! Template matching by normalized cross-correlation (NCC) over grayscale pixels.
+//!
+//! Scores range from -1 to 1, with 1 a perfect match up to brightness and contrast. To keep a full
+//! frame search interactive, every template is first located on a box-downscaled copy of the frame
+//! and the best candidates are then refined at full resolution.
+
+use std::{fs, path::Path, time::SystemTime};
+
+use color_eyre::eyre::WrapErr;
+use serde::Serialize;
+
+use crate::{
+    cli::MatchArgs,
+    screenshot::Screenshot,
+    template::{self, Region, Template},
+};
+
+/// Score at which a match counts as found, unless overridden.
+pub const DEFAULT_THRESHOLD: f32 = 0.8;
+
+/// Coarse-pass positions that get refined at full resolution.
+const CANDIDATES: usize = 4;
+
+/// Single channel image with luma values in `0.0..=255.0`.
+struct GrayImage {
+    width: usize,
+    height: usize,
+    pixels: Vec<f32>,
+}
+
+impl GrayImage {
+    fn from_rgb(width: u32, height: u32, rgb: &[u8]) -> Self {
+        let pixels = rgb
+            .chunks_exact(3)
+            .map(|p| 0.114f32.mul_add(f32::from(p[2]), 0.299f32.mul_add(f32::from(p[0]), 0.587 * f32::from(p[1]))))
+            .collect();
+        Self {
+            width: width as usize,
+            height: height as usize,
+            pixels,
+        }
+    }
+
+    /// Average every `factor` x `factor` block. Partial blocks at the edges are dropped.
+    fn downscale(&self, factor: usize) -> Self {
+        if factor == 1 {
+            return Self {
+                width: self.width,
+                height: self.height,
+                pixels: self.pixels.clone(),
+            };
+        }
+        let width = self.width / factor;
+        let height = self.height / factor;
+        #[allow(clippy::cast_precision_loss)]
+        let area = (factor * factor) as f32;
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = 0.0;
+                for row in y * factor..(y + 1) * factor {
+                    let start = row * self.width + x * factor;
+                    sum += self.pixels[start..start + factor].iter().sum::<f32>();
+                }
+                pixels.push(sum / area);
+            }
+        }
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+/// Summed-area tables of the frame and its squares, for O(1) window mean and variance.
+struct Integral {
+    stride: usize,
+    sum: Vec<f64>,
+    sum_sq: Vec<f64>,
+}
+
+impl Integral {
+    fn new(image: &GrayImage) -> Self {
+        let stride = image.width + 1;
+        let mut sum = vec![0.0; stride * (image.height + 1)];
+        let mut sum_sq = sum.clone();
+        for y in 0..image.height {
+            let (mut row, mut row_sq) = (0.0, 0.0);
+            for x in 0..image.width {
+                let v = f64::from(image.pixels[y * image.width + x]);
+                row += v;
+                row_sq += v * v;
+                sum[(y + 1) * stride + x + 1] = sum[y * stride + x + 1] + row;
+                sum_sq[(y + 1) * stride + x + 1] = sum_sq[y * stride + x + 1] + row_sq;
+            }
+        }
+        Self {
+            stride,
+            sum,
+            sum_sq,
+        }
+    }
+
+    fn window(&self, table: &[f64], (x, y): (usize, usize), (width, height): (usize, usize)) -> f64 {
+        let stride = self.stride;
+        table[(y + height) * stride + x + width] - table[y * stride + x + width] - table[(y + height) * stride + x]
+            + table[y * stride + x]
+    }
+
+    /// Sum and sum of squared deviations of the window of `size` at `at`.
+    fn stats(&self, at: (usize, usize), size: (usize, usize)) -> (f64, f64) {
+        #[allow(clippy::cast_precision_loss)]
+        let count = (size.0 * size.1) as f64;
+        let sum = self.window(&self.sum, at, size);
+        let sum_sq = self.window(&self.sum_sq, at, size);
+        (sum, sum.mul_add(-sum / count, sum_sq).max(0.0))
+    }
+}
+
+/// A template with its mean removed, so the correlation numerator needs no per-window mean.
+struct ZeroMean {
+    width: usize,
+    height: usize,
+    pixels: Vec<f32>,
+    /// Sum of squared deviations, the template half of the NCC denominator.
+    energy: f64,
+}
+
+impl ZeroMean {
+    fn new(image: &GrayImage) -> Self {
+        #[allow(clippy::cast_precision_loss)]
+        let mean = image.pixels.iter().sum::<f32>() / image.pixels.len().max(1) as f32;
+        let pixels: Vec<f32> = image.pixels.iter().map(|p| p - mean).collect();
+        let energy = pixels.iter().map(|&p| f64::from(p) * f64::from(p)).sum();
+        Self {
+            width: image.width,
+            height: image.height,
+            pixels,
+            energy,
+        }
+    }
+}
+
+/// NCC of `template` placed at `(x, y)` in `image`. Flat windows or templates score 0.
+fn ncc_at(image: &GrayImage, integral: &Integral, template: &ZeroMean, x: usize, y: usize) -> f32 {
+    let (_, variance) = integral.stats((x, y), (template.width, template.height));
+    let denominator = (variance * template.energy).sqrt();
+    if denominator < 1e-6 {
+        return 0.0;
+    }
+    let mut numerator = 0.0f32;
+    for row in 0..template.height {
+        let image_row = &image.pixels[(y + row) * image.width + x..][..template.width];
+        let template_row = &template.pixels[row * template.width..][..template.width];
+        numerator += image_row.iter().zip(template_row).map(|(i, t)| i * t).sum::<f32>();
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    let score = (f64::from(numerator) / denominator).clamp(-1.0, 1.0) as f32;
+    score
+}
+
+/// Best NCC position of `template` within `image`, searching every position in `xs` x `ys`.
+fn best_in(
+    image: &GrayImage,
+    integral: &Integral,
+    template: &ZeroMean,
+    xs: std::ops::RangeInclusive<usize>,
+    ys: std::ops::RangeInclusive<usize>,
+    keep: usize,
+) -> Vec<(f32, usize, usize)> {
+    let mut best: Vec<(f32, usize, usize)> = Vec::with_capacity(keep + 1);
+    for y in ys {
+        for x in xs.clone() {
+            let score = ncc_at(image, integral, template, x, y);
+            if best.len() < keep || score > best[best.len() - 1].0 {
+                let at = best.partition_point(|&(s, _, _)| s >= score);
+                best.insert(at, (score, x, y));
+                best.truncate(keep);
+            }
+        }
+    }
+    best
+}
+
+/// The best match of one template against a frame.
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchResult {
+    pub template: String,
+    pub region: Region,
+    pub score: f32,
+    /// The template was cut from a frame of a different resolution, so it is unlikely to match
+    /// one to one.
+    pub resolution_mismatch: bool,
+}
+
+/// Locate every template in a `width` x `height` frame of packed RGB8 pixels. Templates larger
+/// than the frame are skipped.
+pub fn match_templates(width: u32, height: u32, rgb: &[u8], templates: &[Template]) -> Vec<MatchResult> {
+    let image = GrayImage::from_rgb(width, height, rgb);
+    let integral = Integral::new(&image);
+    // Downscaled frames and their tables, shared by templates using the same factor.
+    let mut levels: Vec<(usize, GrayImage, Integral)> = Vec::new();
+    let mut results = Vec::new();
+    for template in templates {
+        if template.width == 0 || template.height == 0 || template.width > width || template.height > height {
+            continue;
+        }
+        let gray = GrayImage::from_rgb(template.width, template.height, &template.rgb);
+        let full_template = ZeroMean::new(&gray);
+        // Keep at least 8 pixels on the short side of the template at the coarse scale.
+        let factor = (gray.width.min(gray.height) / 8).clamp(1, 4);
+
+        let candidates = if factor == 1 {
+            vec![(0.0, 0, 0)]
+        } else {
+            let index = levels.iter().position(|(f, _, _)| *f == factor).unwrap_or_else(|| {
+                let scaled = image.downscale(factor);
+                let scaled_integral = Integral::new(&scaled);
+                levels.push((factor, scaled, scaled_integral));
+                levels.len() - 1
+            });
+            let (_, coarse, coarse_integral) = &levels[index];
+            let coarse_template = ZeroMean::new(&gray.downscale(factor));
+            best_in(
+                coarse,
+                coarse_integral,
+                &coarse_template,
+                0..=coarse.width - coarse_template.width,
+                0..=coarse.height - coarse_template.height,
+                CANDIDATES,
+            )
+        };
+
+        // Refine around every coarse candidate; without a coarse pass this is the full search.
+        let max_x = image.width - gray.width;
+        let max_y = image.height - gray.height;
+        let best = candidates
+            .into_iter()
+            .flat_map(|(_, cx, cy)| {
+                let (xs, ys) = if factor == 1 {
+                    (0..=max_x, 0..=max_y)
+                } else {
+                    let (x, y) = (cx * factor, cy * factor);
+                    (
+                        x.saturating_sub(factor)..=(x + factor).min(max_x),
+                        y.saturating_sub(factor)..=(y + factor).min(max_y),
+                    )
+                };
+                best_in(&image, &integral, &full_template, xs, ys, 1)
+            })
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+        let Some((score, x, y)) = best else {
+            continue;
+        };
+        results.push(MatchResult {
+            template: template.name.clone(),
+            region: Region {
+                x: u32::try_from(x).unwrap_or_default(),
+                y: u32::try_from(y).unwrap_or_default(),
+                width: template.width,
+                height: template.height,
+            },
+            score,
+            resolution_mismatch: template
+                .meta
+                .as_ref()
+                .is_some_and(|meta| (meta.device_width, meta.device_height) != (width, height)),
+        });
+    }
+    results
+}
+
+/// Entry point for `uma-auto-debug match`: run a template set against recorded screenshots and
+/// print one JSON object per template and screenshot.
+pub fn run_headless(args: &MatchArgs) -> color_eyre::Result<()> {
+    let templates = template::load_set(&args.templates)?;
+    if templates.is_empty() {
+        color_eyre::eyre::bail!("no templates found in {}", args.templates.display());
+    }
+    for path in &args.screenshots {
+        let png = fs::read(path).wrap_err_with(|| format!("failed to read {}", path.display()))?;
+        let shot = Screenshot::decode(png, SystemTime::now())
+            .wrap_err_with(|| format!("{} is not a valid PNG", path.display()))?;
+        for result in match_templates(shot.width, shot.height, &shot.rgb, &templates) {
+            #[derive(Serialize)]
+            struct Row<'a> {
+                screenshot: &'a Path,
+                #[serde(flatten)]
+                result: MatchResult,
+                matched: bool,
+            }
+            let matched = result.score >= args.threshold;
+            println!(
+                "{}",
+                serde_json::to_string(&Row {
+                    screenshot: path,
+                    result,
+                    matched
+                })?
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,418 @@
+//! Verification of the values the bot reads off the screen with OCR.
+//!
+//! The bot logs every recognized value as a logcat line containing `OCR ` followed by a JSON
+//! object, for example:
+//!
+//! ```text
+//! OCR {"turn":12,"field":"speed","region":{"x":80,"y":1410,"width":96,"height":40},"text":"523"}
+//! ```
+//!
+//! Each reading is paired with the session screenshot taken just before it and checked against
+//! the reading of the same field on an earlier turn. Readings marked as misread are saved to a
+//! regression dataset under `<data dir>/ocr_regressions`: the crop as `<stem>.png` plus a
+//! `<stem>.json` [`OcrSample`] with the recognized and the expected text.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    career::StatKind,
+    custom_terminal::Frame,
+    screen_view::{render_half_blocks, render_region_outline, render_screenshot},
+    screenshot::Screenshot,
+    session::{LogLine, Session, unix_millis},
+    template::{self, Region},
+    tui,
+};
+
+/// Marker in front of the JSON object on OCR log lines.
+const MARKER: &str = "OCR {";
+/// Readings kept for review; older ones are dropped.
+const MAX_ENTRIES: usize = 4096;
+/// Highest value a trainable stat can reach.
+const STAT_CAP: u32 = 2000;
+/// A paired frame older than this is unlikely to show what the bot read.
+const STALE_FRAME: Duration = Duration::from_secs(2);
+
+/// One OCR result as logged by the bot.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct OcrReading {
+    pub turn: u32,
+    /// Stat key such as `speed` or `skill_points`; other fields are shown but not checked.
+    pub field: String,
+    /// Where on the device screen the text was read, in device pixels.
+    pub region: Region,
+    pub text: String,
+}
+
+impl OcrReading {
+    /// Extract a reading from a logcat line, if it is an OCR line.
+    pub fn parse(line: &str) -> Option<Self> {
+        let start = line.find(MARKER)? + MARKER.len() - 1;
+        serde_json::from_str(&line[start..]).ok()
+    }
+
+    /// The recognized number, ignoring thousands separators and stray spaces.
+    fn value(&self) -> Option<u32> {
+        let digits: String = self.text.chars().filter(|c| !matches!(c, ',' | '.' | ' ')).collect();
+        digits.parse().ok()
+    }
+}
+
+/// The sidecar written next to each regression crop.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OcrSample {
+    pub turn: u32,
+    pub field: String,
+    pub region: Region,
+    /// What the bot read.
+    pub recognized: String,
+    /// What the crop actually shows, when it was entered while marking.
+    pub expected: Option<String>,
+    /// Unix timestamps (milliseconds) of the reading and of the frame the crop was cut from.
+    pub read_at: u64,
+    pub frame_captured_at: u64,
+}
+
+/// Largest believable change of `kind` over `turns` turns, as `(max drop, max gain)`.
+const fn plausible_change(kind: StatKind, turns: u32) -> (u32, u32) {
+    match kind {
+        // Skill points are spent in bulk, so only gains are limited.
+        StatKind::SkillPoints => (u32::MAX, turns.saturating_mul(150)),
+        // Events can take a few points; a single training rarely gives more than ~100.
+        _ => (30, turns.saturating_mul(120)),
+    }
+}
+
+/// Why `reading` looks wrong, judged against the latest trusted reading of the same field on an
+/// earlier turn.
+fn implausibility(reading: &OcrReading, previous: Option<(u32, u32)>) -> Option<String> {
+    let kind = StatKind::from_key(&reading.field)?;
+    let Some(value) = reading.value() else {
+        return Some(format!("{:?} is not a number", reading.text));
+    };
+    if kind != StatKind::SkillPoints && value > STAT_CAP {
+        return Some(format!("above the stat cap of {STAT_CAP}"));
+    }
+    let (previous_turn, previous_value) = previous?;
+    let (max_drop, max_gain) = plausible_change(kind, reading.turn - previous_turn);
+    if previous_value > value && previous_value - value > max_drop {
+        Some(format!("dropped {} since turn {previous_turn} ({previous_value})", previous_value - value))
+    } else if value > previous_value && value - previous_value > max_gain {
+        Some(format!("rose {} since turn {previous_turn} ({previous_value})", value - previous_value))
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+struct OcrEntry {
+    reading: OcrReading,
+    received_at: SystemTime,
+    flag: Option<String>,
+    marked: bool,
+}
+
+/// The crop shown for the selected reading, cut from `frame`.
+struct Crop {
+    frame: Screenshot,
+    crop: Screenshot,
+}
+
+/// State for the OCR tab: every reading of the session, the one under review and its crop.
+pub struct OcrReview {
+    dataset_dir: PathBuf,
+    entries: Vec<OcrEntry>,
+    list_state: ListState,
+    /// Keep the newest reading selected as readings arrive.
+    follow: bool,
+    /// Crop of the selected entry by index. `None` inside means there was no usable frame, cached
+    /// so the session does not decode frames on every draw.
+    crop: Option<(usize, Option<Crop>)>,
+    /// Text typed as the expected value while marking a misread.
+    expected: Option<String>,
+    notice: Option<String>,
+}
+
+impl OcrReview {
+    pub fn new(dataset_dir: PathBuf) -> Self {
+        Self {
+            dataset_dir,
+            entries: Vec::new(),
+            list_state: ListState::default(),
+            follow: true,
+            crop: None,
+            expected: None,
+            notice: None,
+        }
+    }
+
+    /// Feed a logcat line; lines that are not OCR readings are ignored.
+    pub fn on_log_line(&mut self, line: &LogLine) {
+        let Some(reading) = OcrReading::parse(&line.text) else {
+            return;
+        };
+        let previous = self
+            .entries
+            .iter()
+            .rev()
+            .filter(|e| e.reading.field == reading.field && e.reading.turn < reading.turn)
+            .filter(|e| e.flag.is_none() && !e.marked)
+            .find_map(|e| Some((e.reading.turn, e.reading.value()?)));
+        self.entries.push(OcrEntry {
+            flag: implausibility(&reading, previous),
+            reading,
+            received_at: line.received_at,
+            marked: false,
+        });
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.remove(0);
+            self.crop = None;
+            if let Some(selected) = self.list_state.selected() {
+                self.list_state.select(Some(selected.saturating_sub(1)));
+            }
+        }
+        if self.follow {
+            self.list_state.select(Some(self.entries.len() - 1));
+        }
+    }
+
+    fn select(&mut self, index: usize) {
+        let last = self.entries.len().saturating_sub(1);
+        self.list_state.select(Some(index.min(last)));
+        self.follow = index >= last;
+        self.notice = None;
+    }
+
+    fn selected(&self) -> Option<usize> {
+        self.list_state.selected().filter(|&i| i < self.entries.len())
+    }
+
+    /// Select the next flagged reading after (`forward`) or before the current one.
+    fn jump_to_flagged(&mut self, forward: bool) {
+        let current = self.selected().unwrap_or_default();
+        let found = if forward {
+            (current + 1..self.entries.len()).find(|&i| self.entries[i].flag.is_some())
+        } else {
+            (0..current).rev().find(|&i| self.entries[i].flag.is_some())
+        };
+        match found {
+            Some(index) => self.select(index),
+            None => self.notice = Some("no more flagged readings".to_string()),
+        }
+    }
+
+    /// The crop of entry `index`, cut from the newest session frame taken before the reading.
+    fn crop_for(&mut self, index: usize, session: Option<&mut Session>) -> Option<&Crop> {
+        if self.crop.as_ref().is_none_or(|(cached, _)| *cached != index) {
+            let entry = &self.entries[index];
+            let region = entry.reading.region;
+            let crop = session
+                .and_then(|session| session.screenshot_before(entry.received_at))
+                .filter(|frame| {
+                    region.width > 0
+                        && region.height > 0
+                        && region.right() <= frame.width
+                        && region.bottom() <= frame.height
+                })
+                .and_then(|frame| {
+                    let rgb = template::crop(frame, region);
+                    let crop = Screenshot::from_rgb(region.width, region.height, rgb, frame.captured_at)
+                        .inspect_err(|err| tracing::warn!("failed to encode OCR crop: {err}"))
+                        .ok()?;
+                    Some(Crop {
+                        frame: frame.clone(),
+                        crop,
+                    })
+                });
+            self.crop = Some((index, crop));
+        }
+        self.crop.as_ref().and_then(|(_, crop)| crop.as_ref())
+    }
+
+    /// Save the selected reading to the regression dataset.
+    fn save_misread(&mut self, expected: Option<String>, session: Option<&mut Session>) {
+        let Some(index) = self.selected() else {
+            return;
+        };
+        let dataset_dir = self.dataset_dir.clone();
+        let entry = self.entries[index].clone();
+        let Some(crop) = self.crop_for(index, session) else {
+            self.notice = Some("no screenshot to cut the crop from".to_string());
+            return;
+        };
+        let result = save_sample(&dataset_dir, &entry, crop, expected);
+        self.notice = Some(match result {
+            Ok(path) => {
+                self.entries[index].marked = true;
+                format!("saved {}", path.display())
+            }
+            Err(err) => format!("failed to save sample: {err}"),
+        });
+    }
+
+    pub fn handle_key_event(&mut self, tui: &tui::Tui, key_event: KeyEvent, session: Option<&mut Session>) {
+        if let Some(expected) = &mut self.expected {
+            match key_event.code {
+                KeyCode::Enter => {
+                    let expected = self.expected.take().filter(|text| !text.is_empty());
+                    self.save_misread(expected, session);
+                }
+                KeyCode::Esc => self.expected = None,
+                KeyCode::Backspace => {
+                    expected.pop();
+                }
+                KeyCode::Char(c) => expected.push(c),
+                _ => return,
+            }
+            tui.frame_requester().schedule_frame();
+            return;
+        }
+        let current = self.selected().unwrap_or_default();
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => self.select(current.saturating_sub(1)),
+            KeyCode::Down | KeyCode::Char('j') => self.select(current + 1),
+            KeyCode::Home | KeyCode::Char('g') => self.select(0),
+            KeyCode::End | KeyCode::Char('G') => self.select(usize::MAX),
+            KeyCode::Char('n') => self.jump_to_flagged(true),
+            KeyCode::Char('N') => self.jump_to_flagged(false),
+            KeyCode::Char('x') if self.selected().is_some() => self.expected = Some(String::new()),
+            _ => return,
+        }
+        tui.frame_requester().schedule_frame();
+    }
+
+    fn status_line(&self) -> Line<'static> {
+        if let Some(expected) = &self.expected {
+            return Line::from(vec![
+                Span::from(" expected text: ").bold(),
+                Span::from(format!("{expected}▏")),
+                Span::from("  enter: save misread (empty if unknown)  esc: cancel").dim(),
+            ]);
+        }
+        let flagged = self.entries.iter().filter(|e| e.flag.is_some()).count();
+        let mut line = Line::from(format!(
+            " {} readings · {flagged} flagged · j/k: move  n/N: next/prev flagged  x: mark misread",
+            self.entries.len()
+        ))
+        .dim();
+        if let Some(notice) = &self.notice {
+            line.push_span(Span::from(format!(" · {notice}")).green());
+        }
+        line
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, session: Option<&mut Session>) {
+        let [status_area, body] = Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+        frame.render_widget(self.status_line(), status_area);
+        let [list_area, detail_area] = Layout::horizontal([Constraint::Percentage(45), Constraint::Min(0)]).areas(body);
+
+        let items: Vec<ListItem> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let mut line = Line::from(format!(
+                    "T{:<3} {:<12} {:>6}",
+                    entry.reading.turn, entry.reading.field, entry.reading.text
+                ));
+                if entry.marked {
+                    line.push_span(Span::from(" misread").yellow());
+                } else if entry.flag.is_some() {
+                    line.push_span(Span::from(" ⚠").red());
+                }
+                ListItem::new(line)
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::bordered().title(" OCR readings "))
+            .highlight_style(Style::default().reversed());
+        frame.render_stateful_widget(list, list_area, &mut self.list_state);
+
+        let block = Block::bordered().title(" Reading ");
+        let inner = block.inner(detail_area);
+        frame.render_widget(block, detail_area);
+        let Some(index) = self.selected() else {
+            frame.render_widget(Paragraph::new(Line::from("No OCR readings logged yet").italic()), inner);
+            return;
+        };
+        let entry = self.entries[index].clone();
+        let region = entry.reading.region;
+        let mut lines = vec![
+            Line::from(format!("turn {} · {} = {:?}", entry.reading.turn, entry.reading.field, entry.reading.text)),
+            Line::from(format!("region {},{} {}x{}", region.x, region.y, region.width, region.height)),
+        ];
+        match &entry.flag {
+            Some(flag) => lines.push(Line::from(format!("implausible: {flag}")).red()),
+            None => lines.push(Line::from("plausible").green()),
+        }
+        let crop = self.crop_for(index, session);
+        match crop {
+            Some(crop) => {
+                let lag = entry.received_at.duration_since(crop.frame.captured_at).unwrap_or_default();
+                let line = Line::from(format!("frame {:.1}s before the reading", lag.as_secs_f64()));
+                lines.push(if lag > STALE_FRAME {
+                    line.yellow()
+                } else {
+                    line.dim()
+                });
+            }
+            None => lines.push(Line::from("no screenshot of this region").italic()),
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        let [text_area, crop_area, frame_area] =
+            Layout::vertical([Constraint::Length(lines.len() as u16), Constraint::Percentage(30), Constraint::Min(0)])
+                .areas(inner);
+        frame.render_widget(Paragraph::new(lines), text_area);
+        if let Some(crop) = crop {
+            render_screenshot(frame, &crop.crop, crop_area);
+            // The outline is drawn on the cell grid, so the context frame always uses half blocks.
+            let placement = render_half_blocks(&crop.frame, frame_area, frame.buffer_mut());
+            render_region_outline(&placement, region, Style::default().fg(Color::Yellow), frame.buffer_mut());
+        }
+    }
+}
+
+/// Write the crop and sidecar of a misread to `dir`. Returns the path of the PNG.
+fn save_sample(dir: &Path, entry: &OcrEntry, crop: &Crop, expected: Option<String>) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let read_at = unix_millis(entry.received_at);
+    let field: String = entry
+        .reading
+        .field
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let stem = format!("{read_at}_t{}_{field}", entry.reading.turn);
+    let png_path = dir.join(format!("{stem}.png"));
+    fs::write(&png_path, &crop.crop.png)?;
+    let sample = OcrSample {
+        turn: entry.reading.turn,
+        field: entry.reading.field.clone(),
+        region: entry.reading.region,
+        recognized: entry.reading.text.clone(),
+        expected,
+        read_at,
+        frame_captured_at: unix_millis(crop.frame.captured_at),
+    };
+    let json = serde_json::to_vec_pretty(&sample).map_err(std::io::Error::other)?;
+    fs::write(dir.join(format!("{stem}.json")), json)?;
+    Ok(png_path)
+}
@@ -1,4 +1,4 @@
-use std::{io::Result, time::Duration};
+use std::time::Duration;
 
 use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
 use ratatui::{
@@ -8,37 +8,9 @@ use ratatui::{
     text::{Line, Span},
     widgets::{Paragraph, WidgetRef},
 };
+use textwrap::{Options as TwOptions, WordSplitter};
 
-use crate::{insert_history, tui, tui::TuiEvent};
-
-pub(crate) enum Overlay {
-    Transcript(TranscriptOverlay),
-    Static(StaticOverlay),
-}
-
-impl Overlay {
-    pub(crate) fn new_transcript(lines: Vec<Line<'static>>) -> Self {
-        Self::Transcript(TranscriptOverlay::new(lines))
-    }
-
-    pub(crate) fn new_static_with_title(lines: Vec<Line<'static>>, title: String) -> Self {
-        Self::Static(StaticOverlay::with_title(lines, title))
-    }
-
-    pub(crate) fn handle_event(&mut self, tui: &mut tui::Tui, event: TuiEvent) -> Result<()> {
-        match self {
-            Overlay::Transcript(o) => o.handle_event(tui, event),
-            Overlay::Static(o) => o.handle_event(tui, event),
-        }
-    }
-
-    pub(crate) fn is_done(&self) -> bool {
-        match self {
-            Overlay::Transcript(o) => o.is_done(),
-            Overlay::Static(o) => o.is_done(),
-        }
-    }
-}
+use crate::tui;
 
 // Common pager navigation hints rendered on the first line
 const PAGER_KEY_HINTS: &[(&str, &str)] = &[("↑/↓", "scroll"), ("PgUp/PgDn", "page"), ("Home/End", "jump")];
@@ -80,33 +52,17 @@ impl PagerView {
 
     fn render(&mut self, area: Rect, buf: &mut Buffer) {
         self.render_header(area, buf);
-        let content_area = self.scroll_area(area);
+        let content_area = Self::scroll_area(area);
         self.ensure_wrapped(content_area.width);
         // Compute page bounds without holding an immutable borrow on cache while mutating self
-        let wrapped_len = self.wrap_cache.as_ref().map(|c| c.wrapped.len()).unwrap_or(0);
+        let wrapped_len = self.wrap_cache.as_ref().map_or(0, |c| c.wrapped.len());
         self.scroll_offset = self.scroll_offset.min(wrapped_len.saturating_sub(content_area.height as usize));
         let start = self.scroll_offset;
         let end = (start + content_area.height as usize).min(wrapped_len);
 
         let (wrapped, _src_idx) = self.cached();
         let page = &wrapped[start..end];
-        self.render_content_page_prepared(content_area, buf, page);
-        self.render_bottom_bar(area, content_area, buf, wrapped);
-    }
-
-    fn render_with_highlight(&mut self, area: Rect, buf: &mut Buffer, highlight: Option<(usize, usize)>) {
-        self.render_header(area, buf);
-        let content_area = self.scroll_area(area);
-        self.ensure_wrapped(content_area.width);
-        // Compute page bounds first to avoid borrow conflicts
-        let wrapped_len = self.wrap_cache.as_ref().map(|c| c.wrapped.len()).unwrap_or(0);
-        self.scroll_offset = self.scroll_offset.min(wrapped_len.saturating_sub(content_area.height as usize));
-        let start = self.scroll_offset;
-        let end = (start + content_area.height as usize).min(wrapped_len);
-
-        let (wrapped, src_idx) = self.cached();
-        let page = self.page_with_optional_highlight(wrapped, src_idx, start, end, highlight);
-        self.render_content_page_prepared(content_area, buf, &page);
+        Self::render_content_page_prepared(content_area, buf, page);
         self.render_bottom_bar(area, content_area, buf, wrapped);
     }
 
@@ -118,13 +74,13 @@ impl PagerView {
 
     // Removed unused render_content_page (replaced by render_content_page_prepared)
 
-    fn render_content_page_prepared(&self, area: Rect, buf: &mut Buffer, page: &[Line<'static>]) {
+    fn render_content_page_prepared(area: Rect, buf: &mut Buffer, page: &[Line<'static>]) {
         Paragraph::new(page.to_vec()).render_ref(area, buf);
 
         let visible = page.len();
         if visible < area.height as usize {
             for i in 0..(area.height as usize - visible) {
-                let add = ((visible + i).min(u16::MAX as usize)) as u16;
+                let add = u16::try_from(visible + i).unwrap_or(u16::MAX);
                 let y = area.y.saturating_add(add);
                 Span::from("~").dim().render_ref(Rect::new(area.x, y, 1, 1), buf);
             }
@@ -143,16 +99,18 @@ impl PagerView {
             if max_scroll == 0 {
                 100
             } else {
-                (((self.scroll_offset.min(max_scroll)) as f32 / max_scroll as f32) * 100.0).round() as u8
+                #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss, clippy::cast_sign_loss)]
+                let percent = ((self.scroll_offset.min(max_scroll) as f32 / max_scroll as f32) * 100.0).round() as u8;
+                percent
             }
         };
         let pct_text = format!(" {percent}% ");
-        let pct_w = pct_text.chars().count() as u16;
+        let pct_w = u16::try_from(pct_text.chars().count()).unwrap_or_default();
         let pct_x = sep_rect.x + sep_rect.width - pct_w - 1;
         Span::from(pct_text).dim().render_ref(Rect::new(pct_x, sep_rect.y, pct_w, 1), buf);
     }
 
-    fn handle_key_event(&mut self, tui: &mut tui::Tui, key_event: KeyEvent) -> Result<()> {
+    fn handle_key_event(&mut self, tui: &tui::Tui, key_event: KeyEvent) {
         match key_event {
             KeyEvent {
                 code: KeyCode::Up,
@@ -173,7 +131,7 @@ impl PagerView {
                 kind: KeyEventKind::Press | KeyEventKind::Repeat,
                 ..
             } => {
-                let area = self.scroll_area(tui.terminal.viewport_area);
+                let area = Self::scroll_area(tui.terminal.viewport_area);
                 self.scroll_offset = self.scroll_offset.saturating_sub(area.height as usize);
             }
             KeyEvent {
@@ -181,7 +139,7 @@ impl PagerView {
                 kind: KeyEventKind::Press | KeyEventKind::Repeat,
                 ..
             } => {
-                let area = self.scroll_area(tui.terminal.viewport_area);
+                let area = Self::scroll_area(tui.terminal.viewport_area);
                 self.scroll_offset = self.scroll_offset.saturating_add(area.height as usize);
             }
             KeyEvent {
@@ -198,15 +156,12 @@ impl PagerView {
             } => {
                 self.scroll_offset = usize::MAX;
             }
-            _ => {
-                return Ok(());
-            }
+            _ => return,
         }
         tui.frame_requester().schedule_frame_in(Duration::from_millis(16));
-        Ok(())
     }
 
-    fn scroll_area(&self, area: Rect) -> Rect {
+    fn scroll_area(area: Rect) -> Rect {
         let mut area = area;
         area.y = area.y.saturating_add(1);
         area.height = area.height.saturating_sub(2);
@@ -235,7 +190,7 @@ impl PagerView {
         let mut wrapped: Vec<Line<'static>> = Vec::new();
         let mut src_idx: Vec<usize> = Vec::new();
         for (i, line) in self.lines.iter().enumerate() {
-            let ws = insert_history::word_wrap_lines(std::slice::from_ref(line), width);
+            let ws = word_wrap_lines(std::slice::from_ref(line), width);
             src_idx.extend(std::iter::repeat_n(i, ws.len()));
             wrapped.extend(ws);
         }
@@ -248,200 +203,177 @@ impl PagerView {
     }
 
     fn cached(&self) -> (&[Line<'static>], &[usize]) {
-        if let Some(cache) = self.wrap_cache.as_ref() {
-            (&cache.wrapped, &cache.src_idx)
-        } else {
-            (&[], &[])
-        }
-    }
-
-    fn page_with_optional_highlight<'a>(
-        &self,
-        wrapped: &'a [Line<'static>],
-        src_idx: &[usize],
-        start: usize,
-        end: usize,
-        highlight: Option<(usize, usize)>,
-    ) -> std::borrow::Cow<'a, [Line<'static>]> {
-        use ratatui::style::Modifier;
-        let (hi_start, hi_end) = match highlight {
-            Some(r) => r,
-            None => return std::borrow::Cow::Borrowed(&wrapped[start..end]),
-        };
-        let mut out: Vec<Line<'static>> = Vec::with_capacity(end - start);
-        let mut bold_done = false;
-        for (row, src_line) in wrapped.iter().enumerate().skip(start).take(end.saturating_sub(start)) {
-            let mut line = src_line.clone();
-            if let Some(src) = src_idx.get(row).copied()
-                && src >= hi_start
-                && src < hi_end
-            {
-                for (i, s) in line.spans.iter_mut().enumerate() {
-                    s.style.add_modifier |= Modifier::REVERSED;
-                    if !bold_done && i == 0 {
-                        s.style.add_modifier |= Modifier::BOLD;
-                        bold_done = true;
-                    }
-                }
-            }
-            out.push(line);
-        }
-        std::borrow::Cow::Owned(out)
+        self.wrap_cache.as_ref().map_or((&[], &[]), |cache| (&cache.wrapped, &cache.src_idx))
     }
 }
 
-pub(crate) struct TranscriptOverlay {
+pub struct StaticOverlay {
     view: PagerView,
-    highlight_range: Option<(usize, usize)>,
     is_done: bool,
 }
 
-impl TranscriptOverlay {
-    pub(crate) fn new(transcript_lines: Vec<Line<'static>>) -> Self {
+impl StaticOverlay {
+    pub fn with_title(lines: Vec<Line<'static>>, title: String) -> Self {
         Self {
-            view: PagerView::new(transcript_lines, "T R A N S C R I P T".to_string(), usize::MAX),
-            highlight_range: None,
+            view: PagerView::new(lines, title, 0),
             is_done: false,
         }
     }
 
-    pub(crate) fn insert_lines(&mut self, lines: Vec<Line<'static>>) {
-        self.view.lines.extend(lines);
-        self.view.wrap_cache = None;
-    }
-
-    pub(crate) fn set_highlight_range(&mut self, range: Option<(usize, usize)>) {
-        self.highlight_range = range;
-    }
-
-    fn render_hints(&self, area: Rect, buf: &mut Buffer) {
+    fn render_hints(area: Rect, buf: &mut Buffer) {
         let line1 = Rect::new(area.x, area.y, area.width, 1);
         let line2 = Rect::new(area.x, area.y.saturating_add(1), area.width, 1);
         render_key_hints(line1, buf, PAGER_KEY_HINTS);
-        let mut pairs: Vec<(&str, &str)> = vec![("q", "quit"), ("Esc", "edit prev")];
-        if let Some((start, end)) = self.highlight_range
-            && end > start
-        {
-            pairs.push(("⏎", "edit message"));
-        }
+        let pairs = [("q", "quit")];
         render_key_hints(line2, buf, &pairs);
     }
 
-    pub(crate) fn render(&mut self, area: Rect, buf: &mut Buffer) {
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
         let top_h = area.height.saturating_sub(3);
         let top = Rect::new(area.x, area.y, area.width, top_h);
         let bottom = Rect::new(area.x, area.y + top_h, area.width, 3);
-        self.view.render_with_highlight(top, buf, self.highlight_range);
-        self.render_hints(bottom, buf);
+        self.view.render(top, buf);
+        Self::render_hints(bottom, buf);
     }
 }
 
-impl TranscriptOverlay {
-    pub(crate) fn handle_event(&mut self, tui: &mut tui::Tui, event: TuiEvent) -> Result<()> {
-        match event {
-            TuiEvent::Key(key_event) => match key_event {
-                KeyEvent {
-                    code: KeyCode::Char('q'),
-                    kind: KeyEventKind::Press,
-                    ..
-                }
-                | KeyEvent {
-                    code: KeyCode::Char('t'),
-                    modifiers: crossterm::event::KeyModifiers::CONTROL,
-                    kind: KeyEventKind::Press,
-                    ..
-                }
-                | KeyEvent {
-                    code: KeyCode::Char('c'),
-                    modifiers: crossterm::event::KeyModifiers::CONTROL,
-                    kind: KeyEventKind::Press,
-                    ..
-                } => {
-                    self.is_done = true;
-                    Ok(())
-                }
-                other => self.view.handle_key_event(tui, other),
-            },
-            TuiEvent::Draw => {
-                tui.draw(u16::MAX, |frame| {
-                    self.render(frame.area(), frame.buffer);
-                })?;
-                Ok(())
+impl StaticOverlay {
+    pub fn handle_key_event(&mut self, tui: &tui::Tui, key_event: KeyEvent) {
+        match key_event {
+            KeyEvent {
+                code: KeyCode::Char('q'),
+                kind: KeyEventKind::Press,
+                ..
             }
-            _ => Ok(()),
+            | KeyEvent {
+                code: KeyCode::Char('c'),
+                modifiers: crossterm::event::KeyModifiers::CONTROL,
+                kind: KeyEventKind::Press,
+                ..
+            } => self.is_done = true,
+            other => self.view.handle_key_event(tui, other),
         }
     }
 
-    pub(crate) fn is_done(&self) -> bool {
+    pub fn is_done(&self) -> bool {
         self.is_done
     }
+}
 
-    pub(crate) fn set_scroll_offset(&mut self, offset: usize) {
-        self.view.scroll_offset = offset;
+/// Word-aware wrapping for a list of `Line`s preserving styles.
+fn word_wrap_lines(lines: &[Line], width: u16) -> Vec<Line<'static>> {
+    let mut out = Vec::new();
+    let w = width.max(1) as usize;
+    for line in lines {
+        out.extend(word_wrap_line(line, w));
     }
+    out
 }
 
-pub(crate) struct StaticOverlay {
-    view: PagerView,
-    is_done: bool,
-}
+fn word_wrap_line(line: &Line, width: usize) -> Vec<Line<'static>> {
+    if width == 0 {
+        return vec![to_owned_line(line)];
+    }
+    // Concatenate content and keep span boundaries for later re-slicing.
+    let mut flat = String::new();
+    let mut span_bounds = Vec::new(); // (start_byte, end_byte, style)
+    let mut cursor = 0usize;
+    for s in &line.spans {
+        let text = s.content.as_ref();
+        let start = cursor;
+        flat.push_str(text);
+        cursor += text.len();
+        span_bounds.push((start, cursor, s.style));
+    }
 
-impl StaticOverlay {
-    pub(crate) fn with_title(lines: Vec<Line<'static>>, title: String) -> Self {
-        Self {
-            view: PagerView::new(lines, title, 0),
-            is_done: false,
-        }
+    // Use textwrap for robust word-aware wrapping; no hyphenation, no breaking words.
+    let opts = TwOptions::new(width).break_words(false).word_splitter(WordSplitter::NoHyphenation);
+    let wrapped = textwrap::wrap(&flat, &opts);
+
+    if wrapped.len() <= 1 {
+        return vec![to_owned_line(line)];
     }
 
-    fn render_hints(&self, area: Rect, buf: &mut Buffer) {
-        let line1 = Rect::new(area.x, area.y, area.width, 1);
-        let line2 = Rect::new(area.x, area.y.saturating_add(1), area.width, 1);
-        render_key_hints(line1, buf, PAGER_KEY_HINTS);
-        let pairs = [("q", "quit")];
-        render_key_hints(line2, buf, &pairs);
+    // Map wrapped pieces back to byte ranges in `flat` sequentially.
+    let mut start_cursor = 0usize;
+    let mut out: Vec<Line<'static>> = Vec::with_capacity(wrapped.len());
+    for piece in wrapped {
+        let piece_str: &str = &piece;
+        if piece_str.is_empty() {
+            out.push(Line {
+                style: line.style,
+                alignment: line.alignment,
+                spans: Vec::new(),
+            });
+            continue;
+        }
+        // Find the next occurrence of piece_str at or after start_cursor.
+        // textwrap preserves order, so a linear scan is sufficient.
+        if let Some(rel) = flat[start_cursor..].find(piece_str) {
+            let s = start_cursor + rel;
+            let e = s + piece_str.len();
+            out.push(slice_line_spans(line, &span_bounds, s, e));
+            start_cursor = e;
+        } else {
+            // Fallback: slice by length from cursor.
+            let s = start_cursor;
+            let e = (start_cursor + piece_str.len()).min(flat.len());
+            out.push(slice_line_spans(line, &span_bounds, s, e));
+            start_cursor = e;
+        }
     }
 
-    pub(crate) fn render(&mut self, area: Rect, buf: &mut Buffer) {
-        let top_h = area.height.saturating_sub(3);
-        let top = Rect::new(area.x, area.y, area.width, top_h);
-        let bottom = Rect::new(area.x, area.y + top_h, area.width, 3);
-        self.view.render(top, buf);
-        self.render_hints(bottom, buf);
+    out
+}
+
+fn to_owned_line(l: &Line<'_>) -> Line<'static> {
+    Line {
+        style: l.style,
+        alignment: l.alignment,
+        spans: l
+            .spans
+            .iter()
+            .map(|s| Span {
+                style: s.style,
+                content: std::borrow::Cow::Owned(s.content.to_string()),
+            })
+            .collect(),
     }
 }
 
-impl StaticOverlay {
-    pub(crate) fn handle_event(&mut self, tui: &mut tui::Tui, event: TuiEvent) -> Result<()> {
-        match event {
-            TuiEvent::Key(key_event) => match key_event {
-                KeyEvent {
-                    code: KeyCode::Char('q'),
-                    kind: KeyEventKind::Press,
-                    ..
-                }
-                | KeyEvent {
-                    code: KeyCode::Char('c'),
-                    modifiers: crossterm::event::KeyModifiers::CONTROL,
-                    kind: KeyEventKind::Press,
-                    ..
-                } => {
-                    self.is_done = true;
-                    Ok(())
-                }
-                other => self.view.handle_key_event(tui, other),
-            },
-            TuiEvent::Draw => {
-                tui.draw(u16::MAX, |frame| {
-                    self.render(frame.area(), frame.buffer);
-                })?;
-                Ok(())
-            }
-            _ => Ok(()),
+fn slice_line_spans(
+    original: &Line<'_>,
+    span_bounds: &[(usize, usize, ratatui::style::Style)],
+    start_byte: usize,
+    end_byte: usize,
+) -> Line<'static> {
+    let mut acc: Vec<Span<'static>> = Vec::new();
+    for (i, (s, e, style)) in span_bounds.iter().enumerate() {
+        if *e <= start_byte {
+            continue;
+        }
+        if *s >= end_byte {
+            break;
+        }
+        let seg_start = start_byte.max(*s);
+        let seg_end = end_byte.min(*e);
+        if seg_end > seg_start {
+            let local_start = seg_start - *s;
+            let local_end = seg_end - *s;
+            let content = original.spans[i].content.as_ref();
+            let slice = &content[local_start..local_end];
+            acc.push(Span {
+                style: *style,
+                content: std::borrow::Cow::Owned(slice.to_string()),
+            });
+        }
+        if *e >= end_byte {
+            break;
         }
     }
-
-    pub(crate) fn is_done(&self) -> bool {
-        self.is_done
+    Line {
+        style: original.style,
+        alignment: original.alignment,
+        spans: acc,
     }
 }
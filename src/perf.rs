@@ -0,0 +1,207 @@
+//! Periodic performance samples of the game process.
+//!
+//! Every sample runs one shell script that finds the game's pid and prints `/proc/<pid>/stat`,
+//! `top -b -n1`, `dumpsys meminfo` and `dumpsys gfxinfo` in `@@`-separated sections. CPU usage
+//! comes from the change in the process's CPU time between two samples (falling back to `top` for
+//! the first one), so like `top` it can exceed 100% on several cores. Jank is the share of janky
+//! frames among those rendered since the previous sample; `gfxinfo` only counts frames drawn by
+//! the Android UI toolkit, so games rendering straight to a surface may report none.
+
+use std::{
+    fmt::Write as _,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use serde::Serialize;
+
+use crate::{
+    adb::{AdbOptions, shell_output},
+    app_event::AppEvent,
+    app_event_sender::AppEventSender,
+    device_info::GAME_PACKAGES,
+    session::unix_millis,
+};
+
+/// Clock ticks per second of `/proc/<pid>/stat` CPU times; fixed at 100 on Android.
+const CLOCK_TICKS: f64 = 100.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PerfSample {
+    /// Unix timestamp (milliseconds).
+    pub at: u64,
+    pub package: String,
+    pub pid: u32,
+    pub cpu_percent: Option<f64>,
+    pub pss_kb: Option<u64>,
+    pub rss_kb: Option<u64>,
+    pub threads: Option<u32>,
+    /// Janky frames among the frames rendered since the previous sample.
+    pub jank_percent: Option<f64>,
+    /// Frames rendered since the previous sample.
+    pub frames: Option<u64>,
+}
+
+/// Counters from the previous sample that the next one is measured against.
+struct Previous {
+    pid: u32,
+    at: Instant,
+    cpu_ticks: Option<u64>,
+    frames: Option<(u64, u64)>,
+}
+
+/// Parsed sections of one run of the sampling script.
+#[derive(Debug, Default)]
+struct Readings {
+    package: String,
+    pid: u32,
+    /// utime + stime, in clock ticks.
+    cpu_ticks: Option<u64>,
+    rss_pages: Option<u64>,
+    threads: Option<u32>,
+    top_cpu: Option<f64>,
+    pss_kb: Option<u64>,
+    /// Total and janky frames since the counters were last reset.
+    frames: Option<(u64, u64)>,
+}
+
+fn script() -> String {
+    let mut script = format!("for p in {}; do pid=$(pidof $p) && break; done;", GAME_PACKAGES.join(" "));
+    script.push_str("[ -n \"$pid\" ] || { echo '@@missing'; exit 0; }; pid=${pid%% *};");
+    script.push_str("echo \"@@package $p $pid\";");
+    for (section, command) in [
+        ("stat", "cat /proc/$pid/stat"),
+        ("top", "top -b -n1 -p $pid"),
+        ("meminfo", "dumpsys meminfo $pid"),
+        ("gfxinfo", "dumpsys gfxinfo $p"),
+    ] {
+        let _ = write!(script, "echo '@@{section}'; {command};");
+    }
+    script
+}
+
+/// `None` when the game is not running.
+fn parse_readings(output: &str) -> Option<Readings> {
+    let mut readings = Readings::default();
+    for section in output.split("@@").skip(1) {
+        let (header, body) = section.split_once('\n').unwrap_or((section, ""));
+        let mut header = header.split_whitespace();
+        match header.next()? {
+            "missing" => return None,
+            "package" => {
+                readings.package = header.next()?.to_string();
+                readings.pid = header.next()?.parse().ok()?;
+            }
+            "stat" => parse_stat(body, &mut readings),
+            "top" => readings.top_cpu = parse_top_cpu(body, readings.pid),
+            "meminfo" => readings.pss_kb = parse_total_pss(body),
+            "gfxinfo" => readings.frames = parse_frames(body),
+            _ => {}
+        }
+    }
+    (readings.pid != 0).then_some(readings)
+}
+
+/// Fields of `/proc/<pid>/stat` after the parenthesized command name, which may hold spaces.
+fn parse_stat(text: &str, readings: &mut Readings) {
+    let Some((_, rest)) = text.rsplit_once(')') else {
+        return;
+    };
+    // `rest` starts at field 3 (state), so field N is at index N - 3.
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    let field = |n: usize| fields.get(n - 3).and_then(|value| value.parse::<u64>().ok());
+    readings.cpu_ticks = field(14).zip(field(15)).map(|(utime, stime)| utime + stime);
+    readings.threads = field(20).and_then(|threads| u32::try_from(threads).ok());
+    readings.rss_pages = field(24);
+}
+
+/// `%CPU` of `pid` from `top -b` output. The header glues the state and CPU columns together as
+/// `S[%CPU]`, so brackets are split off before looking the column up.
+fn parse_top_cpu(text: &str, pid: u32) -> Option<f64> {
+    let mut lines = text.lines();
+    let header: Vec<String> = lines
+        .find(|line| line.contains("%CPU"))?
+        .split(|c: char| c.is_whitespace() || c == '[' || c == ']')
+        .filter(|token| !token.is_empty())
+        .map(ToString::to_string)
+        .collect();
+    let column = header.iter().position(|token| token == "%CPU")?;
+    let pid = pid.to_string();
+    let row = lines.find(|line| line.split_whitespace().next() == Some(pid.as_str()))?;
+    row.split_whitespace().nth(column)?.parse().ok()
+}
+
+/// Total PSS in KB from `dumpsys meminfo`: the `TOTAL PSS:` summary of recent releases, or the
+/// first number of the `TOTAL` row of the table on older ones.
+fn parse_total_pss(text: &str) -> Option<u64> {
+    let number_after = |rest: &str| rest.split_whitespace().next()?.parse().ok();
+    text.lines()
+        .find_map(|line| line.split_once("TOTAL PSS:").and_then(|(_, rest)| number_after(rest)))
+        .or_else(|| text.lines().find_map(|line| line.trim_start().strip_prefix("TOTAL ").and_then(number_after)))
+}
+
+fn parse_frames(text: &str) -> Option<(u64, u64)> {
+    let value = |label: &str| {
+        text.lines().find_map(|line| line.trim().strip_prefix(label)?.split_whitespace().next()?.parse().ok())
+    };
+    Some((value("Total frames rendered:")?, value("Janky frames:")?))
+}
+
+fn sample(adb: &AdbOptions, previous: &mut Option<Previous>) -> Result<Option<PerfSample>, String> {
+    let output = shell_output(&mut adb.device(), &[&script()]).map_err(|err| err.to_string())?;
+    let now = Instant::now();
+    let Some(readings) = parse_readings(&String::from_utf8_lossy(&output)) else {
+        *previous = None;
+        return Ok(None);
+    };
+    let previous = previous.replace(Previous {
+        pid: readings.pid,
+        at: now,
+        cpu_ticks: readings.cpu_ticks,
+        frames: readings.frames,
+    });
+    // A restarted game starts its counters over.
+    let previous = previous.filter(|previous| previous.pid == readings.pid);
+
+    #[allow(clippy::cast_precision_loss)]
+    let cpu_percent = previous
+        .as_ref()
+        .and_then(|previous| {
+            let ticks = readings.cpu_ticks?.checked_sub(previous.cpu_ticks?)?;
+            let elapsed = now.duration_since(previous.at).as_secs_f64();
+            (elapsed > 0.0).then(|| ticks as f64 / CLOCK_TICKS / elapsed * 100.0)
+        })
+        .or(readings.top_cpu);
+    let frame_delta = previous.as_ref().and_then(|previous| {
+        let (total, janky) = readings.frames?;
+        let (previous_total, previous_janky) = previous.frames?;
+        Some((total.checked_sub(previous_total)?, janky.checked_sub(previous_janky)?))
+    });
+    #[allow(clippy::cast_precision_loss)]
+    let jank_percent =
+        frame_delta.filter(|&(frames, _)| frames > 0).map(|(frames, janky)| janky as f64 / frames as f64 * 100.0);
+
+    Ok(Some(PerfSample {
+        at: unix_millis(SystemTime::now()),
+        package: readings.package,
+        pid: readings.pid,
+        cpu_percent,
+        pss_kb: readings.pss_kb,
+        // Android uses 4 KiB pages.
+        rss_kb: readings.rss_pages.map(|pages| pages * 4),
+        threads: readings.threads,
+        jank_percent,
+        frames: frame_delta.map(|(frames, _)| frames),
+    }))
+}
+
+/// Sample every `interval` until the app shuts down, delivering [`AppEvent::PerfSampled`].
+pub fn spawn_sampler(adb: AdbOptions, app_event_tx: AppEventSender, interval: Duration) {
+    thread::spawn(move || {
+        let mut previous = None;
+        while !app_event_tx.app_event_tx.is_closed() {
+            app_event_tx.send(AppEvent::PerfSampled(sample(&adb, &mut previous)));
+            thread::sleep(interval);
+        }
+    });
+}
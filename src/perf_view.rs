@@ -0,0 +1,178 @@
+//! PERF tab: CPU, memory and jank of the game process over time, sampled by [`crate::perf`].
+
+use std::collections::VecDeque;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Sparkline},
+};
+
+use crate::{custom_terminal::Frame, perf::PerfSample, tui};
+
+/// Samples kept for the charts; twelve hours at the default interval of 10s.
+const MAX_SAMPLES: usize = 4320;
+/// PSS growth over at least this span is reported as a trend.
+const TREND_MIN_SPAN_MS: u64 = 10 * 60 * 1000;
+/// Growth, in MB per hour, above which the trend is highlighted.
+const CREEP_MB_PER_HOUR: f64 = 50.0;
+
+#[derive(Default)]
+pub struct PerfView {
+    samples: VecDeque<PerfSample>,
+    /// Whether the last sample found the game process.
+    running: bool,
+    error: Option<String>,
+}
+
+impl PerfView {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on_sample(&mut self, result: Result<Option<PerfSample>, String>) {
+        match result {
+            Ok(Some(sample)) => {
+                self.running = true;
+                self.error = None;
+                if self.samples.len() == MAX_SAMPLES {
+                    self.samples.pop_front();
+                }
+                self.samples.push_back(sample);
+            }
+            Ok(None) => {
+                self.running = false;
+                self.error = None;
+            }
+            Err(err) => self.error = Some(err),
+        }
+    }
+
+    pub fn handle_key_event(&mut self, tui: &tui::Tui, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Char('c') => self.samples.clear(),
+            _ => return,
+        }
+        tui.frame_requester().schedule_frame();
+    }
+
+    /// PSS growth in MB per hour, by least squares over the samples of the current process.
+    #[allow(clippy::cast_precision_loss)]
+    fn pss_trend(&self) -> Option<f64> {
+        let pid = self.samples.back()?.pid;
+        let points: Vec<(f64, f64)> = self
+            .samples
+            .iter()
+            .rev()
+            .take_while(|sample| sample.pid == pid)
+            .filter_map(|sample| Some((sample.at as f64 / 3_600_000.0, sample.pss_kb? as f64 / 1024.0)))
+            .collect();
+        let (first, last) = (points.last()?.0, points.first()?.0);
+        if (last - first) * 3_600_000.0 < TREND_MIN_SPAN_MS as f64 {
+            return None;
+        }
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let covariance: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+        let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+        (variance > 0.0).then(|| covariance / variance)
+    }
+
+    fn status_line(&self) -> Line<'static> {
+        let mut line = Line::from(" c: clear history").dim();
+        match self.samples.back() {
+            Some(sample) if self.running => {
+                let threads = sample.threads.map_or_else(String::new, |threads| format!(", {threads} threads"));
+                let rss = sample.rss_kb.map_or_else(String::new, |rss| format!(", RSS {} MB", rss / 1024));
+                line.push_span(Span::from(format!(" · {} pid {}{threads}{rss}", sample.package, sample.pid)));
+            }
+            _ if self.running => {}
+            _ => line.push_span(Span::from(" · game not running").yellow()),
+        }
+        if let Some(err) = &self.error {
+            line.push_span(Span::from(format!(" · {err}")).red());
+        }
+        line
+    }
+
+    /// The last `width` values of `value`, scaled to integers for the sparkline.
+    fn series(&self, width: u16, value: impl Fn(&PerfSample) -> Option<f64>) -> Vec<Option<u64>> {
+        let skip = self.samples.len().saturating_sub(usize::from(width));
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        self.samples.iter().skip(skip).map(|sample| value(sample).map(|value| value.max(0.0).round() as u64)).collect()
+    }
+
+    /// Latest, lowest and highest value of a series, for chart titles.
+    fn range(&self, value: impl Fn(&PerfSample) -> Option<f64>) -> Option<(f64, f64, f64)> {
+        let values: Vec<f64> = self.samples.iter().filter_map(&value).collect();
+        let latest = self.samples.back().and_then(&value)?;
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        Some((latest, min, max))
+    }
+
+    fn chart(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        title: Line<'static>,
+        color: Color,
+        value: impl Fn(&PerfSample) -> Option<f64>,
+    ) {
+        let block = Block::bordered().title(title);
+        let data = self.series(area.width.saturating_sub(2), value);
+        frame.render_widget(Sparkline::default().block(block).data(data).style(Style::default().fg(color)), area);
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect) {
+        let [status_area, body] = Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+        frame.render_widget(self.status_line(), status_area);
+        if self.samples.is_empty() {
+            let message = if self.running || self.error.is_some() {
+                "Waiting for samples…"
+            } else {
+                "No samples yet; start the game to see its CPU, memory and frame times"
+            };
+            frame.render_widget(
+                Paragraph::new(Line::from(message).italic()).block(Block::bordered().title(" Perf ")),
+                body,
+            );
+            return;
+        }
+        let [cpu_area, memory_area, jank_area] = Layout::vertical([Constraint::Ratio(1, 3); 3]).areas(body);
+
+        let cpu = |sample: &PerfSample| sample.cpu_percent;
+        let title = self.range(cpu).map_or_else(
+            || " CPU: no data ".to_string(),
+            |(latest, min, max)| format!(" CPU {latest:.0}% (min {min:.0}%, max {max:.0}%) "),
+        );
+        self.chart(frame, cpu_area, Line::from(title), Color::Cyan, cpu);
+
+        #[allow(clippy::cast_precision_loss)]
+        let pss = |sample: &PerfSample| sample.pss_kb.map(|kb| kb as f64 / 1024.0);
+        let mut title = Line::from(self.range(pss).map_or_else(
+            || " PSS: no data ".to_string(),
+            |(latest, min, max)| format!(" PSS {latest:.0} MB (min {min:.0}, max {max:.0}) "),
+        ));
+        if let Some(trend) = self.pss_trend() {
+            let span = Span::from(format!("{trend:+.0} MB/h "));
+            title.push_span(if trend > CREEP_MB_PER_HOUR {
+                span.red().bold()
+            } else {
+                span.dim()
+            });
+        }
+        self.chart(frame, memory_area, title, Color::Magenta, pss);
+
+        let jank = |sample: &PerfSample| sample.jank_percent;
+        let title = self.range(jank).map_or_else(
+            || " Jank: no frames reported ".to_string(),
+            |(latest, _, max)| format!(" Jank {latest:.1}% (max {max:.1}%) "),
+        );
+        // Tenths of a percent, so low jank still shows.
+        self.chart(frame, jank_area, Line::from(title), Color::Yellow, |sample| jank(sample).map(|jank| jank * 10.0));
+    }
+}
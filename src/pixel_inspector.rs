@@ -0,0 +1,193 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Paragraph, Widget},
+};
+
+use crate::{screen_view::ImagePlacement, screenshot::Screenshot, tui};
+
+/// Pixels on each side of the crosshair shown in the magnifier.
+const MAGNIFIER_RADIUS: u16 = 4;
+/// Width of the side panel, sized for the 9x9 magnifier at two columns per pixel.
+pub const PANEL_WIDTH: u16 = 2 * (2 * MAGNIFIER_RADIUS + 1) + 2;
+
+/// Hue in degrees, saturation and value in percent.
+fn hsv([r, g, b]: [u8; 3]) -> (u16, u8, u8) {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = f64::from(max - min);
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((f64::from(g) - f64::from(b)) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((f64::from(b) - f64::from(r)) / delta + 2.0)
+    } else {
+        60.0 * ((f64::from(r) - f64::from(g)) / delta + 4.0)
+    };
+    let saturation = if max == 0 {
+        0.0
+    } else {
+        delta / f64::from(max) * 100.0
+    };
+    let value = f64::from(max) / 255.0 * 100.0;
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    (hue.round() as u16 % 360, saturation.round() as u8, value.round() as u8)
+}
+
+/// A color that stays readable on top of `rgb`.
+fn contrasting([r, g, b]: [u8; 3]) -> Color {
+    let luma = u32::from(r) * 299 + u32::from(g) * 587 + u32::from(b) * 114;
+    if luma > 128_000 {
+        Color::Black
+    } else {
+        Color::White
+    }
+}
+
+/// Crosshair over the SCREEN tab image for reading exact device coordinates and colors.
+#[derive(Debug, Clone)]
+pub struct PixelInspector {
+    /// Crosshair position in device pixels.
+    x: u32,
+    y: u32,
+    status: Option<String>,
+}
+
+impl PixelInspector {
+    /// Start with the crosshair in the middle of `shot`.
+    pub const fn new(shot: &Screenshot) -> Self {
+        Self {
+            x: shot.width / 2,
+            y: shot.height / 2,
+            status: None,
+        }
+    }
+
+    pub fn set_position(&mut self, x: u32, y: u32) {
+        self.x = x;
+        self.y = y;
+        self.status = None;
+    }
+
+    /// Keep the crosshair inside `shot`, e.g. after a capture with a different resolution.
+    pub fn clamp_to(&mut self, shot: &Screenshot) {
+        self.x = self.x.min(shot.width.saturating_sub(1));
+        self.y = self.y.min(shot.height.saturating_sub(1));
+    }
+
+    fn nudge(&mut self, shot: &Screenshot, dx: i64, dy: i64) {
+        let clamp = |v: u32, d: i64, len: u32| {
+            u32::try_from((i64::from(v) + d).clamp(0, i64::from(len.saturating_sub(1)))).unwrap_or_default()
+        };
+        self.set_position(clamp(self.x, dx, shot.width), clamp(self.y, dy, shot.height));
+    }
+
+    /// The values copied with `c`: `x,y #rrggbb`.
+    fn summary(&self, shot: &Screenshot) -> String {
+        let [r, g, b] = shot.pixel(self.x, self.y).unwrap_or_default();
+        format!("{},{} #{r:02x}{g:02x}{b:02x}", self.x, self.y)
+    }
+
+    /// Handle a key while inspecting. Returns `false` for keys the inspector does not use.
+    pub fn handle_key_event(&mut self, shot: &Screenshot, key_event: KeyEvent) -> bool {
+        let step = if key_event.modifiers.contains(KeyModifiers::SHIFT) {
+            10
+        } else {
+            1
+        };
+        match key_event.code {
+            KeyCode::Left | KeyCode::Char('h') => self.nudge(shot, -step, 0),
+            KeyCode::Right | KeyCode::Char('l') => self.nudge(shot, step, 0),
+            KeyCode::Up | KeyCode::Char('k') => self.nudge(shot, 0, -step),
+            KeyCode::Down | KeyCode::Char('j') => self.nudge(shot, 0, step),
+            KeyCode::Char('H') => self.nudge(shot, -10, 0),
+            KeyCode::Char('L') => self.nudge(shot, 10, 0),
+            KeyCode::Char('K') => self.nudge(shot, 0, -10),
+            KeyCode::Char('J') => self.nudge(shot, 0, 10),
+            KeyCode::Char('c') => {
+                let summary = self.summary(shot);
+                self.status = Some(match tui::copy_to_clipboard(&summary) {
+                    Ok(()) => "copied".to_string(),
+                    Err(err) => format!("copy failed: {err}"),
+                });
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Mark the crosshair on a half-block rendering of the image: the cell under it gets a `┼`
+    /// and the surrounding `block_area` border gets ticks on its row and column.
+    pub fn render_crosshair(&self, placement: &ImagePlacement, block_area: Rect, shot: &Screenshot, buf: &mut Buffer) {
+        let Some((col, row)) = placement.cell_at(self.x, self.y) else {
+            return;
+        };
+        let marker = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+        if let Some(cell) = buf.cell_mut((col, row)) {
+            let rgb = shot.pixel(self.x, self.y).unwrap_or_default();
+            cell.set_char('┼').set_fg(contrasting(rgb)).set_bg(Color::Rgb(rgb[0], rgb[1], rgb[2]));
+        }
+        for (position, symbol) in [
+            ((col, block_area.top()), "▼"),
+            ((col, block_area.bottom().saturating_sub(1)), "▲"),
+            ((block_area.left(), row), "▶"),
+            ((block_area.right().saturating_sub(1), row), "◀"),
+        ] {
+            if let Some(cell) = buf.cell_mut(position) {
+                cell.set_symbol(symbol).set_style(marker);
+            }
+        }
+    }
+
+    /// Side panel with the coordinates, the color in RGB and HSV and a magnified neighborhood.
+    pub fn render_panel(&self, shot: &Screenshot, area: Rect, buf: &mut Buffer) {
+        let block = Block::bordered().title(" Inspector ");
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let rgb @ [r, g, b] = shot.pixel(self.x, self.y).unwrap_or_default();
+        let (h, s, v) = hsv(rgb);
+        let mut lines = vec![
+            Line::from(format!("x {}  y {}", self.x, self.y)),
+            Line::from(format!("#{r:02x}{g:02x}{b:02x}")),
+            Line::from(format!("rgb {r} {g} {b}")),
+            Line::from(format!("hsv {h}° {s}% {v}%")),
+        ];
+        if let Some(status) = &self.status {
+            lines.push(Line::from(status.clone()).style(Style::default().fg(Color::Green)));
+        }
+        // Help lines must fit the panel's 18 text columns.
+        for help in ["hjkl/arrows: move", "with shift: ×10", "click: pick", "c: copy  i: close"] {
+            lines.push(Line::from(help).style(Style::default().add_modifier(Modifier::DIM)));
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        let [text_area, magnifier_area] =
+            Layout::vertical([Constraint::Length(lines.len() as u16 + 1), Constraint::Min(0)]).areas(inner);
+        Paragraph::new(lines).render(text_area, buf);
+
+        let radius = i64::from(MAGNIFIER_RADIUS);
+        for (row, dy) in (magnifier_area.y..).zip(-radius..=radius) {
+            for (col, dx) in (magnifier_area.x..).step_by(2).zip(-radius..=radius) {
+                if row >= magnifier_area.bottom() || col + 1 >= magnifier_area.right() {
+                    continue;
+                }
+                let pixel = u32::try_from(i64::from(self.x) + dx)
+                    .ok()
+                    .zip(u32::try_from(i64::from(self.y) + dy).ok())
+                    .and_then(|(x, y)| shot.pixel(x, y));
+                let (symbol, style) = match pixel {
+                    Some(p) if dx == 0 && dy == 0 => {
+                        ("[]", Style::default().bg(Color::Rgb(p[0], p[1], p[2])).fg(contrasting(p)))
+                    }
+                    Some(p) => ("  ", Style::default().bg(Color::Rgb(p[0], p[1], p[2]))),
+                    None => ("··", Style::default().add_modifier(Modifier::DIM)),
+                };
+                buf.set_string(col, row, symbol, style);
+            }
+        }
+    }
+}
@@ -0,0 +1,278 @@
+//! `adb forward` and `adb reverse` rules, managed through the adb server's host protocol.
+//!
+//! Forwards are host services (`host-serial:<serial>:forward:…`) that the server answers with
+//! `OKAY` for the connection and then `OKAY` or `FAIL` for the result. Reverse rules are
+//! `reverse:…` services handled by adbd after switching to the device's transport, which answer
+//! with fewer status words depending on the adb version, so replies are read to the end and any
+//! leading `OKAY`s skipped. Listing returns `<name> <listen> <target>` lines.
+//!
+//! Rules created here are saved in the [`DeviceProfile`] and set up again by
+//! [`spawn_restorer`] whenever the device comes online, e.g. after a USB reconnect.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{self, Read},
+    net::TcpStream,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    adb::{AdbOptions, device_serial, send_service, track_devices},
+    app_event::AppEvent,
+    app_event_sender::AppEventSender,
+    device_profile::DeviceProfile,
+};
+
+/// How long to wait for the server or device to answer a forwarding request.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Host listens, device is connected to.
+    Forward,
+    /// Device listens, host is connected to.
+    Reverse,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Forward => "forward",
+            Self::Reverse => "reverse",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct PortRule {
+    pub direction: Direction,
+    /// Socket spec of the listening side, e.g. `tcp:8080`: on the host for forwards, on the
+    /// device for reverse rules.
+    pub listen: String,
+    /// Socket spec connections are passed on to.
+    pub target: String,
+}
+
+impl fmt::Display for PortRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} → {}", self.direction, self.listen, self.target)
+    }
+}
+
+impl PortRule {
+    /// Parse `LISTEN [TARGET]` as typed by the user. A bare port number means `tcp:PORT`, and the
+    /// target defaults to the same spec as the listening side.
+    pub fn parse(direction: Direction, text: &str) -> Result<Self, String> {
+        let spec = |part: &str| {
+            if part.chars().all(|c| c.is_ascii_digit()) {
+                Ok(format!("tcp:{part}"))
+            } else if part.split_once(':').is_some_and(|(kind, rest)| !kind.is_empty() && !rest.is_empty()) {
+                Ok(part.to_string())
+            } else {
+                Err(format!("{part:?} is not a port or a socket spec such as tcp:8080"))
+            }
+        };
+        let mut parts = text.split_whitespace();
+        let listen = spec(parts.next().ok_or("expected LISTEN [TARGET]")?)?;
+        let target = parts.next().map_or_else(|| Ok(listen.clone()), spec)?;
+        if parts.next().is_some() {
+            return Err("expected LISTEN [TARGET]".to_string());
+        }
+        Ok(Self {
+            direction,
+            listen,
+            target,
+        })
+    }
+}
+
+/// What the adb server reports for a device, plus the rules saved in its profile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortRules {
+    pub serial: String,
+    pub active: Vec<PortRule>,
+    pub saved: Vec<PortRule>,
+    /// What the change that led to this listing did; empty for a plain refresh.
+    pub notice: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortChange {
+    Add(PortRule),
+    Remove(PortRule),
+}
+
+/// Open `service` for `serial`: as a host service for forwards, or on the device's transport
+/// for reverse rules.
+fn open(adb: &AdbOptions, serial: &str, direction: Direction, service: &str) -> io::Result<TcpStream> {
+    let mut stream = adb.connect_server()?;
+    stream.set_read_timeout(Some(REPLY_TIMEOUT))?;
+    match direction {
+        Direction::Forward => send_service(&mut stream, &format!("host-serial:{serial}:{service}"))?,
+        Direction::Reverse => {
+            send_service(&mut stream, &format!("host:transport:{serial}"))?;
+            send_service(&mut stream, &format!("reverse:{service}"))?;
+        }
+    }
+    Ok(stream)
+}
+
+/// Read the rest of a reply: any number of `OKAY`s, then a `FAIL` with its message or an
+/// optional length-prefixed payload.
+fn finish(mut stream: TcpStream) -> io::Result<String> {
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply)?;
+    let mut reply = reply.as_slice();
+    while let Some(rest) = reply.strip_prefix(b"OKAY") {
+        reply = rest;
+    }
+    let payload = |reply: &[u8]| {
+        let length = std::str::from_utf8(reply.get(..4)?).ok().and_then(|hex| usize::from_str_radix(hex, 16).ok())?;
+        reply.get(4..4 + length).map(|payload| String::from_utf8_lossy(payload).into_owned())
+    };
+    if let Some(rest) = reply.strip_prefix(b"FAIL") {
+        return Err(io::Error::other(payload(rest).unwrap_or_else(|| String::from_utf8_lossy(rest).into_owned())));
+    }
+    if reply.is_empty() {
+        return Ok(String::new());
+    }
+    payload(reply).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed forwarding reply"))
+}
+
+fn parse_list(text: &str, direction: Direction, serial: Option<&str>) -> Vec<PortRule> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (name, listen, target) = (fields.next()?, fields.next()?, fields.next()?);
+            serial.is_none_or(|serial| serial == name).then(|| PortRule {
+                direction,
+                listen: listen.to_string(),
+                target: target.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Every forward and reverse rule of `serial`.
+pub fn list(adb: &AdbOptions, serial: &str) -> io::Result<Vec<PortRule>> {
+    // The server lists the forwards of every device, named by serial; adbd only knows its own
+    // reverse rules and names them after the transport.
+    let forwards = finish(open(adb, serial, Direction::Forward, "list-forward")?)?;
+    let reverses = finish(open(adb, serial, Direction::Reverse, "list-forward")?)?;
+    let mut rules = parse_list(&forwards, Direction::Forward, Some(serial));
+    rules.extend(parse_list(&reverses, Direction::Reverse, None));
+    Ok(rules)
+}
+
+/// Set up `rule`, replacing a rule listening on the same spec. Returns the port the listener got
+/// for `tcp:0`, if any.
+pub fn add(adb: &AdbOptions, serial: &str, rule: &PortRule) -> io::Result<Option<String>> {
+    let service = format!("forward:{};{}", rule.listen, rule.target);
+    let port = finish(open(adb, serial, rule.direction, &service)?)?;
+    Ok(Some(port).filter(|port| !port.is_empty()))
+}
+
+pub fn remove(adb: &AdbOptions, serial: &str, rule: &PortRule) -> io::Result<()> {
+    finish(open(adb, serial, rule.direction, &format!("killforward:{}", rule.listen))?).map(drop)
+}
+
+/// Apply `change` to the device and its profile, then read back the rules.
+fn update(adb: &AdbOptions, profiles_dir: &Path, change: Option<&PortChange>) -> io::Result<PortRules> {
+    let serial = device_serial(adb)?;
+    let mut profile = DeviceProfile::load(profiles_dir, &serial)?;
+    let notice = match change {
+        None => String::new(),
+        Some(PortChange::Add(rule)) => {
+            let port = add(adb, &serial, rule)?;
+            // Saved with the port it got, so it is not given a new one on every reconnect.
+            let rule = port.as_ref().map_or_else(
+                || rule.clone(),
+                |port| PortRule {
+                    listen: format!("tcp:{port}"),
+                    ..rule.clone()
+                },
+            );
+            // A rule listening on the same spec was just replaced on the device.
+            profile.port_rules.retain(|saved| saved.direction != rule.direction || saved.listen != rule.listen);
+            profile.port_rules.push(rule.clone());
+            profile.save(profiles_dir, &serial)?;
+            format!("added {rule}")
+        }
+        Some(PortChange::Remove(rule)) => {
+            let active = list(adb, &serial)?;
+            if active.contains(rule) {
+                remove(adb, &serial, rule)?;
+            }
+            profile.port_rules.retain(|saved| saved != rule);
+            profile.save(profiles_dir, &serial)?;
+            format!("removed {rule}")
+        }
+    };
+    Ok(PortRules {
+        active: list(adb, &serial)?,
+        saved: profile.port_rules,
+        serial,
+        notice,
+    })
+}
+
+/// Apply `change`, if any, on a worker thread and deliver the device's rules as
+/// [`AppEvent::PortRules`].
+pub fn spawn_update(adb: AdbOptions, app_event_tx: AppEventSender, profiles_dir: PathBuf, change: Option<PortChange>) {
+    thread::spawn(move || {
+        let result = update(&adb, &profiles_dir, change.as_ref()).map_err(|err| err.to_string());
+        app_event_tx.send(AppEvent::PortRules(result));
+    });
+}
+
+/// Set up the saved rules of `serial` that are not active, returning how many were.
+fn restore(adb: &AdbOptions, profiles_dir: &Path, serial: &str) -> io::Result<usize> {
+    let profile = DeviceProfile::load(profiles_dir, serial)?;
+    if profile.port_rules.is_empty() {
+        return Ok(0);
+    }
+    let active = list(adb, serial)?;
+    let mut restored = 0;
+    for rule in profile.port_rules.iter().filter(|rule| !active.contains(rule)) {
+        add(adb, serial, rule).map_err(|err| io::Error::other(format!("{rule}: {err}")))?;
+        restored += 1;
+    }
+    Ok(restored)
+}
+
+/// Watch devices come and go with `host:track-devices` and restore the saved rules of every
+/// device that comes online, delivering [`AppEvent::PortRulesRestored`] when something was done.
+/// Devices online at startup count as coming online.
+pub fn spawn_restorer(adb: AdbOptions, app_event_tx: AppEventSender, profiles_dir: PathBuf) {
+    thread::spawn(move || {
+        while !app_event_tx.app_event_tx.is_closed() {
+            let mut states: HashMap<String, String> = HashMap::new();
+            let result = track_devices(&adb, |devices| {
+                let previous = std::mem::take(&mut states);
+                for (serial, state) in devices {
+                    if state == "device" && previous.get(&serial).is_none_or(|state| state != "device") {
+                        let result = restore(&adb, &profiles_dir, &serial).map_err(|err| err.to_string());
+                        if result != Ok(0) {
+                            app_event_tx.send(AppEvent::PortRulesRestored {
+                                serial: serial.clone(),
+                                result,
+                            });
+                        }
+                    }
+                    states.insert(serial, state);
+                }
+            });
+            if let Err(err) = result {
+                tracing::debug!("device tracking ended: {err}");
+            }
+            // The adb server may not be up yet, or was restarted.
+            thread::sleep(Duration::from_secs(2));
+        }
+    });
+}
@@ -0,0 +1,219 @@
+//! PORTS tab: the device's `adb forward` and `adb reverse` rules, active or saved in its profile.
+
+use std::path::PathBuf;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+};
+
+use crate::{
+    adb::AdbOptions,
+    app_event_sender::AppEventSender,
+    custom_terminal::Frame,
+    port_forward::{self, Direction, PortChange, PortRule, PortRules},
+    tui,
+};
+
+/// A rule with where it was found.
+struct Row {
+    rule: PortRule,
+    active: bool,
+    saved: bool,
+}
+
+pub struct PortsView {
+    adb: AdbOptions,
+    app_event_tx: AppEventSender,
+    profiles_dir: PathBuf,
+    serial: Option<String>,
+    rows: Vec<Row>,
+    list_state: ListState,
+    loading: bool,
+    /// Rule being typed after `a` or `A`.
+    prompt: Option<(Direction, String)>,
+    notice: Option<String>,
+    error: Option<String>,
+}
+
+impl PortsView {
+    pub fn new(adb: AdbOptions, app_event_tx: AppEventSender, profiles_dir: PathBuf) -> Self {
+        Self {
+            adb,
+            app_event_tx,
+            profiles_dir,
+            serial: None,
+            rows: Vec::new(),
+            list_state: ListState::default(),
+            loading: false,
+            prompt: None,
+            notice: None,
+            error: None,
+        }
+    }
+
+    /// List the rules the first time the tab is shown.
+    pub fn on_shown(&mut self) {
+        if self.serial.is_none() && self.error.is_none() {
+            self.update(None);
+        }
+    }
+
+    fn update(&mut self, change: Option<PortChange>) {
+        if self.loading {
+            return;
+        }
+        self.loading = true;
+        port_forward::spawn_update(self.adb.clone(), self.app_event_tx.clone(), self.profiles_dir.clone(), change);
+    }
+
+    pub fn on_rules(&mut self, result: Result<PortRules, String>) {
+        self.loading = false;
+        let rules = match result {
+            Ok(rules) => rules,
+            Err(err) => {
+                self.error = Some(err);
+                return;
+            }
+        };
+        self.error = None;
+        if !rules.notice.is_empty() {
+            self.notice = Some(rules.notice);
+        }
+        let mut rows: Vec<Row> = rules
+            .active
+            .iter()
+            .map(|rule| Row {
+                rule: rule.clone(),
+                active: true,
+                saved: rules.saved.contains(rule),
+            })
+            .collect();
+        rows.extend(rules.saved.iter().filter(|rule| !rules.active.contains(rule)).map(|rule| Row {
+            rule: rule.clone(),
+            active: false,
+            saved: true,
+        }));
+        rows.sort_by(|a, b| a.rule.cmp(&b.rule));
+        self.rows = rows;
+        self.serial = Some(rules.serial);
+        let selected = self.list_state.selected().unwrap_or_default().min(self.rows.len().saturating_sub(1));
+        self.list_state.select((!self.rows.is_empty()).then_some(selected));
+    }
+
+    /// Saved rules were set up again after `serial` came online.
+    pub fn on_restored(&mut self, serial: &str, result: Result<usize, String>) {
+        self.notice = Some(match result {
+            Ok(restored) => format!("restored {restored} saved rules on {serial}"),
+            Err(err) => format!("restoring saved rules on {serial} failed: {err}"),
+        });
+        self.update(None);
+    }
+
+    pub fn handle_key_event(&mut self, tui: &tui::Tui, key_event: KeyEvent) {
+        if let Some((direction, input)) = &mut self.prompt {
+            match key_event.code {
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Esc => self.prompt = None,
+                KeyCode::Enter => {
+                    match PortRule::parse(*direction, input) {
+                        Ok(rule) => self.update(Some(PortChange::Add(rule))),
+                        Err(err) => self.notice = Some(err),
+                    }
+                    self.prompt = None;
+                }
+                _ => return,
+            }
+            tui.frame_requester().schedule_frame();
+            return;
+        }
+        match key_event.code {
+            KeyCode::Char('r') => self.update(None),
+            KeyCode::Char('a') => self.prompt = Some((Direction::Forward, String::new())),
+            KeyCode::Char('A') => self.prompt = Some((Direction::Reverse, String::new())),
+            KeyCode::Char('d') | KeyCode::Delete => {
+                if let Some(row) = self.list_state.selected().and_then(|index| self.rows.get(index)) {
+                    self.update(Some(PortChange::Remove(row.rule.clone())));
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.list_state.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => {
+                if self.list_state.selected().is_some_and(|index| index + 1 < self.rows.len()) {
+                    self.list_state.select_next();
+                }
+            }
+            _ => return,
+        }
+        tui.frame_requester().schedule_frame();
+    }
+
+    fn status_line(&self) -> Line<'static> {
+        if let Some((direction, input)) = &self.prompt {
+            let example = match direction {
+                Direction::Forward => "host port, then device port if different, e.g. 8080 or tcp:8080 tcp:9000",
+                Direction::Reverse => "device port, then host port if different, e.g. 8080 or tcp:8080 tcp:9000",
+            };
+            return Line::from(vec![
+                Span::from(format!(" add {direction}: ")).bold(),
+                Span::from(format!("{input}▏")),
+                Span::from(format!("  {example}  enter: add  esc: cancel")).dim(),
+            ]);
+        }
+        let mut line = Line::from(" a: add forward  A: add reverse  d: remove  r: refresh").dim();
+        if self.loading {
+            line.push_span(Span::from(" · updating…").yellow());
+        }
+        if let Some(notice) = &self.notice {
+            line.push_span(Span::from(format!(" · {notice}")).green());
+        }
+        if let Some(err) = &self.error {
+            line.push_span(Span::from(format!(" · {err}")).red());
+        }
+        line
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect) {
+        let [status_area, list_area] = Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+        frame.render_widget(self.status_line(), status_area);
+        let title = self.serial.as_ref().map_or_else(|| " Ports ".to_string(), |serial| format!(" Ports of {serial} "));
+        let block = Block::bordered().title(title);
+        if self.rows.is_empty() {
+            let message = if self.loading {
+                "Listing rules…"
+            } else {
+                "No forward or reverse rules; rules added here are saved and restored when the device reconnects"
+            };
+            frame.render_widget(Paragraph::new(Line::from(message).italic()).block(block), list_area);
+            return;
+        }
+        let items: Vec<ListItem> = self
+            .rows
+            .iter()
+            .map(|row| {
+                let state = match (row.active, row.saved) {
+                    (true, true) => Span::from("active, saved").green(),
+                    (true, false) => Span::from("active").dim(),
+                    (false, _) => Span::from("saved, not active").yellow(),
+                };
+                let rule = &row.rule;
+                ListItem::new(Line::from(vec![
+                    Span::from(format!(
+                        " {:<8} {:<24} → {:<24} ",
+                        rule.direction.to_string(),
+                        rule.listen,
+                        rule.target
+                    )),
+                    state,
+                ]))
+            })
+            .collect();
+        let list = List::new(items).block(block).highlight_style(Style::default().reversed());
+        frame.render_stateful_widget(list, list_area, &mut self.list_state);
+    }
+}
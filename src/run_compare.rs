@@ -0,0 +1,389 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    symbols::Marker,
+    text::{Line, Span},
+    widgets::{
+        Axis, Block, Cell, Chart, Dataset, GraphType, List, ListItem, ListState, Paragraph, Row, StatefulWidget, Table,
+        Widget,
+    },
+};
+
+use crate::{
+    career::{CareerRun, RunStore, StatKind},
+    export::{ExportFormat, export_runs},
+    session::unix_millis,
+    tui,
+};
+
+/// Colors assigned to compared runs, in selection order.
+const RUN_COLORS: &[Color] = &[Color::Cyan, Color::Yellow, Color::Magenta, Color::Green, Color::LightRed, Color::Blue];
+
+const fn run_color(index: usize) -> Color {
+    RUN_COLORS[index % RUN_COLORS.len()]
+}
+
+/// The first turn at which the compared runs made different decisions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Position of the turn in each run's timeline.
+    pub index: usize,
+    pub turn: u32,
+    /// Decision taken by each run at that turn, `None` if the run had already ended.
+    pub decisions: Vec<Option<String>>,
+}
+
+/// Find the first turn where the runs disagree. Runs are aligned by turn position; a run that
+/// ends before the others counts as diverging at the turn it is missing.
+pub fn first_divergence(runs: &[CareerRun]) -> Option<Divergence> {
+    let longest = runs.iter().map(|r| r.turns.len()).max()?;
+    (0..longest).find_map(|index| {
+        let decisions: Vec<Option<&str>> =
+            runs.iter().map(|r| r.turns.get(index).map(|t| t.decision.as_str())).collect();
+        if decisions.windows(2).all(|w| w[0] == w[1]) {
+            return None;
+        }
+        let turn = runs.iter().find_map(|r| r.turns.get(index)).map_or(0, |t| t.number);
+        Some(Divergence {
+            index,
+            turn,
+            decisions: decisions.into_iter().map(|d| d.map(str::to_string)).collect(),
+        })
+    })
+}
+
+/// Two or more runs selected for side-by-side comparison.
+#[derive(Debug, Clone)]
+pub struct RunComparison {
+    pub runs: Vec<CareerRun>,
+    pub divergence: Option<Divergence>,
+}
+
+impl RunComparison {
+    pub fn new(runs: Vec<CareerRun>) -> Self {
+        let divergence = first_divergence(&runs);
+        Self {
+            runs,
+            divergence,
+        }
+    }
+
+    fn render_chart(&self, area: Rect, buf: &mut Buffer, stat: StatKind) {
+        let series: Vec<Vec<(f64, f64)>> = self
+            .runs
+            .iter()
+            .map(|run| run.turns.iter().map(|t| (f64::from(t.number), f64::from(t.stats.get(stat)))).collect())
+            .collect();
+        let max_turn = self.runs.iter().filter_map(|r| r.turns.last()).map(|t| t.number).max().unwrap_or(0).max(1);
+        let max_value = self.runs.iter().flat_map(|r| &r.turns).map(|t| t.stats.get(stat)).max().unwrap_or(0).max(1);
+        let y_max = f64::from(max_value) * 1.1;
+
+        let divergence_marker: Vec<(f64, f64)> = self
+            .divergence
+            .as_ref()
+            .map(|d| vec![(f64::from(d.turn), 0.0), (f64::from(d.turn), y_max)])
+            .unwrap_or_default();
+
+        let mut datasets: Vec<Dataset<'_>> = self
+            .runs
+            .iter()
+            .zip(&series)
+            .enumerate()
+            .map(|(i, (run, data))| {
+                Dataset::default()
+                    .name(run.id.clone())
+                    .marker(Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(Style::default().fg(run_color(i)))
+                    .data(data)
+            })
+            .collect();
+        if !divergence_marker.is_empty() {
+            datasets.push(
+                Dataset::default()
+                    .name("diverge")
+                    .marker(Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(Style::default().fg(Color::Red))
+                    .data(&divergence_marker),
+            );
+        }
+
+        let x_labels = vec![Span::from("0"), Span::from(format!("{}", max_turn / 2)), Span::from(max_turn.to_string())];
+        let y_labels =
+            vec![Span::from("0"), Span::from(format!("{}", max_value / 2)), Span::from(max_value.to_string())];
+        Chart::new(datasets)
+            .block(Block::bordered().title(format!(" {} by turn (s: next stat) ", stat.label())))
+            .x_axis(Axis::default().title("turn").bounds([0.0, f64::from(max_turn)]).labels(x_labels).gray())
+            .y_axis(Axis::default().bounds([0.0, y_max]).labels(y_labels).gray())
+            .render(area, buf);
+    }
+
+    fn render_divergence(&self, area: Rect, buf: &mut Buffer) {
+        let block = Block::bordered().title(" First divergence ");
+        let Some(divergence) = &self.divergence else {
+            Paragraph::new("All runs made identical decisions.".italic()).block(block).render(area, buf);
+            return;
+        };
+        let mut lines = Vec::with_capacity(self.runs.len());
+        for (i, (run, decision)) in self.runs.iter().zip(&divergence.decisions).enumerate() {
+            let previous = divergence.index.checked_sub(1).and_then(|p| run.turns.get(p)).map(|t| t.decision.as_str());
+            let decision = decision.as_deref().unwrap_or("(run ended)");
+            let mut spans = vec![
+                Span::styled(format!("{:<24}", run.id), Style::default().fg(run_color(i))),
+                Span::from(format!(" turn {:>3}: ", divergence.turn)),
+            ];
+            if let Some(previous) = previous {
+                spans.push(Span::from(format!("{previous} → ")).dim());
+            }
+            spans
+                .push(Span::styled(decision.to_string(), Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)));
+            lines.push(Line::from(spans));
+        }
+        Paragraph::new(lines).block(block).render(area, buf);
+    }
+
+    fn render_final_diff(&self, area: Rect, buf: &mut Buffer) {
+        let Some(baseline) = self.runs.first() else {
+            return;
+        };
+        let base = baseline.final_stats();
+        let header = Row::new(
+            std::iter::once(Cell::from("Stat")).chain(
+                self.runs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, run)| Cell::from(run.id.clone()).style(Style::default().fg(run_color(i)))),
+            ),
+        )
+        .add_modifier(Modifier::BOLD);
+
+        let rows = StatKind::ALL
+            .iter()
+            .map(|&kind| (kind.label(), base.get(kind), self.runs.iter().map(|r| r.final_stats().get(kind)).collect()))
+            .chain(std::iter::once((
+                "Total",
+                base.total(),
+                self.runs.iter().map(|r| r.final_stats().total()).collect(),
+            )));
+        let rows = rows.map(|(label, base_value, values): (&str, u32, Vec<u32>)| {
+            Row::new(std::iter::once(Cell::from(label)).chain(values.into_iter().enumerate().map(|(i, value)| {
+                if i == 0 {
+                    return Cell::from(value.to_string());
+                }
+                let delta = i64::from(value) - i64::from(base_value);
+                let style = match delta.signum() {
+                    1 => Style::default().fg(Color::Green),
+                    -1 => Style::default().fg(Color::Red),
+                    _ => Style::default().dim(),
+                };
+                Cell::from(Line::from(vec![
+                    Span::from(format!("{value} ")),
+                    Span::styled(format!("({delta:+})"), style),
+                ]))
+            })))
+        });
+
+        let widths = std::iter::once(Constraint::Length(10))
+            .chain(std::iter::repeat_n(Constraint::Min(12), self.runs.len()))
+            .collect::<Vec<_>>();
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(Block::bordered().title(" Final stats (delta vs first run) "));
+        Widget::render(table, area, buf);
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer, stat: StatKind) {
+        let divergence_height = u16::try_from(self.runs.len()).unwrap_or(u16::MAX).saturating_add(2);
+        let table_height = u16::try_from(StatKind::ALL.len()).unwrap_or(u16::MAX).saturating_add(4);
+        let [chart_area, divergence_area, table_area] = Layout::vertical([
+            Constraint::Min(8),
+            Constraint::Length(divergence_height),
+            Constraint::Length(table_height),
+        ])
+        .areas(area);
+        self.render_chart(chart_area, buf, stat);
+        self.render_divergence(divergence_area, buf);
+        self.render_final_diff(table_area, buf);
+    }
+}
+
+/// State for the RUNS tab: the run history list and, once two or more runs have been picked,
+/// their comparison.
+pub struct RunCompareView {
+    store: RunStore,
+    export_dir: PathBuf,
+    runs: Vec<CareerRun>,
+    list_state: ListState,
+    /// Indices into `runs`, in the order they were marked.
+    marked: Vec<usize>,
+    comparison: Option<RunComparison>,
+    stat: StatKind,
+    status: Option<String>,
+}
+
+impl RunCompareView {
+    pub fn new(store: RunStore, export_dir: PathBuf) -> Self {
+        let mut view = Self {
+            store,
+            export_dir,
+            runs: Vec::new(),
+            list_state: ListState::default(),
+            marked: Vec::new(),
+            comparison: None,
+            stat: StatKind::Speed,
+            status: None,
+        };
+        view.reload();
+        view
+    }
+
+    /// Re-read the run history from disk, dropping the current selection.
+    pub fn reload(&mut self) {
+        self.marked.clear();
+        self.comparison = None;
+        match self.store.load_all() {
+            Ok(runs) => {
+                self.status = runs.is_empty().then(|| format!("No runs found in {}", self.store.dir().display()));
+                self.runs = runs;
+            }
+            Err(err) => {
+                self.status = Some(format!("Failed to read {}: {err}", self.store.dir().display()));
+                self.runs.clear();
+            }
+        }
+        self.list_state.select((!self.runs.is_empty()).then_some(0));
+    }
+
+    fn toggle_mark(&mut self) {
+        let Some(selected) = self.list_state.selected() else {
+            return;
+        };
+        if let Some(pos) = self.marked.iter().position(|&i| i == selected) {
+            self.marked.remove(pos);
+        } else {
+            self.marked.push(selected);
+        }
+    }
+
+    fn compare_marked(&mut self) {
+        if self.marked.len() < 2 {
+            self.status = Some("Mark at least two runs with Space to compare them".to_string());
+            return;
+        }
+        let runs = self.marked.iter().filter_map(|&i| self.runs.get(i).cloned()).collect();
+        self.comparison = Some(RunComparison::new(runs));
+        self.status = None;
+    }
+
+    /// Export the marked runs, or the highlighted one if none are marked, into a fresh
+    /// timestamped directory under the export dir.
+    fn export_selected(&mut self, format: ExportFormat) {
+        let runs: Vec<&CareerRun> = if self.marked.is_empty() {
+            self.list_state.selected().and_then(|i| self.runs.get(i)).into_iter().collect()
+        } else {
+            self.marked.iter().filter_map(|&i| self.runs.get(i)).collect()
+        };
+        if runs.is_empty() {
+            return;
+        }
+        let out_dir = match fresh_export_dir(&self.export_dir) {
+            Ok(out_dir) => out_dir,
+            Err(err) => {
+                self.status = Some(format!("Export to {} failed: {err}", self.export_dir.display()));
+                return;
+            }
+        };
+        self.status = Some(match export_runs(runs.iter().copied(), format, &out_dir) {
+            Ok(_) => format!("Exported {} run(s) to {}", runs.len(), out_dir.display()),
+            Err(err) => format!("Export to {} failed: {err}", out_dir.display()),
+        });
+    }
+
+    pub fn handle_key_event(&mut self, tui: &tui::Tui, key_event: KeyEvent) {
+        if !matches!(key_event.kind, KeyEventKind::Press | KeyEventKind::Repeat) {
+            return;
+        }
+        if self.comparison.is_some() {
+            match key_event.code {
+                KeyCode::Esc | KeyCode::Backspace => self.comparison = None,
+                KeyCode::Char('s') => self.stat = self.stat.next(),
+                _ => return,
+            }
+        } else {
+            match key_event.code {
+                KeyCode::Up | KeyCode::Char('k') => self.list_state.select_previous(),
+                KeyCode::Down | KeyCode::Char('j') => self.list_state.select_next(),
+                KeyCode::Char(' ') => self.toggle_mark(),
+                KeyCode::Enter => self.compare_marked(),
+                KeyCode::Char('r') => self.reload(),
+                KeyCode::Char('e') => self.export_selected(ExportFormat::Csv),
+                KeyCode::Char('E') => self.export_selected(ExportFormat::Jsonl),
+                _ => return,
+            }
+        }
+        tui.frame_requester().schedule_frame_in(Duration::from_millis(16));
+    }
+
+    fn render_list(&mut self, area: Rect, buf: &mut Buffer) {
+        let items: Vec<ListItem<'_>> = self
+            .runs
+            .iter()
+            .enumerate()
+            .map(|(i, run)| {
+                let mark = self.marked.iter().position(|&m| m == i).map_or_else(
+                    || Span::from("[ ]").dim(),
+                    |order| Span::styled(format!("[{}]", order + 1), Style::default().fg(run_color(order))),
+                );
+                let stats = run.final_stats();
+                ListItem::new(Line::from(vec![
+                    mark,
+                    Span::from(format!(" {:<24} ", run.id)),
+                    Span::from(format!("{:<20} ", run.trainee)).cyan(),
+                    Span::from(format!("{:>3} turns  total {:>5}", run.turns.len(), stats.total())).dim(),
+                ]))
+            })
+            .collect();
+        let title = " Career runs (Space: mark, Enter: compare, e/E: export CSV/JSONL, r: reload) ";
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        StatefulWidget::render(list, area, buf, &mut self.list_state);
+    }
+
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
+        let [body, status_area] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(area);
+        if let Some(comparison) = &self.comparison {
+            comparison.render(body, buf, self.stat);
+            Line::from(" Esc: back to run list").dim().render(status_area, buf);
+        } else {
+            self.render_list(body, buf);
+            if let Some(status) = &self.status {
+                Line::from(format!(" {status}")).dim().render(status_area, buf);
+            }
+        }
+    }
+}
+
+/// Create a directory under `parent` named after the current Unix time in milliseconds, counting
+/// up from there if that name is taken, so no export lands on top of another.
+fn fresh_export_dir(parent: &Path) -> io::Result<PathBuf> {
+    fs::create_dir_all(parent)?;
+    let mut stamp = unix_millis(SystemTime::now());
+    loop {
+        let dir = parent.join(stamp.to_string());
+        match fs::create_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => stamp += 1,
+            Err(err) => return Err(err),
+        }
+    }
+}
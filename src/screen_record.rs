@@ -0,0 +1,203 @@
+//! Screen recordings of the device, pulled into the session directory.
+//!
+//! `screenrecord` stops on its own after three minutes, so a recording is a chain of segments:
+//! as soon as one ends the next one starts, while the finished file is pulled over the sync
+//! protocol and deleted from the device on another thread. Segments are saved as
+//! `<unix millis>.mp4`, named after the host time the segment was started like the session's
+//! screenshots and log lines, and listed in `index.jsonl` with their start and end times so
+//! they can be lined up with the log timeline. Stopping sends `SIGINT`, on which `screenrecord`
+//! finishes the file it is writing.
+
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{self, BufWriter, Write as _},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use serde::Serialize;
+
+use crate::{
+    adb::{AdbOptions, shell_output},
+    adb_sync::SyncConnection,
+    app_event::AppEvent,
+    app_event_sender::AppEventSender,
+    cli::RecordArgs,
+    session::unix_millis,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RecordedSegment {
+    /// Unix timestamps (milliseconds) of the host clock when the segment started and ended.
+    pub started_at: u64,
+    pub ended_at: u64,
+    pub path: PathBuf,
+    pub bytes: u64,
+}
+
+/// A segment that finished recording on the device and still has to be pulled.
+struct PendingSegment {
+    remote: String,
+    started_at: u64,
+    ended_at: u64,
+}
+
+fn screenrecord_command(options: &RecordArgs, seconds: u32, remote: &str) -> String {
+    let mut command = format!("screenrecord --time-limit {seconds}");
+    if let Some(bit_rate) = options.bit_rate {
+        let _ = write!(command, " --bit-rate {bit_rate}");
+    }
+    if let Some((width, height)) = options.size {
+        let _ = write!(command, " --size {width}x{height}");
+    }
+    let _ = write!(command, " {remote} 2>&1");
+    command
+}
+
+/// A recording in progress. Dropping it does not stop the device; call [`ScreenRecording::stop`].
+pub struct ScreenRecording {
+    adb: AdbOptions,
+    stop: Arc<AtomicBool>,
+    started: Instant,
+}
+
+impl ScreenRecording {
+    /// Start recording into `dir`, delivering an [`AppEvent::ScreenRecordSegment`] for every
+    /// saved segment and an [`AppEvent::ScreenRecordFinished`] with the segment count at the end.
+    pub fn start(adb: &AdbOptions, app_event_tx: &AppEventSender, options: RecordArgs, dir: PathBuf) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (pending_tx, pending_rx) = mpsc::channel();
+        let pulling = {
+            let (adb, tx) = (adb.clone(), app_event_tx.clone());
+            thread::spawn(move || pull_segments(&adb, &tx, &dir, &pending_rx))
+        };
+        let (adb_for_thread, thread_stop, tx) = (adb.clone(), stop.clone(), app_event_tx.clone());
+        thread::spawn(move || {
+            let recorded = record_segments(&adb_for_thread, &options, &thread_stop, &pending_tx);
+            drop(pending_tx);
+            let pulled = pulling.join().unwrap_or_else(|_| Err("pulling recordings panicked".to_string()));
+            tx.send(AppEvent::ScreenRecordFinished(recorded.and(pulled)));
+        });
+        Self {
+            adb: adb.clone(),
+            stop,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// Interrupt the running segment; it is still pulled before the recording finishes.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let adb = self.adb.clone();
+        thread::spawn(move || {
+            // Again a moment later in case the next segment was just being started.
+            let command = "pkill -INT screenrecord; sleep 1; pkill -INT screenrecord";
+            if let Err(err) = shell_output(&mut adb.device(), &[command]) {
+                tracing::warn!("failed to stop screenrecord: {err}");
+            }
+        });
+    }
+}
+
+/// Run `screenrecord` segment after segment until stopped or the limit is reached, handing each
+/// finished file to the puller.
+fn record_segments(
+    adb: &AdbOptions,
+    options: &RecordArgs,
+    stop: &AtomicBool,
+    pending: &mpsc::Sender<PendingSegment>,
+) -> Result<(), String> {
+    let mut device = adb.device();
+    let started = Instant::now();
+    let limit = options.limit_secs.map(Duration::from_secs);
+    for index in 0.. {
+        let remaining = limit.map(|limit| limit.saturating_sub(started.elapsed()));
+        if stop.load(Ordering::Relaxed) || remaining.is_some_and(|remaining| remaining.is_zero()) {
+            break;
+        }
+        // Whole seconds, at least one, so the tail of a limit is still recorded.
+        let seconds = remaining.map_or(options.segment_secs, |remaining| {
+            u32::try_from(remaining.as_secs().max(1)).unwrap_or(u32::MAX).min(options.segment_secs)
+        });
+        let started_at = unix_millis(SystemTime::now());
+        let remote = format!("/sdcard/uma-record-{started_at}-{index}.mp4");
+        let output = shell_output(&mut device, &[&screenrecord_command(options, seconds, &remote)])
+            .map_err(|err| format!("screenrecord: {err}"))?;
+        let ended_at = unix_millis(SystemTime::now());
+        let exists = shell_output(&mut device, &[&format!("[ -s {remote} ] && echo yes")])
+            .is_ok_and(|output| output.starts_with(b"yes"));
+        if !exists {
+            // Stopped before anything was written, or `screenrecord` refused to run.
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            return Err(format!("screenrecord failed: {}", String::from_utf8_lossy(&output).trim()));
+        }
+        if pending
+            .send(PendingSegment {
+                remote,
+                started_at,
+                ended_at,
+            })
+            .is_err()
+        {
+            return Err("stopped pulling recordings".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Pull every segment handed over into `dir`, appending it to the index.
+fn pull_segments(
+    adb: &AdbOptions,
+    app_event_tx: &AppEventSender,
+    dir: &Path,
+    pending: &mpsc::Receiver<PendingSegment>,
+) -> Result<usize, String> {
+    fs::create_dir_all(dir).map_err(|err| format!("{}: {err}", dir.display()))?;
+    let mut pulled = 0;
+    for segment in pending {
+        // A segment that could not be pulled is left on the device.
+        let result = pull_segment(adb, dir, &segment).map_err(|err| format!("pulling {}: {err}", segment.remote));
+        if result.is_ok()
+            && let Err(err) = shell_output(&mut adb.device(), &[&format!("rm -f {}", segment.remote)])
+        {
+            tracing::warn!("failed to delete {}: {err}", segment.remote);
+        }
+        pulled += usize::from(result.is_ok());
+        app_event_tx.send(AppEvent::ScreenRecordSegment(result));
+    }
+    Ok(pulled)
+}
+
+fn pull_segment(adb: &AdbOptions, dir: &Path, segment: &PendingSegment) -> io::Result<RecordedSegment> {
+    let path = dir.join(format!("{}.mp4", segment.started_at));
+    let mut file = BufWriter::new(fs::File::create(&path)?);
+    let mut bytes = 0;
+    SyncConnection::connect(adb)?.pull(&segment.remote, &mut file, |received| bytes = received)?;
+    let recorded = RecordedSegment {
+        started_at: segment.started_at,
+        ended_at: segment.ended_at,
+        path,
+        bytes,
+    };
+    let mut line = serde_json::to_vec(&recorded).map_err(io::Error::other)?;
+    line.push(b'\n');
+    fs::OpenOptions::new().create(true).append(true).open(dir.join("index.jsonl"))?.write_all(&line)?;
+    Ok(recorded)
+}
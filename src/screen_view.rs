@@ -0,0 +1,997 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Widget},
+};
+
+use crate::{
+    adb::AdbOptions,
+    app_event::AppEvent,
+    app_event_sender::AppEventSender,
+    cli::RecordArgs,
+    custom_terminal::Frame,
+    getevent::InputEvent,
+    input::{self, InputAction},
+    matching::{self, MatchResult},
+    pixel_inspector::{self, PixelInspector},
+    screen_record::{RecordedSegment, ScreenRecording},
+    screenshot::{self, Screenshot},
+    template::{self, Region},
+    terminal_graphics::{self, FrameImage, GraphicsProtocol},
+    touch_macro::{self, Recorder, ReplayVia, TouchMacro},
+    touch_trace::TouchTraces,
+    tui,
+};
+
+/// Auto-refresh intervals cycled through with `a`. `None` disables auto-refresh.
+const AUTO_REFRESH_STEPS: &[Option<Duration>] = &[
+    None,
+    Some(Duration::from_secs(1)),
+    Some(Duration::from_secs(2)),
+    Some(Duration::from_secs(5)),
+    Some(Duration::from_secs(10)),
+];
+
+/// Where an image ended up on screen after being fitted into an area.
+///
+/// Every cell shows two vertically stacked image pixels using `▀`, so the image is laid out on a
+/// virtual grid of `area.width` x `area.height * 2` roughly square pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImagePlacement {
+    /// Cells covered by the image.
+    pub area: Rect,
+    pub image_width: u32,
+    pub image_height: u32,
+}
+
+impl ImagePlacement {
+    /// Fit a `width` x `height` image into `bounds`, preserving its aspect ratio and centering it.
+    pub fn fit(bounds: Rect, width: u32, height: u32) -> Self {
+        let grid_w = f64::from(bounds.width);
+        let grid_h = f64::from(bounds.height) * 2.0;
+        let scale = (grid_w / f64::from(width.max(1))).min(grid_h / f64::from(height.max(1)));
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let cols = ((f64::from(width) * scale).round() as u16).clamp(1, bounds.width.max(1));
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let rows = ((f64::from(height) * scale / 2.0).ceil() as u16).clamp(1, bounds.height.max(1));
+        let x = bounds.x + (bounds.width.saturating_sub(cols)) / 2;
+        let y = bounds.y + (bounds.height.saturating_sub(rows)) / 2;
+        Self {
+            area: Rect::new(x, y, cols, rows),
+            image_width: width,
+            image_height: height,
+        }
+    }
+
+    /// The device pixel shown at the center of terminal cell `(col, row)`, if the image covers it.
+    pub fn device_at(&self, col: u16, row: u16) -> Option<(u32, u32)> {
+        if !self.area.contains((col, row).into()) {
+            return None;
+        }
+        let vx = u32::from(col - self.area.x);
+        let vy = u32::from(row - self.area.y) * 2;
+        let (x0, x1, y0, _) = self.device_box(vx, vy);
+        let (_, _, _, y1) = self.device_box(vx, vy + 1);
+        Some((x0.midpoint(x1), y0.midpoint(y1).min(self.image_height.saturating_sub(1))))
+    }
+
+    /// The cell of the image nearest to terminal cell `(col, row)`.
+    pub fn nearest_cell(&self, col: u16, row: u16) -> (u16, u16) {
+        let area = self.area;
+        (
+            col.clamp(area.left(), area.right().saturating_sub(1).max(area.left())),
+            row.clamp(area.top(), area.bottom().saturating_sub(1).max(area.top())),
+        )
+    }
+
+    /// The terminal cell showing device pixel `(x, y)`.
+    pub fn cell_at(&self, x: u32, y: u32) -> Option<(u16, u16)> {
+        if x >= self.image_width || y >= self.image_height {
+            return None;
+        }
+        let col = x * u32::from(self.area.width) / self.image_width;
+        let row = y * u32::from(self.area.height) / self.image_height;
+        Some((self.area.x + u16::try_from(col).ok()?, self.area.y + u16::try_from(row).ok()?))
+    }
+
+    /// Device pixels covered by virtual pixel `(vx, vy)`, as a half-open `(x0, x1, y0, y1)` box.
+    fn device_box(&self, vx: u32, vy: u32) -> (u32, u32, u32, u32) {
+        let cols = u32::from(self.area.width);
+        let rows = u32::from(self.area.height) * 2;
+        let x0 = vx * self.image_width / cols;
+        let x1 = ((vx + 1) * self.image_width / cols).max(x0 + 1);
+        let y0 = vy * self.image_height / rows;
+        let y1 = ((vy + 1) * self.image_height / rows).max(y0 + 1);
+        (x0, x1, y0, y1)
+    }
+}
+
+/// Average a few samples from the device-pixel box so downscaled text does not shimmer as much
+/// as plain nearest-neighbour sampling.
+fn sample(shot: &Screenshot, (x0, x1, y0, y1): (u32, u32, u32, u32)) -> Option<[u8; 3]> {
+    let xs = [x0 + (x1 - x0) / 4, x0 + (x1 - x0) * 3 / 4];
+    let ys = [y0 + (y1 - y0) / 4, y0 + (y1 - y0) * 3 / 4];
+    let mut sum = [0u32; 3];
+    let mut count = 0u32;
+    for &y in &ys {
+        for &x in &xs {
+            if let Some(p) = shot.pixel(x, y) {
+                for (acc, c) in sum.iter_mut().zip(p) {
+                    *acc += u32::from(c);
+                }
+                count += 1;
+            }
+        }
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    (count > 0).then(|| sum.map(|c| (c / count) as u8))
+}
+
+/// Map an RGB color to the closest entry of the xterm 256-color palette, for terminals without
+/// truecolor support.
+fn ansi256([r, g, b]: [u8; 3]) -> u8 {
+    if r.abs_diff(g) < 10 && g.abs_diff(b) < 10 {
+        return match r {
+            0..8 => 16,
+            249.. => 231,
+            #[allow(clippy::cast_possible_truncation)]
+            _ => 232 + ((u16::from(r) - 8) * 24 / 241) as u8,
+        };
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    let q = |c: u8| ((u16::from(c) * 5 + 127) / 255) as u8;
+    16 + 36 * q(r) + 6 * q(g) + q(b)
+}
+
+fn to_color(rgb: [u8; 3], truecolor: bool) -> Color {
+    if truecolor {
+        Color::Rgb(rgb[0], rgb[1], rgb[2])
+    } else {
+        Color::Indexed(ansi256(rgb))
+    }
+}
+
+/// Draw `shot` into `bounds` with upper half block characters: the foreground paints the top pixel
+/// of each cell and the background the bottom one. Works on any terminal that can show colors.
+pub fn render_half_blocks(shot: &Screenshot, bounds: Rect, buf: &mut Buffer) -> ImagePlacement {
+    let placement = ImagePlacement::fit(bounds, shot.width, shot.height);
+    let truecolor = supports_color::on_cached(supports_color::Stream::Stdout).is_some_and(|level| level.has_16m);
+    let area = placement.area;
+    for row in 0..area.height {
+        for col in 0..area.width {
+            let vx = u32::from(col);
+            let top = sample(shot, placement.device_box(vx, u32::from(row) * 2)).unwrap_or_default();
+            let bottom = sample(shot, placement.device_box(vx, u32::from(row) * 2 + 1)).unwrap_or_default();
+            if let Some(cell) = buf.cell_mut((area.x + col, area.y + row)) {
+                cell.set_char('▀').set_fg(to_color(top, truecolor)).set_bg(to_color(bottom, truecolor));
+            }
+        }
+    }
+    placement
+}
+
+/// Draw `shot` into `bounds` with the session's terminal graphics protocol, falling back to half
+/// blocks when the terminal has none.
+pub fn render_screenshot(frame: &mut Frame, shot: &Screenshot, bounds: Rect) -> ImagePlacement {
+    if terminal_graphics::protocol() == GraphicsProtocol::HalfBlocks {
+        return render_half_blocks(shot, bounds, frame.buffer_mut());
+    }
+    let placement = ImagePlacement::fit(bounds, shot.width, shot.height);
+    frame.render_image(FrameImage {
+        id: shot.id,
+        area: placement.area,
+        width: shot.width,
+        height: shot.height,
+        png: shot.png.clone(),
+        rgb: shot.rgb.clone(),
+    });
+    placement
+}
+
+/// Outline `region` on a half-block rendering of the image.
+pub fn render_region_outline(placement: &ImagePlacement, region: Region, style: Style, buf: &mut Buffer) {
+    let (Some((left, top)), Some((right, bottom))) =
+        (placement.cell_at(region.x, region.y), placement.cell_at(region.right() - 1, region.bottom() - 1))
+    else {
+        return;
+    };
+    for row in top..=bottom {
+        for col in left..=right {
+            let symbol = match (col == left, col == right, row == top, row == bottom) {
+                (true, _, true, _) => "┌",
+                (_, true, true, _) => "┐",
+                (true, _, _, true) => "└",
+                (_, true, _, true) => "┘",
+                (_, _, true, _) | (_, _, _, true) => "─",
+                (true, _, _, _) | (_, true, _, _) => "│",
+                _ => continue,
+            };
+            if let Some(cell) = buf.cell_mut((col, row)) {
+                cell.set_symbol(symbol).set_style(style);
+            }
+        }
+    }
+}
+
+/// Width of the match list next to the image.
+const MATCH_PANEL_WIDTH: u16 = 32;
+
+fn match_style(score: f32, threshold: f32) -> Style {
+    if score >= threshold {
+        Style::default().fg(Color::Green)
+    } else {
+        Style::default().fg(Color::Red)
+    }
+}
+
+/// Outline the best position of a template and label it with its name and score.
+fn render_match(placement: &ImagePlacement, result: &MatchResult, threshold: f32, buf: &mut Buffer) {
+    let style = match_style(result.score, threshold);
+    render_region_outline(placement, result.region, style, buf);
+    if let Some((col, row)) = placement.cell_at(result.region.x, result.region.y) {
+        let label = format!("{} {:.2}", result.template, result.score);
+        let max_width = usize::from(buf.area.right().saturating_sub(col + 1));
+        buf.set_stringn(col + 1, row, label, max_width, style.reversed());
+    }
+}
+
+/// Every template of the set, best score first.
+fn render_match_panel(matches: &[MatchResult], threshold: f32, area: Rect, buf: &mut Buffer) {
+    let block = Block::bordered().title(" Matches ");
+    let inner = block.inner(area);
+    block.render(area, buf);
+    let mut sorted: Vec<&MatchResult> = matches.iter().collect();
+    sorted.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let lines: Vec<Line> = sorted
+        .into_iter()
+        .map(|result| {
+            let mut line = Line::from(vec![
+                Span::styled(format!("{:5.2} ", result.score), match_style(result.score, threshold)),
+                Span::from(result.template.clone()),
+            ]);
+            if result.resolution_mismatch {
+                line.push_span(Span::from(" ≠res").yellow());
+            }
+            line
+        })
+        .collect();
+    if lines.is_empty() {
+        Paragraph::new(Line::from("no templates fit the frame").italic()).render(inner, buf);
+    } else {
+        Paragraph::new(lines).render(inner, buf);
+    }
+}
+
+/// Human friendly "how long ago" for a capture time.
+pub fn format_age(at: SystemTime) -> String {
+    let secs = at.elapsed().unwrap_or_default().as_secs();
+    match secs {
+        0..60 => format!("{secs}s ago"),
+        60..3600 => format!("{}m{:02}s ago", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m ago", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// What a left click or drag on the image does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MouseMode {
+    /// Move the inspector crosshair, opening it if needed.
+    Inspect,
+    /// Span a region to save as a template. Toggled with `v`.
+    Select,
+    /// Tap or swipe on the device. Toggled with `t`.
+    Touch,
+}
+
+/// State for the SCREEN tab: the latest device frame and the capture/auto-refresh machinery.
+pub struct ScreenView {
+    adb: AdbOptions,
+    app_event_tx: AppEventSender,
+    latest: Option<Screenshot>,
+    capturing: bool,
+    auto_refresh_step: usize,
+    /// Bumped whenever the auto-refresh interval changes so timers armed earlier are ignored.
+    refresh_generation: u64,
+    last_error: Option<String>,
+    /// Crosshair and pixel readout, toggled with `i`.
+    inspector: Option<PixelInspector>,
+    /// Where the image was drawn on the last frame, for mapping mouse clicks to device pixels.
+    placement: Option<ImagePlacement>,
+    template_dir: PathBuf,
+    /// Where touch macros recorded with `R` are saved; `P` replays the newest one.
+    macro_dir: PathBuf,
+    /// The `getevent` recording in progress.
+    recording: Option<Recorder>,
+    /// Options and destination of screen recordings started with `V`.
+    record_options: RecordArgs,
+    video_dir: PathBuf,
+    /// The screen recording in progress, kept until its last segment is pulled.
+    video: Option<ScreenRecording>,
+    /// Live touch traces drawn over the image, toggled with `T`.
+    touches: Option<TouchTraces>,
+    replaying: bool,
+    mouse_mode: MouseMode,
+    /// Device position and time of the mouse press that starts a tap or swipe in touch mode.
+    touch_start: Option<((u32, u32), Instant)>,
+    /// Anchor and opposite corner of the dragged region, in device pixels.
+    selection: Option<((u32, u32), (u32, u32))>,
+    /// Template name being typed after pressing `s`.
+    naming: Option<String>,
+    /// Input command being typed after pressing `:`.
+    command: Option<String>,
+    notice: Option<String>,
+    /// Template set directories found under `template_dir`. Non-empty while matching is on, in
+    /// which case every new frame is matched against the selected set. Toggled with `m`.
+    template_sets: Vec<PathBuf>,
+    template_set: usize,
+    matching_in_flight: bool,
+    /// Latest match results and the id of the screenshot they were computed on.
+    matches: Option<(u32, Vec<MatchResult>)>,
+    threshold: f32,
+}
+
+impl ScreenView {
+    pub fn new(
+        adb: AdbOptions,
+        app_event_tx: AppEventSender,
+        template_dir: PathBuf,
+        macro_dir: PathBuf,
+        record_options: RecordArgs,
+        video_dir: PathBuf,
+    ) -> Self {
+        Self {
+            adb,
+            app_event_tx,
+            latest: None,
+            capturing: false,
+            auto_refresh_step: 0,
+            refresh_generation: 0,
+            last_error: None,
+            inspector: None,
+            placement: None,
+            template_dir,
+            macro_dir,
+            recording: None,
+            record_options,
+            video_dir,
+            video: None,
+            touches: None,
+            replaying: false,
+            mouse_mode: MouseMode::Inspect,
+            touch_start: None,
+            selection: None,
+            naming: None,
+            command: None,
+            notice: None,
+            template_sets: Vec::new(),
+            template_set: 0,
+            matching_in_flight: false,
+            matches: None,
+            threshold: matching::DEFAULT_THRESHOLD,
+        }
+    }
+
+    fn auto_refresh(&self) -> Option<Duration> {
+        AUTO_REFRESH_STEPS[self.auto_refresh_step]
+    }
+
+    /// Called when the tab becomes visible; grabs a first frame if there is none yet.
+    pub fn on_shown(&mut self) {
+        if self.latest.is_none() && self.last_error.is_none() {
+            self.request_capture();
+        }
+    }
+
+    /// Start a capture on a worker thread unless one is already in flight. The result arrives as
+    /// [`AppEvent::ScreenCaptured`].
+    pub fn request_capture(&mut self) {
+        if self.capturing {
+            return;
+        }
+        self.capturing = true;
+        let adb = self.adb.clone();
+        let tx = self.app_event_tx.clone();
+        thread::spawn(move || {
+            let result = screenshot::capture(&adb).map_err(|err| format!("{err:#}"));
+            tx.send(AppEvent::ScreenCaptured(result));
+        });
+    }
+
+    pub fn on_captured(&mut self, result: Result<Screenshot, String>) {
+        self.capturing = false;
+        match result {
+            Ok(shot) => {
+                if let Some(inspector) = &mut self.inspector {
+                    inspector.clamp_to(&shot);
+                }
+                self.latest = Some(shot);
+                self.last_error = None;
+                if self.matching() {
+                    self.request_matches();
+                }
+            }
+            Err(err) => self.last_error = Some(err),
+        }
+        self.arm_auto_refresh();
+    }
+
+    /// Match the selected template set against the latest frame on a worker thread. The result
+    /// arrives as [`AppEvent::TemplateMatches`].
+    fn request_matches(&mut self) {
+        let (Some(shot), Some(set)) = (&self.latest, self.template_sets.get(self.template_set)) else {
+            return;
+        };
+        if self.matching_in_flight {
+            // `on_matches` notices the newer frame and runs again.
+            return;
+        }
+        self.matching_in_flight = true;
+        let (screenshot_id, width, height, rgb) = (shot.id, shot.width, shot.height, shot.rgb.clone());
+        let set = set.clone();
+        let tx = self.app_event_tx.clone();
+        thread::spawn(move || {
+            let result = template::load_set(&set)
+                .map(|templates| matching::match_templates(width, height, &rgb, &templates))
+                .map_err(|err| format!("{err:#}"));
+            tx.send(AppEvent::TemplateMatches {
+                screenshot_id,
+                result,
+            });
+        });
+    }
+
+    pub fn on_matches(&mut self, screenshot_id: u32, result: Result<Vec<MatchResult>, String>) {
+        self.matching_in_flight = false;
+        if !self.matching() {
+            return;
+        }
+        match result {
+            Ok(matches) => self.matches = Some((screenshot_id, matches)),
+            Err(err) => self.notice = Some(format!("matching failed: {err}")),
+        }
+        if self.latest.as_ref().is_some_and(|shot| shot.id != screenshot_id) {
+            self.request_matches();
+        }
+    }
+
+    const fn matching(&self) -> bool {
+        !self.template_sets.is_empty()
+    }
+
+    fn toggle_matching(&mut self) {
+        self.matches = None;
+        if self.matching() {
+            self.template_sets.clear();
+            return;
+        }
+        match template::list_sets(&self.template_dir) {
+            Ok(sets) if !sets.is_empty() => {
+                self.template_set = self.template_set.min(sets.len() - 1);
+                self.template_sets = sets;
+                self.request_matches();
+            }
+            Ok(_) => self.notice = Some(format!("no templates in {}", self.template_dir.display())),
+            Err(err) => self.notice = Some(format!("failed to list templates: {err}")),
+        }
+    }
+
+    fn cycle_template_set(&mut self) {
+        if !self.matching() {
+            return;
+        }
+        self.template_set = (self.template_set + 1) % self.template_sets.len();
+        self.matches = None;
+        self.request_matches();
+    }
+
+    fn template_set_name(&self) -> Option<String> {
+        let set = self.template_sets.get(self.template_set)?;
+        Some(
+            set.strip_prefix(&self.template_dir)
+                .ok()
+                .filter(|rel| !rel.as_os_str().is_empty())
+                .map_or_else(|| ".".to_string(), |rel| rel.display().to_string()),
+        )
+    }
+
+    /// Matches computed on the frame currently shown.
+    fn current_matches(&self) -> Option<&[MatchResult]> {
+        match (&self.matches, &self.latest) {
+            (Some((id, matches)), Some(shot)) if *id == shot.id => Some(matches),
+            _ => None,
+        }
+    }
+
+    /// Schedule the next auto-refresh capture. Captures are chained rather than run on a fixed
+    /// timer so a slow device never has more than one `screencap` in flight.
+    fn arm_auto_refresh(&self) {
+        let Some(interval) = self.auto_refresh() else {
+            return;
+        };
+        let generation = self.refresh_generation;
+        let tx = self.app_event_tx.clone();
+        thread::spawn(move || {
+            thread::sleep(interval);
+            tx.send(AppEvent::ScreenRefreshDue(generation));
+        });
+    }
+
+    pub fn on_refresh_due(&mut self, generation: u64) {
+        if generation == self.refresh_generation && self.auto_refresh().is_some() {
+            self.request_capture();
+        }
+    }
+
+    fn cycle_auto_refresh(&mut self) {
+        self.auto_refresh_step = (self.auto_refresh_step + 1) % AUTO_REFRESH_STEPS.len();
+        self.refresh_generation = self.refresh_generation.wrapping_add(1);
+        if self.auto_refresh().is_some() {
+            if self.capturing {
+                // The in-flight capture re-arms the timer with the new generation when it lands.
+                return;
+            }
+            self.request_capture();
+        }
+    }
+
+    pub fn handle_key_event(&mut self, tui: &tui::Tui, key_event: KeyEvent) {
+        if !matches!(key_event.kind, KeyEventKind::Press | KeyEventKind::Repeat) {
+            return;
+        }
+        if self.naming.is_some() {
+            self.handle_naming_key(key_event);
+            tui.frame_requester().schedule_frame();
+            return;
+        }
+        if self.command.is_some() {
+            self.handle_command_key(key_event);
+            tui.frame_requester().schedule_frame();
+            return;
+        }
+        if let (Some(inspector), Some(shot)) = (&mut self.inspector, &self.latest)
+            && inspector.handle_key_event(shot, key_event)
+        {
+            tui.frame_requester().schedule_frame();
+            return;
+        }
+        match key_event.code {
+            KeyCode::Char('r') => self.request_capture(),
+            KeyCode::Char('a') => self.cycle_auto_refresh(),
+            KeyCode::Char('i') => self.toggle_inspector(),
+            KeyCode::Char('v') => {
+                self.toggle_mouse_mode(MouseMode::Select);
+                self.selection = None;
+            }
+            KeyCode::Char('t') => self.toggle_mouse_mode(MouseMode::Touch),
+            KeyCode::Char(':') => self.command = Some(String::new()),
+            KeyCode::Char('R') => self.toggle_recording(),
+            KeyCode::Char('P') => self.replay_latest_macro(),
+            KeyCode::Char('V') => self.toggle_video(),
+            KeyCode::Char('T') => {
+                self.touches = match self.touches {
+                    Some(_) => None,
+                    None => Some(TouchTraces::start(&self.adb, &self.app_event_tx)),
+                };
+            }
+            KeyCode::Char('s') => self.start_naming(),
+            KeyCode::Char('m') => self.toggle_matching(),
+            KeyCode::Char('M') => self.cycle_template_set(),
+            KeyCode::Char('+' | '=') if self.matching() => self.threshold = (self.threshold + 0.05).min(1.0),
+            KeyCode::Char('-') if self.matching() => self.threshold = (self.threshold - 0.05).max(-1.0),
+            KeyCode::Esc if self.mouse_mode == MouseMode::Select => {
+                self.mouse_mode = MouseMode::Inspect;
+                self.selection = None;
+            }
+            KeyCode::Esc if self.mouse_mode == MouseMode::Touch => self.mouse_mode = MouseMode::Inspect,
+            KeyCode::Esc if self.inspector.is_some() => self.inspector = None,
+            KeyCode::Esc if self.matching() => self.toggle_matching(),
+            _ => return,
+        }
+        tui.frame_requester().schedule_frame();
+    }
+
+    fn selected_region(&self) -> Option<Region> {
+        self.selection.map(|(anchor, corner)| Region::from_corners(anchor, corner))
+    }
+
+    fn start_naming(&mut self) {
+        let Some(region) = self.selected_region() else {
+            self.notice = Some("drag a region first (v to select)".to_string());
+            return;
+        };
+        self.naming = Some(format!("template_{}_{}", region.x, region.y));
+    }
+
+    fn handle_naming_key(&mut self, key_event: KeyEvent) {
+        let Some(name) = &mut self.naming else {
+            return;
+        };
+        match key_event.code {
+            KeyCode::Char(c) => name.push(c),
+            KeyCode::Backspace => {
+                name.pop();
+            }
+            KeyCode::Esc => self.naming = None,
+            KeyCode::Enter => {
+                let name = self.naming.take().unwrap_or_default();
+                self.save_template(&name);
+            }
+            _ => {}
+        }
+    }
+
+    fn toggle_mouse_mode(&mut self, mode: MouseMode) {
+        self.mouse_mode = if self.mouse_mode == mode {
+            MouseMode::Inspect
+        } else {
+            mode
+        };
+        self.touch_start = None;
+    }
+
+    fn handle_command_key(&mut self, key_event: KeyEvent) {
+        let Some(command) = &mut self.command else {
+            return;
+        };
+        match key_event.code {
+            KeyCode::Char(c) => command.push(c),
+            KeyCode::Backspace => {
+                command.pop();
+            }
+            KeyCode::Esc => self.command = None,
+            KeyCode::Enter => {
+                let command = self.command.take().unwrap_or_default();
+                match InputAction::parse(&command) {
+                    Ok(action) => self.send_input(action),
+                    Err(err) => self.notice = Some(err),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn send_input(&mut self, action: InputAction) {
+        self.notice = Some(format!("sending {action}…"));
+        input::spawn_send(&self.adb, &self.app_event_tx, action);
+    }
+
+    /// An injected input went through or failed. A fresh frame is captured so its effect shows.
+    pub fn on_input_sent(&mut self, action: &InputAction, result: Result<(), String>) {
+        match result {
+            Ok(()) => {
+                self.notice = Some(format!("sent {action}"));
+                self.request_capture();
+            }
+            Err(err) => self.notice = Some(format!("{action} failed: {err}")),
+        }
+    }
+
+    fn toggle_recording(&mut self) {
+        let Some(recorder) = self.recording.take() else {
+            self.recording = Some(Recorder::start(&self.adb));
+            return;
+        };
+        let touch_macro = recorder.finish();
+        if touch_macro.events.is_empty() {
+            self.notice = Some("recording stopped, no input captured".to_string());
+            return;
+        }
+        let path = self.macro_dir.join(format!("{}.json", touch_macro.recorded_at));
+        self.notice = Some(match touch_macro.save(&path) {
+            Ok(()) => format!("saved {} events to {}", touch_macro.events.len(), path.display()),
+            Err(err) => format!("saving macro failed: {err}"),
+        });
+    }
+
+    fn toggle_video(&mut self) {
+        match &self.video {
+            Some(video) if !video.is_stopping() => video.stop(),
+            // Still pulling the last segment.
+            Some(_) => {}
+            None => {
+                self.video = Some(ScreenRecording::start(
+                    &self.adb,
+                    &self.app_event_tx,
+                    self.record_options.clone(),
+                    self.video_dir.clone(),
+                ));
+            }
+        }
+    }
+
+    pub fn on_video_segment(&mut self, result: Result<RecordedSegment, String>) {
+        self.notice = Some(match result {
+            Ok(segment) => format!("saved {} ({} MB)", segment.path.display(), segment.bytes / (1024 * 1024)),
+            Err(err) => err,
+        });
+    }
+
+    pub fn on_video_finished(&mut self, result: Result<usize, String>) {
+        self.video = None;
+        self.notice = Some(match result {
+            Ok(segments) => format!("screen recording finished, {segments} segments in {}", self.video_dir.display()),
+            Err(err) => format!("screen recording failed: {err}"),
+        });
+    }
+
+    fn replay_latest_macro(&mut self) {
+        if self.replaying || self.recording.is_some() {
+            return;
+        }
+        let Some(path) = touch_macro::latest_in(&self.macro_dir) else {
+            self.notice = Some(format!("no macros in {}", self.macro_dir.display()));
+            return;
+        };
+        self.replaying = true;
+        self.notice = Some(format!("replaying {}…", path.display()));
+        let adb = self.adb.clone();
+        let tx = self.app_event_tx.clone();
+        thread::spawn(move || {
+            let result = TouchMacro::load(&path)
+                .and_then(|touch_macro| touch_macro.replay(&adb, 1.0, ReplayVia::Sendevent))
+                .map(|()| path)
+                .map_err(|err| format!("{err:#}"));
+            tx.send(AppEvent::MacroReplayed(result));
+        });
+    }
+
+    pub fn on_input_events(&mut self, tui: &tui::Tui, events: Vec<InputEvent>) {
+        if let Some(touches) = &mut self.touches {
+            touches.feed(tui, events);
+        }
+    }
+
+    pub fn on_touch_ranges(&mut self, ranges: HashMap<String, (u32, u32)>) {
+        if let Some(touches) = &mut self.touches {
+            touches.set_ranges(ranges);
+        }
+    }
+
+    pub fn on_macro_replayed(&mut self, result: Result<PathBuf, String>) {
+        self.replaying = false;
+        self.notice = Some(match result {
+            Ok(path) => format!("replayed {}", path.display()),
+            Err(err) => format!("replay failed: {err}"),
+        });
+        self.request_capture();
+    }
+
+    /// Turn a press and release in touch mode into a tap, or a swipe if the pointer moved.
+    fn finish_touch(&mut self, to: (u32, u32), width: u32) {
+        let Some((from, pressed_at)) = self.touch_start.take() else {
+            return;
+        };
+        let slop = (width / 100).max(8);
+        let action = if from.0.abs_diff(to.0) <= slop && from.1.abs_diff(to.1) <= slop {
+            InputAction::Tap {
+                x: from.0,
+                y: from.1,
+            }
+        } else {
+            InputAction::Swipe {
+                from,
+                to,
+                duration: pressed_at.elapsed().clamp(Duration::from_millis(100), Duration::from_secs(2)),
+            }
+        };
+        self.send_input(action);
+    }
+
+    fn save_template(&mut self, name: &str) {
+        let (Some(shot), Some(region)) = (&self.latest, self.selected_region()) else {
+            return;
+        };
+        self.notice = Some(match template::save(&self.template_dir, name, shot, region) {
+            Ok(path) => format!("saved {}", path.display()),
+            Err(err) => format!("saving template failed: {err}"),
+        });
+    }
+
+    fn toggle_inspector(&mut self) {
+        self.inspector = match (&self.inspector, &self.latest) {
+            (None, Some(shot)) => Some(PixelInspector::new(shot)),
+            _ => None,
+        };
+    }
+
+    /// Clicking or dragging on the image moves the crosshair there, opening the inspector. In
+    /// selection mode a drag spans the region to save as a template instead, and in touch mode a
+    /// click taps the device and a drag swipes it.
+    pub fn handle_mouse_event(&mut self, tui: &tui::Tui, mouse_event: MouseEvent) {
+        if !matches!(
+            mouse_event.kind,
+            MouseEventKind::Down(MouseButton::Left)
+                | MouseEventKind::Drag(MouseButton::Left)
+                | MouseEventKind::Up(MouseButton::Left)
+        ) {
+            return;
+        }
+        let released = matches!(mouse_event.kind, MouseEventKind::Up(_));
+        let (Some(shot), Some(placement)) = (&self.latest, &self.placement) else {
+            self.touch_start = None;
+            return;
+        };
+        // A swipe released off the image ends at its edge.
+        let (column, row) = if released {
+            placement.nearest_cell(mouse_event.column, mouse_event.row)
+        } else {
+            (mouse_event.column, mouse_event.row)
+        };
+        let Some((x, y)) = placement.device_at(column, row) else {
+            if released {
+                self.touch_start = None;
+            }
+            return;
+        };
+        match (self.mouse_mode, mouse_event.kind) {
+            (MouseMode::Touch, MouseEventKind::Down(_)) => self.touch_start = Some(((x, y), Instant::now())),
+            (MouseMode::Touch, MouseEventKind::Up(_)) => self.finish_touch((x, y), shot.width),
+            (MouseMode::Touch, _) | (_, MouseEventKind::Up(_)) => return,
+            (MouseMode::Select, kind) => {
+                let anchor = match (kind, self.selection) {
+                    (MouseEventKind::Drag(_), Some((anchor, _))) => anchor,
+                    _ => (x, y),
+                };
+                self.selection = Some((anchor, (x, y)));
+            }
+            (MouseMode::Inspect, _) => {
+                self.inspector.get_or_insert_with(|| PixelInspector::new(shot)).set_position(x, y);
+            }
+        }
+        tui.frame_requester().schedule_frame();
+    }
+
+    fn status_line(&self) -> Line<'static> {
+        if let Some(name) = &self.naming {
+            return Line::from(vec![
+                Span::from(" template name: ").bold(),
+                Span::from(format!("{name}▏")),
+                Span::from("  enter: save  esc: cancel").dim(),
+            ]);
+        }
+        if let Some(command) = &self.command {
+            return Line::from(vec![
+                Span::from(" input: ").bold(),
+                Span::from(format!("{command}▏")),
+                Span::from("  tap X Y · swipe X1 Y1 X2 Y2 [MS] · key back · text STRING  enter: send  esc: cancel")
+                    .dim(),
+            ]);
+        }
+        let mut parts = Vec::new();
+        if let Some(shot) = &self.latest {
+            parts.push(format!("{}x{}", shot.width, shot.height));
+            parts.push(format!("captured {}", format_age(shot.captured_at)));
+        }
+        parts.push(
+            self.auto_refresh()
+                .map_or_else(|| "auto-refresh off".to_string(), |i| format!("auto-refresh {}s", i.as_secs())),
+        );
+        if self.capturing {
+            parts.push("capturing…".to_string());
+        }
+        if self.recording.is_some() {
+            parts.push("● recording input (R: stop)".to_string());
+        }
+        if let Some(video) = &self.video {
+            let secs = video.elapsed().as_secs();
+            parts.push(if video.is_stopping() {
+                "saving screen recording…".to_string()
+            } else {
+                format!("● screen recording {}:{:02} (V: stop)", secs / 60, secs % 60)
+            });
+        }
+        if let Some(touches) = &self.touches {
+            parts.push(format!("touch trails, {} down (T: off)", touches.active_traces()));
+        }
+        if self.matching() {
+            let set = self.template_set_name().unwrap_or_default();
+            parts.push(format!("templates {set} ≥{:.2}", self.threshold));
+            if self.matching_in_flight {
+                parts.push("matching…".to_string());
+            }
+        }
+        if let Some(region) = self.selected_region() {
+            parts.push(format!("region {},{} {}x{}", region.x, region.y, region.width, region.height));
+        }
+        if self.mouse_mode == MouseMode::Select {
+            parts.push("drag to select  s: save template  v: done".to_string());
+        } else if self.mouse_mode == MouseMode::Touch {
+            parts.push("click: tap  drag: swipe  :: input command  R: record  P: replay last  t: done".to_string());
+        } else if self.matching() {
+            parts.push("M: next set  +/-: threshold  m: stop matching".to_string());
+        } else {
+            parts.push(
+                "r: refresh  a: auto-refresh  i: inspect  v: select region  m: match  t: touch  V: record video"
+                    .to_string(),
+            );
+        }
+        let mut line = Line::from(format!(" {}", parts.join(" · "))).dim();
+        if let Some(notice) = &self.notice {
+            line.push_span(Span::from(format!(" · {notice}")).green());
+        }
+        if let (Some(_), Some(err)) = (&self.latest, &self.last_error) {
+            line.push_span(Span::from(format!(" · last capture failed: {err}")).red());
+        }
+        line
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect) {
+        let [status_area, mut image_area] = Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+        frame.render_widget(self.status_line(), status_area);
+
+        self.placement = None;
+        let inspecting = self.inspector.as_ref().zip(self.latest.as_ref());
+        if let Some((inspector, shot)) = inspecting {
+            let [image, panel] =
+                Layout::horizontal([Constraint::Min(0), Constraint::Length(pixel_inspector::PANEL_WIDTH)])
+                    .areas(image_area);
+            inspector.render_panel(shot, panel, frame.buffer_mut());
+            image_area = image;
+        } else if let Some(matches) = self.current_matches() {
+            let [image, panel] =
+                Layout::horizontal([Constraint::Min(0), Constraint::Length(MATCH_PANEL_WIDTH)]).areas(image_area);
+            render_match_panel(matches, self.threshold, panel, frame.buffer_mut());
+            image_area = image;
+        }
+        let matches = self.current_matches().filter(|_| inspecting.is_none());
+
+        let block = Block::bordered().title(" Screen ");
+        let inner = block.inner(image_area);
+        frame.render_widget(block, image_area);
+        match (&self.latest, &self.last_error) {
+            (Some(shot), _) => {
+                self.placement = Some(
+                    if inspecting.is_some()
+                        || self.mouse_mode == MouseMode::Select
+                        || matches.is_some()
+                        || self.touches.is_some()
+                    {
+                        // Overlays are drawn on the cell grid, which graphics protocols would cover.
+                        let placement = render_half_blocks(shot, inner, frame.buffer_mut());
+                        for result in matches.unwrap_or_default() {
+                            render_match(&placement, result, self.threshold, frame.buffer_mut());
+                        }
+                        if let Some(region) = self.selected_region() {
+                            render_region_outline(
+                                &placement,
+                                region,
+                                Style::default().fg(Color::Yellow),
+                                frame.buffer_mut(),
+                            );
+                        }
+                        if let Some(touches) = &self.touches {
+                            touches.render(&placement, shot, frame.buffer_mut());
+                        }
+                        if let Some((inspector, _)) = inspecting {
+                            inspector.render_crosshair(&placement, image_area, shot, frame.buffer_mut());
+                        }
+                        placement
+                    } else {
+                        render_screenshot(frame, shot, inner)
+                    },
+                );
+            }
+            (None, Some(err)) => {
+                frame.render_widget(Paragraph::new(Line::from(format!("Capture failed: {err}")).red()), inner);
+            }
+            (None, None) => {
+                let message = if self.capturing {
+                    "Capturing…"
+                } else {
+                    "No screenshot yet, press r to capture"
+                };
+                frame.render_widget(Paragraph::new(Line::from(message).italic()), inner);
+            }
+        }
+    }
+}
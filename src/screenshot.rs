@@ -0,0 +1,122 @@
+use std::{
+    io,
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::SystemTime,
+};
+
+use color_eyre::eyre::{Result, WrapErr, bail};
+use png::{ColorType, Decoder, Transformations};
+
+use crate::adb::{AdbOptions, shell_output};
+
+/// A decoded device frame.
+#[derive(Debug, Clone)]
+pub struct Screenshot {
+    /// Unique per decoded frame, so terminal graphics can tell a new frame from a redraw.
+    pub id: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Tightly packed RGB8 pixels, row-major.
+    pub rgb: Arc<[u8]>,
+    /// The PNG exactly as `screencap -p` produced it, kept for saving and for terminal graphics protocols.
+    pub png: Arc<[u8]>,
+    pub captured_at: SystemTime,
+}
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+/// Decode any PNG into `(width, height, packed RGB8 pixels)`. Alpha is dropped.
+pub fn decode_rgb(png: &[u8]) -> Result<(u32, u32, Vec<u8>)> {
+    let mut decoder = Decoder::new(png);
+    decoder.set_transformations(Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    buf.truncate(info.buffer_size());
+    let rgb = match info.color_type {
+        ColorType::Rgb => buf,
+        ColorType::Rgba => buf.chunks_exact(4).flat_map(|p| [p[0], p[1], p[2]]).collect(),
+        ColorType::Grayscale => buf.iter().flat_map(|&g| [g, g, g]).collect(),
+        ColorType::GrayscaleAlpha => buf.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0]]).collect(),
+        ColorType::Indexed => bail!("indexed PNG was not expanded to RGB"),
+    };
+    Ok((info.width, info.height, rgb))
+}
+
+/// Encode packed RGB8 pixels as a PNG.
+pub fn encode_png(width: u32, height: u32, rgb: &[u8]) -> io::Result<Vec<u8>> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header().and_then(|mut writer| writer.write_image_data(rgb)).map_err(io::Error::other)?;
+    Ok(png)
+}
+
+impl Screenshot {
+    /// Wrap pixels that did not come from a PNG, e.g. a crop, encoding one for terminal graphics.
+    pub fn from_rgb(width: u32, height: u32, rgb: Vec<u8>, captured_at: SystemTime) -> io::Result<Self> {
+        let png = encode_png(width, height, &rgb)?;
+        Ok(Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            width,
+            height,
+            rgb: rgb.into(),
+            png: png.into(),
+            captured_at,
+        })
+    }
+
+    pub fn decode(png: Vec<u8>, captured_at: SystemTime) -> Result<Self> {
+        let (width, height, rgb) = decode_rgb(&png)?;
+        Ok(Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            width,
+            height,
+            rgb: rgb.into(),
+            png: png.into(),
+            captured_at,
+        })
+    }
+
+    /// The RGB value at device coordinates `(x, y)`, or `None` outside the frame.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 3]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let offset = (y as usize * self.width as usize + x as usize) * 3;
+        self.rgb.get(offset..offset + 3).map(|p| [p[0], p[1], p[2]])
+    }
+}
+
+/// Capture the current device frame with `screencap -p`. Blocks until the PNG has been read, so
+/// call it from a worker thread.
+pub fn capture(adb: &AdbOptions) -> Result<Screenshot> {
+    let mut device = adb.device();
+    let mut png = shell_output(&mut device, &["screencap", "-p"]).wrap_err("screencap failed")?;
+    let captured_at = SystemTime::now();
+    if png.is_empty() {
+        bail!("screencap returned no data");
+    }
+    // Devices that still run `shell:` on a pty rewrite every LF into CRLF, which corrupts binary
+    // output. The PNG signature itself contains "\r\n", so the mangling is easy to spot.
+    if png.starts_with(b"\x89PNG\r\r\n") {
+        png = crlf_to_lf(&png);
+    }
+    Screenshot::decode(png, captured_at).wrap_err("screencap output is not a valid PNG")
+}
+
+fn crlf_to_lf(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut iter = data.iter().copied().peekable();
+    while let Some(byte) = iter.next() {
+        if byte == b'\r' && iter.peek() == Some(&b'\n') {
+            continue;
+        }
+        out.push(byte);
+    }
+    out
+}
@@ -0,0 +1,186 @@
+//! Recording of a live debugging session.
+//!
+//! Every TUI session gets its own directory under `<data dir>/sessions/<unix secs>` holding
+//! `logcat.log`, with one `<unix millis>\t<line>` entry per received logcat line, and a
+//! `screenshots` directory with one `<unix millis>.png` per periodic capture. Both use the
+//! host clock at the time of receipt so log lines and frames can be lined up. `device.json` holds
+//! the [`DeviceInfo`] of the device the session ran against, once it is known, `perf.jsonl`
+//! one [`PerfSample`] per line, and `events.log` notable events such as thermal warnings in the
+//! same format as `logcat.log`. Screen recordings go to `recordings`, see
+//! [`crate::screen_record`], and watchdog snapshots to `watchdog`, see [`crate::watchdog`].
+
+use std::{
+    fs,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::atomic::AtomicBool,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    adb::{AdbOptions, LineSink},
+    app_event::AppEvent,
+    app_event_sender::AppEventSender,
+    device_info::DeviceInfo,
+    perf::PerfSample,
+    screenshot::{self, Screenshot},
+};
+
+/// A logcat line as received from the device.
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub received_at: SystemTime,
+    pub text: String,
+}
+
+/// A periodic screenshot that has been written to the session directory.
+#[derive(Debug, Clone)]
+pub struct TimelineFrame {
+    pub captured_at: SystemTime,
+    pub path: PathBuf,
+}
+
+/// `at` in milliseconds since the Unix epoch, as timestamps are written to files.
+pub fn unix_millis(at: SystemTime) -> u64 {
+    at.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+pub struct Session {
+    dir: PathBuf,
+    /// Frames ordered by capture time.
+    frames: Vec<TimelineFrame>,
+    /// The last frame decoded for display, by index into `frames`. A failed decode is cached too
+    /// so a broken file is not re-read on every draw.
+    shown: Option<(usize, Option<Screenshot>)>,
+}
+
+impl Session {
+    /// Create the directory for a session starting now.
+    pub fn create(data_dir: &Path) -> io::Result<Self> {
+        let started = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let dir = data_dir.join("sessions").join(started.to_string());
+        fs::create_dir_all(dir.join("screenshots"))?;
+        Ok(Self {
+            dir,
+            frames: Vec::new(),
+            shown: None,
+        })
+    }
+
+    pub fn push_frame(&mut self, frame: TimelineFrame) {
+        let index = self.frames.partition_point(|f| f.captured_at <= frame.captured_at);
+        if let Some((shown, _)) = &mut self.shown
+            && *shown >= index
+        {
+            *shown += 1;
+        }
+        self.frames.insert(index, frame);
+    }
+
+    /// The newest frame captured at or before `at`, decoded from disk.
+    pub fn screenshot_before(&mut self, at: SystemTime) -> Option<&Screenshot> {
+        let index = self.frames.partition_point(|f| f.captured_at <= at).checked_sub(1)?;
+        if self.shown.as_ref().is_none_or(|(shown, _)| *shown != index) {
+            let frame = &self.frames[index];
+            let decoded = fs::read(&frame.path)
+                .map_err(color_eyre::Report::from)
+                .and_then(|png| Screenshot::decode(png, frame.captured_at))
+                .inspect_err(|err| tracing::warn!("failed to load {}: {err:#}", frame.path.display()))
+                .ok();
+            self.shown = Some((index, decoded));
+        }
+        self.shown.as_ref().and_then(|(_, shot)| shot.as_ref())
+    }
+
+    pub fn recordings_dir(&self) -> PathBuf {
+        self.dir.join("recordings")
+    }
+
+    pub fn watchdog_dir(&self) -> PathBuf {
+        self.dir.join("watchdog")
+    }
+
+    /// Write the session header describing the device.
+    pub fn record_device(&self, info: &DeviceInfo) -> io::Result<()> {
+        let json = serde_json::to_vec_pretty(info).map_err(io::Error::other)?;
+        fs::write(self.dir.join("device.json"), json)
+    }
+
+    /// Append a performance sample to `perf.jsonl`.
+    pub fn record_perf(&self, sample: &PerfSample) -> io::Result<()> {
+        let mut line = serde_json::to_vec(sample).map_err(io::Error::other)?;
+        line.push(b'\n');
+        fs::OpenOptions::new().create(true).append(true).open(self.dir.join("perf.jsonl"))?.write_all(&line)
+    }
+
+    /// Append a line to `events.log`, stamped with the current time.
+    pub fn record_event(&self, text: &str) -> io::Result<()> {
+        let line = format!("{}\t{text}\n", unix_millis(SystemTime::now()));
+        fs::OpenOptions::new().create(true).append(true).open(self.dir.join("events.log"))?.write_all(line.as_bytes())
+    }
+
+    /// Stream logcat into `logcat.log` and the app as [`AppEvent::LogLine`]s until the device
+    /// goes away or the app shuts down.
+    pub fn spawn_logcat(&self, adb: AdbOptions, app_event_tx: AppEventSender) {
+        let path = self.dir.join("logcat.log");
+        thread::spawn(move || {
+            let mut file = fs::File::create(&path)
+                .inspect_err(|err| tracing::warn!("not recording logcat to {}: {err}", path.display()))
+                .ok()
+                .map(BufWriter::new);
+            // Only the app shutting down ends the stream early.
+            let stop = AtomicBool::new(false);
+            let sink = LineSink::new(&stop, |lines| {
+                let received_at = SystemTime::now();
+                for text in lines {
+                    if let Some(writer) = &mut file
+                        && let Err(err) = writeln!(writer, "{}\t{text}", unix_millis(received_at))
+                    {
+                        tracing::warn!("stopped recording logcat: {err}");
+                        file = None;
+                    }
+                    app_event_tx.send(AppEvent::LogLine(LogLine {
+                        received_at,
+                        text,
+                    }));
+                }
+                // Flush per batch so the recording survives a crash of the app.
+                if let Some(writer) = &mut file
+                    && let Err(err) = writer.flush()
+                {
+                    tracing::warn!("stopped recording logcat: {err}");
+                    file = None;
+                }
+                !app_event_tx.app_event_tx.is_closed()
+            });
+            if let Err(err) = adb.device().get_logs(sink) {
+                tracing::warn!("logcat stream ended: {err}");
+            }
+        });
+    }
+
+    /// Capture a screenshot every `interval` into `screenshots/`, announcing each one as
+    /// [`AppEvent::TimelineFrame`]. Stops when the app shuts down.
+    pub fn spawn_capture(&self, adb: AdbOptions, app_event_tx: AppEventSender, interval: Duration) {
+        let dir = self.dir.join("screenshots");
+        thread::spawn(move || {
+            while !app_event_tx.app_event_tx.is_closed() {
+                match screenshot::capture(&adb) {
+                    Ok(shot) => {
+                        let path = dir.join(format!("{}.png", unix_millis(shot.captured_at)));
+                        match fs::write(&path, &shot.png) {
+                            Ok(()) => app_event_tx.send(AppEvent::TimelineFrame(TimelineFrame {
+                                captured_at: shot.captured_at,
+                                path,
+                            })),
+                            Err(err) => tracing::warn!("failed to save {}: {err}", path.display()),
+                        }
+                    }
+                    Err(err) => tracing::debug!("timeline capture failed: {err:#}"),
+                }
+                thread::sleep(interval);
+            }
+        });
+    }
+}
@@ -0,0 +1,473 @@
+//! Interactive `shell:` console for the SHELL tab.
+//!
+//! Commands run one at a time on a worker thread and stream their output back line by line.
+//! Every command line is prefixed with `echo $$;` so the worker learns the pid of the remote
+//! shell, which adbd makes a process group leader. Ctrl-C sends SIGINT to that group over a
+//! second `shell:`; a second Ctrl-C, or one before the pid is known, detaches instead: the
+//! worker stops forwarding output and ends at the next write. Quick commands are kept in
+//! `<data dir>/shell_commands.json` as a JSON array of command lines.
+
+use std::{
+    fs, io, mem,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU32, Ordering},
+    },
+    thread,
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::Stylize,
+    text::{Line, Span},
+    widgets::{Block, Paragraph},
+};
+
+use crate::{
+    adb::{AdbOptions, LineSink},
+    ansi::ansi_line,
+    app_event::AppEvent,
+    app_event_sender::AppEventSender,
+    custom_terminal::Frame,
+    pager_overlay::StaticOverlay,
+    tui,
+};
+
+/// Transcript lines kept; older ones are dropped.
+const MAX_LINES: usize = 10_000;
+/// Commands remembered for Up/Down.
+const MAX_HISTORY: usize = 500;
+const QUICK_PANEL_WIDTH: u16 = 36;
+const DEFAULT_QUICK_COMMANDS: &[&str] = &[
+    "dumpsys activity top",
+    "dumpsys window | grep mCurrentFocus",
+    "dumpsys meminfo",
+    "getprop ro.build.fingerprint",
+    "wm size; wm density",
+    "pm list packages -3",
+];
+
+/// The command currently streaming output.
+struct Running {
+    id: u64,
+    stop: Arc<AtomicBool>,
+    /// Pid of the remote shell once its first line came back, 0 before.
+    pid: Arc<AtomicU32>,
+    /// Whether SIGINT was sent already.
+    interrupted: bool,
+    command: String,
+    /// Index into `transcript` of the first output line.
+    output_start: usize,
+}
+
+pub struct ShellView {
+    adb: AdbOptions,
+    app_event_tx: AppEventSender,
+    quick_path: PathBuf,
+    quick_commands: Vec<String>,
+    /// Echoed commands, their output and exit statuses.
+    transcript: Vec<Line<'static>>,
+    /// Lines scrolled up from the bottom of the transcript.
+    scroll: usize,
+    input: String,
+    /// Byte offset of the cursor in `input`, always on a char boundary.
+    cursor: usize,
+    history: Vec<String>,
+    /// Position while browsing history, with the line that was being edited before.
+    browsing: Option<(usize, String)>,
+    running: Option<Running>,
+    next_id: u64,
+    /// Command line and output of the last finished command, for the pager.
+    last_output: Option<(String, Vec<Line<'static>>)>,
+    pager: Option<StaticOverlay>,
+    /// Height of the transcript pane at the last draw; longer output opens in the pager.
+    output_height: usize,
+    notice: Option<String>,
+}
+
+impl ShellView {
+    pub fn new(adb: AdbOptions, app_event_tx: AppEventSender, quick_path: PathBuf) -> Self {
+        let quick_commands = fs::read(&quick_path)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .unwrap_or_else(|| DEFAULT_QUICK_COMMANDS.iter().map(ToString::to_string).collect());
+        Self {
+            adb,
+            app_event_tx,
+            quick_path,
+            quick_commands,
+            transcript: Vec::new(),
+            scroll: 0,
+            input: String::new(),
+            cursor: 0,
+            history: Vec::new(),
+            browsing: None,
+            running: None,
+            next_id: 0,
+            last_output: None,
+            pager: None,
+            output_height: usize::MAX,
+            notice: None,
+        }
+    }
+
+    fn push_line(&mut self, line: Line<'static>) {
+        self.transcript.push(line);
+        if self.transcript.len() > MAX_LINES {
+            let overflow = self.transcript.len() - MAX_LINES;
+            self.transcript.drain(..overflow);
+            if let Some(running) = &mut self.running {
+                running.output_start = running.output_start.saturating_sub(overflow);
+            }
+        }
+    }
+
+    fn run(&mut self, command: String) {
+        if self.running.is_some() {
+            self.notice = Some("a command is still running, Ctrl-C to interrupt it".to_string());
+            return;
+        }
+        if self.history.last() != Some(&command) {
+            self.history.push(command.clone());
+            if self.history.len() > MAX_HISTORY {
+                self.history.remove(0);
+            }
+        }
+        self.push_line(Line::from(vec![Span::from("$ ").cyan(), Span::from(command.clone()).bold()]));
+        self.next_id += 1;
+        let stop = Arc::new(AtomicBool::new(false));
+        let pid = Arc::new(AtomicU32::new(0));
+        self.running = Some(Running {
+            id: self.next_id,
+            stop: stop.clone(),
+            pid: pid.clone(),
+            interrupted: false,
+            command: command.clone(),
+            output_start: self.transcript.len(),
+        });
+        self.scroll = 0;
+        self.notice = None;
+        spawn_command(self.adb.clone(), self.app_event_tx.clone(), self.next_id, command, stop, pid);
+    }
+
+    pub fn on_output(&mut self, id: u64, lines: Vec<String>) {
+        if self.running.as_ref().is_none_or(|running| running.id != id) {
+            return;
+        }
+        for line in lines {
+            self.push_line(ansi_line(&line));
+        }
+    }
+
+    pub fn on_finished(&mut self, id: u64, result: Result<Option<u8>, String>) {
+        let Some(running) = self.running.take_if(|running| running.id == id) else {
+            return;
+        };
+        let output = self.transcript.get(running.output_start..).unwrap_or_default().to_vec();
+        match result {
+            Ok(Some(0) | None) => {}
+            Ok(Some(code)) => self.push_line(Line::from(format!("[exit {code}]")).yellow()),
+            Err(err) => self.push_line(Line::from(format!("[failed: {err}]")).red()),
+        }
+        if output.len() > self.output_height {
+            self.open_pager(&running.command, output.clone());
+        }
+        self.last_output = Some((running.command, output));
+    }
+
+    fn interrupt(&mut self) {
+        let Some(running) = &mut self.running else {
+            return;
+        };
+        let pid = running.pid.load(Ordering::Relaxed);
+        if pid == 0 || running.interrupted {
+            // Not started yet, or ignoring SIGINT: stop waiting for it.
+            running.stop.store(true, Ordering::Relaxed);
+            self.running = None;
+            self.push_line(Line::from("^C (detached)").dim());
+            return;
+        }
+        running.interrupted = true;
+        spawn_kill(self.adb.clone(), pid);
+        self.push_line(Line::from("^C").dim());
+    }
+
+    fn open_pager(&mut self, command: &str, lines: Vec<Line<'static>>) {
+        self.pager = Some(StaticOverlay::with_title(lines, format!("$ {command}")));
+    }
+
+    /// Append the input line to the quick commands and persist them.
+    fn save_quick_command(&mut self) {
+        let command = self.input.trim().to_string();
+        if command.is_empty() || self.quick_commands.contains(&command) {
+            return;
+        }
+        self.quick_commands.push(command);
+        let result = serde_json::to_vec_pretty(&self.quick_commands)
+            .map_err(io::Error::other)
+            .and_then(|json| fs::write(&self.quick_path, json));
+        self.notice = Some(match result {
+            Ok(()) => format!("saved quick command F{}", self.quick_commands.len()),
+            Err(err) => format!("failed to save {}: {err}", self.quick_path.display()),
+        });
+    }
+
+    fn set_input(&mut self, input: String) {
+        self.cursor = input.len();
+        self.input = input;
+    }
+
+    fn browse_history(&mut self, older: bool) {
+        let index = match (&self.browsing, older) {
+            (None, true) if !self.history.is_empty() => self.history.len() - 1,
+            (Some((index, _)), true) => index.saturating_sub(1),
+            (Some((index, _)), false) if index + 1 < self.history.len() => index + 1,
+            (Some(_), false) => {
+                if let Some((_, draft)) = self.browsing.take() {
+                    self.set_input(draft);
+                }
+                return;
+            }
+            _ => return,
+        };
+        let draft = self.browsing.take().map_or_else(|| self.input.clone(), |(_, draft)| draft);
+        self.browsing = Some((index, draft));
+        self.set_input(self.history[index].clone());
+    }
+
+    fn previous_boundary(&self) -> usize {
+        self.input[..self.cursor].char_indices().next_back().map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.input[self.cursor..].chars().next().map_or(self.cursor, |c| self.cursor + c.len_utf8())
+    }
+
+    /// Start of the word before the cursor, skipping whitespace first.
+    fn word_start(&self) -> usize {
+        let before = self.input[..self.cursor].trim_end();
+        before.rfind(char::is_whitespace).map_or(0, |i| i + 1)
+    }
+
+    /// Cursor movement and edits. Returns `false` for keys that do not edit the line.
+    fn edit(&mut self, key_event: KeyEvent) -> bool {
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        match key_event.code {
+            KeyCode::Left => self.cursor = self.previous_boundary(),
+            KeyCode::Right => self.cursor = self.next_boundary(),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::End => self.cursor = self.input.len(),
+            KeyCode::Char('e') if ctrl => self.cursor = self.input.len(),
+            KeyCode::Backspace => {
+                let start = self.previous_boundary();
+                self.input.replace_range(start..self.cursor, "");
+                self.cursor = start;
+            }
+            KeyCode::Delete => {
+                let end = self.next_boundary();
+                self.input.replace_range(self.cursor..end, "");
+            }
+            KeyCode::Char('u') if ctrl => {
+                self.input.replace_range(..self.cursor, "");
+                self.cursor = 0;
+            }
+            KeyCode::Char('k') if ctrl => self.input.truncate(self.cursor),
+            KeyCode::Char('w') if ctrl => {
+                let start = self.word_start();
+                self.input.replace_range(start..self.cursor, "");
+                self.cursor = start;
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.input.insert(self.cursor, c);
+                self.cursor += c.len_utf8();
+            }
+            _ => return false,
+        }
+        self.browsing = None;
+        true
+    }
+
+    /// Insert pasted text at the cursor, joining its lines into one command line.
+    pub fn handle_paste(&mut self, tui: &tui::Tui, text: &str) {
+        if self.pager.is_some() {
+            return;
+        }
+        let text = text.lines().map(str::trim).filter(|line| !line.is_empty()).collect::<Vec<_>>().join("; ");
+        self.input.insert_str(self.cursor, &text);
+        self.cursor += text.len();
+        self.browsing = None;
+        tui.frame_requester().schedule_frame();
+    }
+
+    pub fn handle_key_event(&mut self, tui: &tui::Tui, key_event: KeyEvent) {
+        if let Some(pager) = &mut self.pager {
+            pager.handle_key_event(tui, key_event);
+            if pager.is_done() {
+                self.pager = None;
+            }
+            tui.frame_requester().schedule_frame();
+            return;
+        }
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        match key_event.code {
+            KeyCode::Enter => {
+                let command = self.input.trim().to_string();
+                self.set_input(String::new());
+                self.browsing = None;
+                if !command.is_empty() {
+                    self.run(command);
+                }
+            }
+            KeyCode::Char('c') if ctrl => {
+                if self.running.is_some() {
+                    self.interrupt();
+                } else {
+                    self.set_input(String::new());
+                    self.browsing = None;
+                }
+            }
+            KeyCode::Char('l') if ctrl => {
+                self.transcript.clear();
+                self.scroll = 0;
+                if let Some(running) = &mut self.running {
+                    running.output_start = 0;
+                }
+            }
+            KeyCode::Char('o') if ctrl => match &self.last_output {
+                Some((command, lines)) => {
+                    let (command, lines) = (command.clone(), lines.clone());
+                    self.open_pager(&command, lines);
+                }
+                None => self.notice = Some("no finished command yet".to_string()),
+            },
+            KeyCode::Char('s') if ctrl => self.save_quick_command(),
+            KeyCode::F(n) => match self.quick_commands.get(usize::from(n).wrapping_sub(1)) {
+                Some(command) => self.run(command.clone()),
+                None => self.notice = Some(format!("no quick command F{n}")),
+            },
+            KeyCode::Up => self.browse_history(true),
+            KeyCode::Down => self.browse_history(false),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_add(self.output_height.max(1)),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_sub(self.output_height.max(1)),
+            _ => {
+                if !self.edit(key_event) {
+                    return;
+                }
+            }
+        }
+        tui.frame_requester().schedule_frame();
+    }
+
+    fn status_line(&self) -> Line<'static> {
+        let mut line = match &self.running {
+            Some(running) if running.interrupted => Line::from(" interrupted · ctrl-c: detach").yellow(),
+            Some(_) => Line::from(" running · ctrl-c: interrupt").yellow(),
+            None => Line::from(" enter: run  ↑/↓: history  F1-F9: quick command  ctrl-s: save as quick  ctrl-o: pager")
+                .dim(),
+        };
+        if let Some(notice) = &self.notice {
+            line.push_span(Span::from(format!(" · {notice}")).green());
+        }
+        line
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect) {
+        if let Some(pager) = &mut self.pager {
+            pager.render(area, frame.buffer_mut());
+            return;
+        }
+        let [status_area, body, input_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0), Constraint::Length(3)]).areas(area);
+        frame.render_widget(self.status_line(), status_area);
+        let [output_area, quick_area] =
+            Layout::horizontal([Constraint::Min(0), Constraint::Length(QUICK_PANEL_WIDTH)]).areas(body);
+
+        let block = Block::bordered().title(" Shell ");
+        let inner = block.inner(output_area);
+        frame.render_widget(block, output_area);
+        let height = usize::from(inner.height);
+        self.output_height = height;
+        self.scroll = self.scroll.min(self.transcript.len().saturating_sub(height));
+        let end = self.transcript.len() - self.scroll;
+        let visible = self.transcript[end.saturating_sub(height)..end].to_vec();
+        frame.render_widget(Paragraph::new(visible), inner);
+
+        let quick: Vec<Line> = self
+            .quick_commands
+            .iter()
+            .enumerate()
+            .map(|(i, command)| {
+                let key = if i < 12 {
+                    format!("F{:<3}", i + 1)
+                } else {
+                    "    ".to_string()
+                };
+                Line::from(vec![Span::from(key).cyan(), Span::from(command.clone())])
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(quick).block(Block::bordered().title(" Quick commands ")), quick_area);
+
+        let block = Block::bordered().title(" $ ");
+        let inner = block.inner(input_area);
+        frame.render_widget(block, input_area);
+        // Keep the cursor in view by scrolling long lines horizontally.
+        let before = self.input[..self.cursor].chars().count();
+        let offset = before.saturating_sub(usize::from(inner.width.saturating_sub(1)));
+        let shown: String = self.input.chars().skip(offset).collect();
+        frame.render_widget(Paragraph::new(shown), inner);
+        let column = u16::try_from(before - offset).unwrap_or(u16::MAX);
+        frame.set_cursor_position((inner.x.saturating_add(column), inner.y));
+    }
+}
+
+/// Run `command` through `shell:` on a worker thread, streaming its output as
+/// [`AppEvent::ShellOutput`] and ending with [`AppEvent::ShellFinished`]. The pid of the remote
+/// shell goes to `pid`.
+fn spawn_command(
+    adb: AdbOptions,
+    app_event_tx: AppEventSender,
+    id: u64,
+    command: String,
+    stop: Arc<AtomicBool>,
+    pid: Arc<AtomicU32>,
+) {
+    thread::spawn(move || {
+        let mut pid_line = true;
+        let mut sink = LineSink::new(&stop, |mut lines| {
+            if mem::take(&mut pid_line)
+                && !lines.is_empty()
+                && let Ok(shell_pid) = lines.remove(0).trim().parse()
+            {
+                pid.store(shell_pid, Ordering::Relaxed);
+            }
+            if !lines.is_empty() {
+                app_event_tx.send(AppEvent::ShellOutput {
+                    id,
+                    lines,
+                });
+            }
+            true
+        });
+        let command = format!("echo $$; {command}");
+        let result = adb.device().shell_command(&[&command], &mut sink).map_err(|err| err.to_string());
+        sink.finish();
+        app_event_tx.send(AppEvent::ShellFinished {
+            id,
+            result,
+        });
+    });
+}
+
+/// Send SIGINT to the process group of the remote shell `pid`, or to the shell alone where it
+/// leads no group of its own.
+fn spawn_kill(adb: AdbOptions, pid: u32) {
+    thread::spawn(move || {
+        let command = format!("kill -INT -{pid} 2>/dev/null || kill -INT {pid}");
+        if let Err(err) = adb.device().shell_command(&[&command], &mut io::sink()) {
+            tracing::warn!("failed to interrupt shell {pid}: {err}");
+        }
+    });
+}
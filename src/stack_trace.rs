@@ -0,0 +1,242 @@
+//! Folding of stack traces on the LOGS tab.
+//!
+//! A trace is a run of at least two consecutive stack frame lines: Java and Mono `at …` frames
+//! with Java's `... N more`, native `#00 pc …` frames from tombstones, and Unity's
+//! `Class:Method (…)` frames logged under the `Unity` tag. The line right before the run, the
+//! exception or `backtrace:` line, is the trace's header. Folded traces show the header and the
+//! first frame only. Frames are told apart as app code or framework code by where they point.
+
+use std::{collections::BTreeSet, ops::Range};
+
+use crate::{farm::split_logcat, session::LogLine};
+
+/// Packages and namespaces of the platform, runtimes and engine.
+const FRAMEWORK_PREFIXES: &[&str] = &[
+    "java.",
+    "javax.",
+    "jdk.",
+    "sun.",
+    "libcore.",
+    "dalvik.",
+    "android.",
+    "androidx.",
+    "com.android.",
+    "com.google.android.",
+    "kotlin.",
+    "kotlinx.",
+    "org.apache.",
+    "System.",
+    "Mono.",
+    "UnityEngine.",
+    "Unity.",
+    "com.unity3d.",
+];
+
+/// A folded or unfolded stack trace in the log buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    /// Index of the header line.
+    pub header: usize,
+    /// Indices of the frame lines.
+    pub frames: Range<usize>,
+}
+
+/// Whether the message of a logcat line is a stack frame.
+fn is_frame(tag: &str, message: &str) -> bool {
+    if message.starts_with("at ") || message.contains("(at Assets/") {
+        return true;
+    }
+    if message.starts_with("... ") && message.ends_with(" more") {
+        return true;
+    }
+    if let Some(rest) = message.strip_prefix('#') {
+        let digits = rest.chars().take_while(char::is_ascii_digit).count();
+        return digits > 0 && rest[digits..].trim_start().starts_with("pc ");
+    }
+    // Unity writes `Namespace.Class:Method (Arguments)` without a marker.
+    let is_name = |name: &str| !name.is_empty() && !name.contains(char::is_whitespace);
+    tag == "Unity"
+        && message.split_once(':').is_some_and(|(class, rest)| {
+            is_name(class) && rest.split_once('(').is_some_and(|(method, _)| is_name(method.trim()))
+        })
+}
+
+/// Whether a stack frame line points into the app rather than the platform, runtime or engine.
+pub fn is_app_frame(text: &str) -> bool {
+    let message = split_logcat(text).2;
+    if message.starts_with("... ") {
+        return false;
+    }
+    if message.starts_with('#') {
+        return message.contains(" /data/app/") || message.contains(" /data/data/");
+    }
+    if message.contains("(at Assets/") {
+        return true;
+    }
+    let symbol = message.strip_prefix("at ").unwrap_or(message).trim_start();
+    !FRAMEWORK_PREFIXES.iter().any(|prefix| symbol.starts_with(prefix))
+}
+
+/// The stack traces in `lines`, in order.
+pub fn find_traces(lines: &[LogLine]) -> Vec<Trace> {
+    let mut traces = Vec::new();
+    extend_traces(&mut traces, lines, 0);
+    traces
+}
+
+/// Bring `traces`, the stack traces of `lines[..scanned]`, up to date with the lines appended
+/// since. Only the run of frames the scanned lines end with can grow.
+pub fn extend_traces(traces: &mut Vec<Trace>, lines: &[LogLine], scanned: usize) {
+    let is_frame_line = |line: &LogLine| {
+        let (_, tag, message) = split_logcat(&line.text);
+        is_frame(tag, message)
+    };
+    let mut start = None;
+    if let Some(last) = traces.pop_if(|trace| trace.frames.end == scanned) {
+        start = Some(last.frames.start);
+    } else if scanned > 0 && is_frame_line(&lines[scanned - 1]) {
+        start = Some(scanned - 1);
+    }
+    for (index, line) in lines.iter().enumerate().skip(scanned) {
+        match (is_frame_line(line), start) {
+            (true, None) => start = Some(index),
+            (false, Some(first)) => {
+                traces.extend(trace(first..index));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(first) = start {
+        traces.extend(trace(first..lines.len()));
+    }
+}
+
+/// Update `traces` for the first `removed` lines of the log having been dropped.
+pub fn shift_traces(traces: &mut Vec<Trace>, removed: usize) {
+    *traces = traces
+        .drain(..)
+        .filter_map(|old| trace(old.frames.start.saturating_sub(removed)..old.frames.end.saturating_sub(removed)))
+        .collect();
+}
+
+fn trace(frames: Range<usize>) -> Option<Trace> {
+    // A lone frame has nothing to fold.
+    (frames.len() >= 2).then(|| Trace {
+        header: frames.start.saturating_sub(1),
+        frames,
+    })
+}
+
+/// Indices of the lines shown when every trace but those whose header is in `expanded` is
+/// folded.
+pub fn visible_lines(total: usize, traces: &[Trace], expanded: &BTreeSet<usize>) -> Vec<usize> {
+    let mut visible = Vec::with_capacity(total);
+    let mut next = 0;
+    for trace in traces.iter().filter(|trace| !expanded.contains(&trace.header)) {
+        visible.extend(next..=trace.frames.start);
+        next = trace.frames.end;
+    }
+    visible.extend(next..total);
+    visible
+}
+
+/// The trace `index` is the header or a frame of.
+pub fn trace_at(traces: &[Trace], index: usize) -> Option<&Trace> {
+    let after = traces.partition_point(|trace| trace.header <= index);
+    traces[..after].last().filter(|trace| index < trace.frames.end)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use super::*;
+
+    fn log(lines: &[&str]) -> Vec<LogLine> {
+        lines
+            .iter()
+            .map(|text| LogLine {
+                received_at: SystemTime::UNIX_EPOCH,
+                text: (*text).to_string(),
+            })
+            .collect()
+    }
+
+    /// A crash with three Java frames, an unrelated line, then two native frames.
+    fn crash_log() -> Vec<LogLine> {
+        log(&[
+            "10-18 12:00:00.000  100  100 I Bot: STATE race",
+            "10-18 12:00:00.001  100  100 E AndroidRuntime: java.lang.IllegalStateException: boom",
+            "10-18 12:00:00.001  100  100 E AndroidRuntime: \tat com.example.bot.Race.run(Race.java:12)",
+            "10-18 12:00:00.001  100  100 E AndroidRuntime: \tat android.os.Handler.dispatch(Handler.java:99)",
+            "10-18 12:00:00.001  100  100 E AndroidRuntime: \t... 4 more",
+            "10-18 12:00:00.002  100  100 I Bot: restarting",
+            "10-18 12:00:00.003  200  200 F DEBUG: backtrace:",
+            "10-18 12:00:00.003  200  200 F DEBUG:       #00 pc 0004a3c4  /system/lib64/libc.so (abort+164)",
+            "10-18 12:00:00.003  200  200 F DEBUG:       #01 pc 00012f00  /data/app/com.example/lib/libbot.so",
+        ])
+    }
+
+    #[test]
+    fn finds_java_and_native_traces() {
+        let traces = find_traces(&crash_log());
+        assert_eq!(
+            traces,
+            [
+                Trace {
+                    header: 1,
+                    frames: 2..5,
+                },
+                Trace {
+                    header: 6,
+                    frames: 7..9,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn lone_frame_is_not_a_trace() {
+        let lines = log(&["E Tag: java.lang.Error", "E Tag: at com.example.Main.main(Main.java:1)", "I Tag: done"]);
+        assert!(find_traces(&lines).is_empty());
+    }
+
+    #[test]
+    fn folded_traces_show_header_and_first_frame() {
+        let lines = crash_log();
+        let traces = find_traces(&lines);
+        assert_eq!(visible_lines(lines.len(), &traces, &BTreeSet::new()), [0, 1, 2, 5, 6, 7]);
+        assert_eq!(visible_lines(lines.len(), &traces, &BTreeSet::from([1])), [0, 1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn trace_at_covers_header_and_frames() {
+        let traces = find_traces(&crash_log());
+        assert_eq!(trace_at(&traces, 0), None);
+        assert_eq!(trace_at(&traces, 1).map(|trace| trace.header), Some(1));
+        assert_eq!(trace_at(&traces, 4).map(|trace| trace.header), Some(1));
+        assert_eq!(trace_at(&traces, 5), None);
+        assert_eq!(trace_at(&traces, 8).map(|trace| trace.header), Some(6));
+    }
+
+    #[test]
+    fn extending_line_by_line_matches_a_full_scan() {
+        let lines = crash_log();
+        let mut traces = Vec::new();
+        for scanned in 0..lines.len() {
+            extend_traces(&mut traces, &lines[..=scanned], scanned);
+            assert_eq!(traces, find_traces(&lines[..=scanned]), "after line {scanned}");
+        }
+    }
+
+    #[test]
+    fn shifting_matches_a_full_scan() {
+        let lines = crash_log();
+        for removed in 0..lines.len() {
+            let mut traces = find_traces(&lines);
+            shift_traces(&mut traces, removed);
+            assert_eq!(traces, find_traces(&lines[removed..]), "after dropping {removed} lines");
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Matching templates cropped from device screenshots.
+//!
+//! A template named `NAME` is stored as `NAME.png` with a `NAME.json` sidecar describing where it
+//! was cut from, so it can be matched against frames of the same resolution later.
+
+use std::{
+    collections::BTreeSet,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::WrapErr;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    screenshot::{self, Screenshot},
+    session::unix_millis,
+};
+
+/// A rectangle in device pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    /// The rectangle spanned by two opposite corners, both inclusive.
+    pub fn from_corners((ax, ay): (u32, u32), (bx, by): (u32, u32)) -> Self {
+        Self {
+            x: ax.min(bx),
+            y: ay.min(by),
+            width: ax.abs_diff(bx) + 1,
+            height: ay.abs_diff(by) + 1,
+        }
+    }
+
+    pub const fn right(&self) -> u32 {
+        self.x + self.width
+    }
+
+    pub const fn bottom(&self) -> u32 {
+        self.y + self.height
+    }
+}
+
+/// The JSON sidecar written next to every template PNG.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateMeta {
+    /// Resolution of the screenshot the template was cut from.
+    pub device_width: u32,
+    pub device_height: u32,
+    pub region: Region,
+    /// Unix timestamp (milliseconds) of the source capture.
+    pub captured_at: u64,
+}
+
+/// Packed RGB8 pixels of `region` within `shot`. The region must lie inside the frame.
+pub fn crop(shot: &Screenshot, region: Region) -> Vec<u8> {
+    let stride = shot.width as usize * 3;
+    let mut rgb = Vec::with_capacity(region.width as usize * region.height as usize * 3);
+    for y in region.y..region.bottom() {
+        let start = y as usize * stride + region.x as usize * 3;
+        rgb.extend_from_slice(&shot.rgb[start..start + region.width as usize * 3]);
+    }
+    rgb
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        && !name.starts_with('.')
+}
+
+/// Save `region` of `shot` as template `name` in `dir`, creating the directory if needed.
+/// Refuses to overwrite an existing template. Returns the path of the PNG.
+pub fn save(dir: &Path, name: &str, shot: &Screenshot, region: Region) -> io::Result<PathBuf> {
+    if !valid_name(name) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid template name {name:?}: use letters, digits, '_', '-' and '.'"),
+        ));
+    }
+    if region.width == 0 || region.height == 0 || region.right() > shot.width || region.bottom() > shot.height {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "region is outside the screenshot"));
+    }
+    fs::create_dir_all(dir)?;
+    let png_path = dir.join(format!("{name}.png"));
+    let json_path = dir.join(format!("{name}.json"));
+
+    let png = screenshot::encode_png(region.width, region.height, &crop(shot, region))?;
+    fs::File::options().write(true).create_new(true).open(&png_path)?.write_all(&png)?;
+
+    let meta = TemplateMeta {
+        device_width: shot.width,
+        device_height: shot.height,
+        region,
+        captured_at: unix_millis(shot.captured_at),
+    };
+    let json = serde_json::to_vec_pretty(&meta).map_err(io::Error::other)?;
+    fs::write(&json_path, json)?;
+    Ok(png_path)
+}
+
+/// A template loaded for matching.
+#[derive(Debug, Clone)]
+pub struct Template {
+    pub name: String,
+    /// `None` for PNGs without a sidecar, e.g. templates cut in an image editor.
+    pub meta: Option<TemplateMeta>,
+    pub width: u32,
+    pub height: u32,
+    pub rgb: Vec<u8>,
+}
+
+fn png_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "png"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Template sets under `dir`: `dir` itself and each subdirectory, as long as they hold at least
+/// one PNG. A missing directory has no sets.
+pub fn list_sets(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut candidates: Vec<PathBuf> =
+        entries.filter_map(|entry| entry.ok().map(|e| e.path())).filter(|path| path.is_dir()).collect();
+    candidates.sort();
+    candidates.insert(0, dir.to_path_buf());
+    Ok(candidates.into_iter().filter(|set| png_files(set).is_ok_and(|files| !files.is_empty())).collect())
+}
+
+/// Load every template of the set in `dir`.
+pub fn load_set(dir: &Path) -> color_eyre::Result<Vec<Template>> {
+    png_files(dir)
+        .wrap_err_with(|| format!("failed to read {}", dir.display()))?
+        .into_iter()
+        .map(|path| {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+            let png = fs::read(&path).wrap_err_with(|| format!("failed to read {}", path.display()))?;
+            let (width, height, rgb) =
+                screenshot::decode_rgb(&png).wrap_err_with(|| format!("{} is not a valid PNG", path.display()))?;
+            let meta = fs::read(path.with_extension("json")).ok().and_then(|json| serde_json::from_slice(&json).ok());
+            Ok(Template {
+                name,
+                meta,
+                width,
+                height,
+                rgb,
+            })
+        })
+        .collect()
+}
+
+/// Source resolutions recorded in the sidecars of the set in `dir`. Templates without a sidecar
+/// are skipped.
+pub fn set_resolutions(dir: &Path) -> io::Result<BTreeSet<(u32, u32)>> {
+    Ok(png_files(dir)?
+        .into_iter()
+        .filter_map(|path| fs::read(path.with_extension("json")).ok())
+        .filter_map(|json| serde_json::from_slice::<TemplateMeta>(&json).ok())
+        .map(|meta| (meta.device_width, meta.device_height))
+        .collect())
+}
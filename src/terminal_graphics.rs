@@ -0,0 +1,276 @@
+//! Inline image output through terminal graphics protocols.
+//!
+//! Frames carry [`FrameImage`] placements next to their cell buffer. After the cell diff has been
+//! flushed, [`write_updates`] removes placements that went away and emits new ones using the
+//! protocol picked by [`protocol`]. iTerm2 and Sixel paint into the cells themselves, so cells under
+//! such an image are marked `skip` and the cell diff never paints over it; once the image is gone
+//! those cells compare unequal to the new content and get repainted, which is what erases it. Kitty
+//! keeps images on a separate layer, so they are deleted explicitly by id.
+
+use std::{
+    env,
+    hash::{Hash, Hasher},
+    io::{self, Write},
+    sync::{Arc, OnceLock},
+};
+
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use clap::ValueEnum;
+use ratatui::layout::Rect;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GraphicsProtocol {
+    Kitty,
+    Iterm2,
+    Sixel,
+    /// No graphics protocol: images are drawn with `▀` cells by the widgets themselves.
+    HalfBlocks,
+}
+
+static PROTOCOL: OnceLock<GraphicsProtocol> = OnceLock::new();
+
+/// Force a protocol instead of detecting one. Must be called before the first [`protocol`] call.
+pub fn set_protocol(protocol: GraphicsProtocol) {
+    let _ = PROTOCOL.set(protocol);
+}
+
+/// The protocol used for this session, detected from the environment on first use.
+pub fn protocol() -> GraphicsProtocol {
+    *PROTOCOL.get_or_init(detect)
+}
+
+fn detect() -> GraphicsProtocol {
+    let var = |name: &str| env::var(name).unwrap_or_default();
+    let term = var("TERM");
+    let term_program = var("TERM_PROGRAM");
+
+    // Multiplexers need per-protocol passthrough wrapping; stay on the safe side.
+    if env::var_os("TMUX").is_some() || term.starts_with("screen") {
+        return GraphicsProtocol::HalfBlocks;
+    }
+    if env::var_os("KITTY_WINDOW_ID").is_some() || term == "xterm-kitty" || term_program == "ghostty" {
+        return GraphicsProtocol::Kitty;
+    }
+    if term_program == "iTerm.app" || term_program == "WezTerm" || var("LC_TERMINAL") == "iTerm2" {
+        return GraphicsProtocol::Iterm2;
+    }
+    if term.contains("sixel") || term.starts_with("foot") || matches!(term_program.as_str(), "mlterm" | "contour") {
+        return GraphicsProtocol::Sixel;
+    }
+    GraphicsProtocol::HalfBlocks
+}
+
+/// An image placed on a frame. Images are identified by `id`: a placement with the same id and
+/// area as on the previous frame is left alone, anything else is re-emitted.
+#[derive(Debug, Clone)]
+pub struct FrameImage {
+    pub id: u32,
+    /// Cells covered by the image.
+    pub area: Rect,
+    pub width: u32,
+    pub height: u32,
+    /// The encoded PNG, sent as-is to Kitty and iTerm2.
+    pub png: Arc<[u8]>,
+    /// Packed RGB8 pixels, used to build Sixel data.
+    pub rgb: Arc<[u8]>,
+}
+
+impl PartialEq for FrameImage {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id && self.area == other.area
+    }
+}
+
+impl Eq for FrameImage {}
+
+impl Hash for FrameImage {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+        self.area.hash(state);
+    }
+}
+
+/// What changed in the image layer since the last flush.
+#[derive(Debug, Default)]
+pub struct ImageUpdates {
+    /// Every image the terminal may still be showing must go, e.g. after a clear or resize.
+    pub purge: bool,
+    pub removed: Vec<FrameImage>,
+    pub added: Vec<FrameImage>,
+}
+
+impl ImageUpdates {
+    pub fn is_empty(&self) -> bool {
+        !self.purge && self.removed.is_empty() && self.added.is_empty()
+    }
+}
+
+/// Emit `updates` using the session protocol. The cursor position is saved and restored around
+/// the output so the caller's cursor bookkeeping stays valid.
+pub fn write_updates<W: Write>(writer: &mut W, updates: &ImageUpdates) -> io::Result<()> {
+    let protocol = protocol();
+    if updates.is_empty() || protocol == GraphicsProtocol::HalfBlocks {
+        return Ok(());
+    }
+    writer.write_all(b"\x1b7")?;
+    if protocol == GraphicsProtocol::Kitty {
+        if updates.purge {
+            writer.write_all(KITTY_DELETE_ALL)?;
+        }
+        for image in &updates.removed {
+            write!(writer, "\x1b_Ga=d,d=I,i={},q=2\x1b\\", image.id)?;
+        }
+    }
+    for image in &updates.added {
+        write!(writer, "\x1b[{};{}H", image.area.y + 1, image.area.x + 1)?;
+        match protocol {
+            GraphicsProtocol::Kitty => write_kitty(writer, image)?,
+            GraphicsProtocol::Iterm2 => write_iterm2(writer, image)?,
+            GraphicsProtocol::Sixel => write_sixel(writer, image)?,
+            GraphicsProtocol::HalfBlocks => {}
+        }
+    }
+    writer.write_all(b"\x1b8")?;
+    writer.flush()
+}
+
+const KITTY_DELETE_ALL: &[u8] = b"\x1b_Ga=d,d=A,q=2\x1b\\";
+
+/// Remove every image the session may have left behind. Only Kitty needs this: iTerm2 and Sixel
+/// images live in the cells and go away with them.
+pub fn purge_all<W: Write>(writer: &mut W) -> io::Result<()> {
+    if PROTOCOL.get() == Some(&GraphicsProtocol::Kitty) {
+        writer.write_all(KITTY_DELETE_ALL)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// Kitty graphics protocol: transmit the PNG (`f=100`) and display it scaled to the cell area in
+/// one go. The payload is base64 split into 4096 byte chunks; `q=2` suppresses replies that would
+/// otherwise show up as keyboard input.
+fn write_kitty<W: Write>(writer: &mut W, image: &FrameImage) -> io::Result<()> {
+    const CHUNK: usize = 4096;
+    let payload = BASE64.encode(&image.png);
+    let chunks: Vec<&[u8]> = payload.as_bytes().chunks(CHUNK).collect();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        if i == 0 {
+            write!(
+                writer,
+                "\x1b_Ga=T,f=100,i={},c={},r={},C=1,q=2,m={more};",
+                image.id, image.area.width, image.area.height
+            )?;
+        } else {
+            write!(writer, "\x1b_Gm={more};")?;
+        }
+        writer.write_all(chunk)?;
+        writer.write_all(b"\x1b\\")?;
+    }
+    Ok(())
+}
+
+/// iTerm2 inline images (also understood by `WezTerm`), stretched over the cell area. The area was
+/// already fitted to the image aspect ratio, so no further correction is requested.
+fn write_iterm2<W: Write>(writer: &mut W, image: &FrameImage) -> io::Result<()> {
+    write!(
+        writer,
+        "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=0:",
+        image.png.len(),
+        image.area.width,
+        image.area.height
+    )?;
+    writer.write_all(BASE64.encode(&image.png).as_bytes())?;
+    writer.write_all(b"\x07")
+}
+
+/// Pixel size of a terminal cell, falling back to a common 10x20 when the terminal does not
+/// report its pixel dimensions.
+fn cell_pixel_size() -> (u32, u32) {
+    crossterm::terminal::window_size()
+        .ok()
+        .filter(|s| s.width > 0 && s.height > 0 && s.columns > 0 && s.rows > 0)
+        .map_or((10, 20), |s| (u32::from(s.width / s.columns), u32::from(s.height / s.rows)))
+}
+
+/// Sixel output: scale to the pixel size of the cell area, quantize to a 6x6x6 color cube and
+/// emit one run-length encoded plane per color and six-row band.
+fn write_sixel<W: Write>(writer: &mut W, image: &FrameImage) -> io::Result<()> {
+    const LEVELS: u32 = 6;
+    const COLORS: usize = (LEVELS * LEVELS * LEVELS) as usize;
+
+    let (cell_w, cell_h) = cell_pixel_size();
+    let out_w = (u32::from(image.area.width) * cell_w).max(1);
+    let out_h = (u32::from(image.area.height) * cell_h).max(1);
+    let quantize = |c: u8| (u32::from(c) * (LEVELS - 1) + 127) / 255;
+
+    let mut indices = vec![0u8; (out_w * out_h) as usize];
+    for y in 0..out_h {
+        let sy = (y * image.height / out_h).min(image.height.saturating_sub(1));
+        for x in 0..out_w {
+            let sx = (x * image.width / out_w).min(image.width.saturating_sub(1));
+            let offset = ((sy * image.width + sx) * 3) as usize;
+            let Some(p) = image.rgb.get(offset..offset + 3) else {
+                continue;
+            };
+            #[allow(clippy::cast_possible_truncation)]
+            let index = (quantize(p[0]) * LEVELS * LEVELS + quantize(p[1]) * LEVELS + quantize(p[2])) as u8;
+            indices[(y * out_w + x) as usize] = index;
+        }
+    }
+
+    let mut out = Vec::with_capacity(indices.len() / 2);
+    write!(out, "\x1bP0;1q\"1;1;{out_w};{out_h}")?;
+    for index in 0..COLORS {
+        #[allow(clippy::cast_possible_truncation)]
+        let level = |v: usize| (v as u32) * 100 / (LEVELS - 1);
+        let l = LEVELS as usize;
+        write!(out, "#{index};2;{};{};{}", level(index / (l * l)), level(index / l % l), level(index % l))?;
+    }
+
+    let mut planes: Vec<Option<Vec<u8>>> = vec![None; COLORS];
+    for band_top in (0..out_h).step_by(6) {
+        for plane in planes.iter_mut().flatten() {
+            plane.fill(0);
+        }
+        for dy in 0..6.min(out_h - band_top) {
+            let row = ((band_top + dy) * out_w) as usize;
+            for x in 0..out_w as usize {
+                let plane = planes[indices[row + x] as usize].get_or_insert_with(|| vec![0; out_w as usize]);
+                plane[x] |= 1 << dy;
+            }
+        }
+        let mut first = true;
+        for (index, plane) in planes.iter().enumerate() {
+            let Some(plane) = plane.as_ref().filter(|p| p.iter().any(|&b| b != 0)) else {
+                continue;
+            };
+            if !first {
+                out.push(b'$');
+            }
+            first = false;
+            write!(out, "#{index}")?;
+            write_sixel_run_length(&mut out, plane)?;
+        }
+        out.push(b'-');
+    }
+    out.extend_from_slice(b"\x1b\\");
+    writer.write_all(&out)
+}
+
+fn write_sixel_run_length(out: &mut Vec<u8>, plane: &[u8]) -> io::Result<()> {
+    let mut i = 0;
+    while i < plane.len() {
+        let bits = plane[i];
+        let run = plane[i..].iter().take_while(|&&b| b == bits).count();
+        let ch = b'?' + bits;
+        if run > 3 {
+            write!(out, "!{run}")?;
+            out.push(ch);
+        } else {
+            out.extend(std::iter::repeat_n(ch, run));
+        }
+        i += run;
+    }
+    Ok(())
+}
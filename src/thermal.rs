@@ -0,0 +1,161 @@
+//! Battery and thermal samples of the device, with per-device warning thresholds.
+//!
+//! Every sample runs one shell script printing `dumpsys battery` and the type and temperature of
+//! every `/sys/class/thermal` zone. Zones report millidegrees Celsius on most kernels and whole
+//! degrees on a few, so values are normalized; zones the shell may not read, or that report
+//! nonsense such as -273, are skipped.
+//!
+//! Thresholds live in `<data dir>/thermal_thresholds.json`, an object keyed by device serial with
+//! an optional `"default"` entry for devices that are not listed:
+//!
+//! ```json
+//! { "default": { "battery_temp_c": 42 }, "R58M12ABCDE": { "zone_temp_c": 65, "min_battery_level": 30 } }
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::Path,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    adb::{AdbOptions, device_serial, shell_output},
+    app_event::AppEvent,
+    app_event_sender::AppEventSender,
+    session::unix_millis,
+};
+
+const SCRIPT: &str = "dumpsys battery; echo '@@thermal'; for z in /sys/class/thermal/thermal_zone*; do \
+                      echo \"$(cat $z/type 2>/dev/null) $(cat $z/temp 2>/dev/null)\"; done";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ThermalSample {
+    /// Unix timestamp (milliseconds).
+    pub at: u64,
+    pub serial: String,
+    /// Charge in percent.
+    pub battery_level: Option<u8>,
+    /// Battery temperature in degrees Celsius.
+    pub battery_temp: Option<f64>,
+    /// Whether any charger is connected.
+    pub powered: Option<bool>,
+    /// Thermal zone types and temperatures in degrees Celsius.
+    pub zones: Vec<(String, f64)>,
+}
+
+impl ThermalSample {
+    /// The hottest thermal zone.
+    pub fn hottest_zone(&self) -> Option<(&str, f64)> {
+        self.zones.iter().map(|(name, temp)| (name.as_str(), *temp)).max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Thresholds {
+    /// Warn above this battery temperature.
+    pub battery_temp_c: f64,
+    /// Warn when any thermal zone is above this temperature.
+    pub zone_temp_c: f64,
+    /// Warn below this charge level.
+    pub min_battery_level: u8,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            battery_temp_c: 45.0,
+            zone_temp_c: 75.0,
+            min_battery_level: 20,
+        }
+    }
+}
+
+impl Thresholds {
+    /// The thresholds for `serial` from the file at `path`, falling back to its `"default"` entry
+    /// and then to the built-in values.
+    pub fn load(path: &Path, serial: &str) -> io::Result<Self> {
+        let mut entries: BTreeMap<String, Self> = match fs::read(path) {
+            Ok(json) => serde_json::from_slice(&json).map_err(io::Error::other)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(err),
+        };
+        Ok(entries.remove(serial).or_else(|| entries.remove("default")).unwrap_or_default())
+    }
+
+    /// Every threshold `sample` crosses, as a stable key and a description with the reading, so an
+    /// ongoing warning can be told apart from a new one.
+    pub fn check(&self, sample: &ThermalSample) -> Vec<(&'static str, String)> {
+        let mut warnings = Vec::new();
+        if let Some(temp) = sample.battery_temp
+            && temp > self.battery_temp_c
+        {
+            warnings.push(("battery_temp", format!("battery at {temp:.1}°C (limit {:.0}°C)", self.battery_temp_c)));
+        }
+        if let Some((zone, temp)) = sample.hottest_zone()
+            && temp > self.zone_temp_c
+        {
+            warnings.push(("zone_temp", format!("{zone} at {temp:.1}°C (limit {:.0}°C)", self.zone_temp_c)));
+        }
+        if let Some(level) = sample.battery_level
+            && level < self.min_battery_level
+        {
+            warnings.push(("battery_level", format!("battery at {level}% (limit {}%)", self.min_battery_level)));
+        }
+        warnings
+    }
+}
+
+fn parse_sample(output: &str, serial: String, at: u64) -> ThermalSample {
+    let (battery, thermal) = output.split_once("@@thermal").unwrap_or((output, ""));
+    let field = |label: &str| {
+        battery.lines().find_map(|line| line.trim().strip_prefix(label)?.strip_prefix(':').map(str::trim))
+    };
+    let powered = ["AC powered", "USB powered", "Wireless powered", "Dock powered"]
+        .iter()
+        .filter_map(|label| field(label))
+        .map(|value| value == "true")
+        .reduce(|a, b| a || b);
+    ThermalSample {
+        at,
+        serial,
+        battery_level: field("level").and_then(|level| level.parse().ok()),
+        // Reported in tenths of a degree.
+        battery_temp: field("temperature").and_then(|temp| temp.parse::<f64>().ok()).map(|temp| temp / 10.0),
+        powered,
+        zones: thermal.lines().filter_map(parse_zone).collect(),
+    }
+}
+
+fn parse_zone(line: &str) -> Option<(String, f64)> {
+    let (name, temp) = line.trim().rsplit_once(' ')?;
+    let temp: f64 = temp.parse().ok()?;
+    let temp = if temp.abs() >= 1000.0 {
+        temp / 1000.0
+    } else {
+        temp
+    };
+    (!name.is_empty() && (-40.0..=150.0).contains(&temp)).then(|| (name.to_string(), temp))
+}
+
+fn sample(adb: &AdbOptions) -> io::Result<ThermalSample> {
+    let serial = device_serial(adb)?;
+    let output = shell_output(&mut adb.device(), &[SCRIPT]).map_err(|err| io::Error::other(err.to_string()))?;
+    let at = unix_millis(SystemTime::now());
+    Ok(parse_sample(&String::from_utf8_lossy(&output), serial, at))
+}
+
+/// Sample every `interval` until the app shuts down, delivering [`AppEvent::ThermalSampled`].
+pub fn spawn_sampler(adb: AdbOptions, app_event_tx: AppEventSender, interval: Duration) {
+    thread::spawn(move || {
+        while !app_event_tx.app_event_tx.is_closed() {
+            let result = sample(&adb).map(Box::new).map_err(|err| err.to_string());
+            app_event_tx.send(AppEvent::ThermalSampled(result));
+            thread::sleep(interval);
+        }
+    });
+}
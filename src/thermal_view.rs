@@ -0,0 +1,216 @@
+//! THERMAL tab: battery charge and device temperatures over time, with warnings when the
+//! thresholds configured for the device are crossed.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::PathBuf,
+};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Sparkline},
+};
+
+use crate::{
+    custom_terminal::Frame,
+    thermal::{ThermalSample, Thresholds},
+    tui,
+};
+
+/// Samples kept for the charts; twelve hours at the default interval of 30s.
+const MAX_SAMPLES: usize = 1440;
+
+pub struct ThermalView {
+    thresholds_path: PathBuf,
+    /// Thresholds of the device the samples come from, by serial.
+    thresholds: Option<(String, Thresholds)>,
+    samples: VecDeque<ThermalSample>,
+    /// Thresholds crossed by the latest sample, by key.
+    warnings: BTreeMap<&'static str, String>,
+    error: Option<String>,
+}
+
+impl ThermalView {
+    pub const fn new(thresholds_path: PathBuf) -> Self {
+        Self {
+            thresholds_path,
+            thresholds: None,
+            samples: VecDeque::new(),
+            warnings: BTreeMap::new(),
+            error: None,
+        }
+    }
+
+    fn thresholds_for(&mut self, serial: &str) -> Thresholds {
+        match &self.thresholds {
+            Some((known, thresholds)) if known == serial => *thresholds,
+            _ => {
+                let thresholds = Thresholds::load(&self.thresholds_path, serial).unwrap_or_else(|err| {
+                    self.error = Some(format!("{}: {err}", self.thresholds_path.display()));
+                    Thresholds::default()
+                });
+                self.thresholds = Some((serial.to_string(), thresholds));
+                thresholds
+            }
+        }
+    }
+
+    /// Take a new sample and return the warnings raised or cleared by it, for the session log.
+    pub fn on_sample(&mut self, result: Result<Box<ThermalSample>, String>) -> Vec<String> {
+        let sample = match result {
+            Ok(sample) => *sample,
+            Err(err) => {
+                self.error = Some(err);
+                return Vec::new();
+            }
+        };
+        self.error = None;
+        let thresholds = self.thresholds_for(&sample.serial);
+        let warnings: BTreeMap<_, _> = thresholds.check(&sample).into_iter().collect();
+        let mut events: Vec<String> = warnings
+            .iter()
+            .filter(|(key, _)| !self.warnings.contains_key(*key))
+            .map(|(_, warning)| format!("thermal warning: {warning}"))
+            .collect();
+        events.extend(
+            self.warnings
+                .iter()
+                .filter(|(key, _)| !warnings.contains_key(*key))
+                .map(|(_, warning)| format!("thermal warning cleared: {warning}")),
+        );
+        self.warnings = warnings;
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        events
+    }
+
+    /// Warning for the tab bar while any threshold is crossed.
+    pub fn warning_line(&self) -> Option<Line<'static>> {
+        let warnings: Vec<&str> = self.warnings.values().map(String::as_str).collect();
+        (!warnings.is_empty()).then(|| Line::from(format!("⚠ {}", warnings.join(" · "))).red().bold())
+    }
+
+    pub fn handle_key_event(&mut self, tui: &tui::Tui, key_event: KeyEvent) {
+        match key_event.code {
+            // Picked up again with the next sample.
+            KeyCode::Char('r') => {
+                self.thresholds = None;
+                self.error = None;
+            }
+            KeyCode::Char('c') => self.samples.clear(),
+            _ => return,
+        }
+        tui.frame_requester().schedule_frame();
+    }
+
+    fn status_line(&self) -> Line<'static> {
+        let mut line = Line::from(" r: reload thresholds  c: clear history").dim();
+        if let Some(sample) = self.samples.back() {
+            let power = match sample.powered {
+                Some(true) => ", charging",
+                Some(false) => ", on battery",
+                None => "",
+            };
+            line.push_span(Span::from(format!(" · {}{power}", sample.serial)));
+        }
+        if let Some(err) = &self.error {
+            line.push_span(Span::from(format!(" · {err}")).red());
+        }
+        line
+    }
+
+    /// The last `width` values of `value`, rounded for the sparkline.
+    fn series(&self, width: u16, value: impl Fn(&ThermalSample) -> Option<f64>) -> Vec<Option<u64>> {
+        let skip = self.samples.len().saturating_sub(usize::from(width));
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        self.samples.iter().skip(skip).map(|sample| value(sample).map(|value| value.max(0.0).round() as u64)).collect()
+    }
+
+    fn chart(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        title: String,
+        color: Color,
+        value: impl Fn(&ThermalSample) -> Option<f64>,
+    ) {
+        let block = Block::bordered().title(title);
+        let data = self.series(area.width.saturating_sub(2), value);
+        frame.render_widget(Sparkline::default().block(block).data(data).style(Style::default().fg(color)), area);
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect) {
+        let [status_area, body] = Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+        frame.render_widget(self.status_line(), status_area);
+        let Some(latest) = self.samples.back() else {
+            frame.render_widget(
+                Paragraph::new(Line::from("Waiting for the first sample…").italic())
+                    .block(Block::bordered().title(" Thermal ")),
+                body,
+            );
+            return;
+        };
+        let [charts_area, side_area] = Layout::horizontal([Constraint::Min(0), Constraint::Length(36)]).areas(body);
+        let [battery_temp_area, zone_area, level_area] =
+            Layout::vertical([Constraint::Ratio(1, 3); 3]).areas(charts_area);
+        let thresholds = self.thresholds.as_ref().map(|(_, thresholds)| *thresholds).unwrap_or_default();
+        let temp = |temp: Option<f64>| temp.map_or_else(|| "?".to_string(), |temp| format!("{temp:.1}°C"));
+
+        // Tenths of a degree, so small changes still show.
+        self.chart(
+            frame,
+            battery_temp_area,
+            format!(" Battery {} (limit {:.0}°C) ", temp(latest.battery_temp), thresholds.battery_temp_c),
+            Color::Red,
+            |sample| sample.battery_temp.map(|temp| temp * 10.0),
+        );
+        let hottest = latest.hottest_zone();
+        self.chart(
+            frame,
+            zone_area,
+            format!(
+                " Hottest zone {} {} (limit {:.0}°C) ",
+                hottest.map_or("", |(name, _)| name),
+                temp(hottest.map(|(_, temp)| temp)),
+                thresholds.zone_temp_c
+            ),
+            Color::Yellow,
+            |sample| sample.hottest_zone().map(|(_, temp)| temp * 10.0),
+        );
+        self.chart(
+            frame,
+            level_area,
+            format!(
+                " Charge {} (warn below {}%) ",
+                latest.battery_level.map_or_else(|| "?".to_string(), |level| format!("{level}%")),
+                thresholds.min_battery_level
+            ),
+            Color::Green,
+            |sample| sample.battery_level.map(f64::from),
+        );
+
+        let mut zones: Vec<&(String, f64)> = latest.zones.iter().collect();
+        zones.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let lines: Vec<Line> = if zones.is_empty() {
+            vec![Line::from("no readable zones").italic()]
+        } else {
+            zones
+                .into_iter()
+                .map(|(name, temp)| {
+                    let line = Line::from(format!("{temp:>6.1}°C  {name}"));
+                    if *temp > thresholds.zone_temp_c {
+                        line.red()
+                    } else {
+                        line
+                    }
+                })
+                .collect()
+        };
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(" Zones ")), side_area);
+    }
+}
@@ -0,0 +1,287 @@
+//! Touch input macros: raw `getevent` recordings replayed to reproduce UI sequences.
+//!
+//! A macro is a JSON file holding the recorded [`InputEvent`]s with times relative to the first
+//! one. Replaying through `sendevent` writes the exact events back to the same input device, which
+//! needs write access to `/dev/input` (emulators and most debug builds allow it). Replaying through
+//! `input` converts single-finger gestures to taps and swipes instead, assuming touch coordinates
+//! are screen pixels; extra fingers are ignored. Either way the timing is approximate, as every
+//! device round trip adds latency.
+
+use std::{
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use clap::ValueEnum;
+use color_eyre::eyre::{WrapErr, bail, eyre};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    adb::{AdbOptions, shell_output},
+    cli::{MacroArgs, MacroCommand},
+    getevent::{self, InputEvent},
+    input::{self, InputAction},
+    session::unix_millis,
+};
+
+/// `sendevent` commands are batched into shell scripts of about this size.
+const SCRIPT_CHUNK: usize = 16 * 1024;
+/// Movement in touch units below which a touch replays as a tap rather than a swipe.
+const TAP_SLOP: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReplayVia {
+    /// Write the recorded events to the input device with `sendevent`.
+    Sendevent,
+    /// Re-create taps, swipes and key presses with `input`.
+    Input,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TouchMacro {
+    /// Unix timestamp (milliseconds) of the end of the recording.
+    pub recorded_at: u64,
+    /// Events with `time` in seconds since the first event.
+    pub events: Vec<InputEvent>,
+}
+
+impl TouchMacro {
+    /// Wrap freshly recorded events, rebasing their kernel timestamps to the first one.
+    pub fn new(mut events: Vec<InputEvent>) -> Self {
+        let start = events.first().map_or(0.0, |e| e.time);
+        for event in &mut events {
+            event.time -= start;
+        }
+        Self {
+            recorded_at: unix_millis(SystemTime::now()),
+            events,
+        }
+    }
+
+    pub fn load(path: &Path) -> color_eyre::Result<Self> {
+        let json = fs::read(path).wrap_err_with(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_slice(&json).wrap_err_with(|| format!("{} is not a touch macro", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_vec_pretty(self).map_err(io::Error::other)?)
+    }
+
+    /// Replay at `speed` times the recorded pace. Blocks until the last event has been sent.
+    pub fn replay(&self, adb: &AdbOptions, speed: f64, via: ReplayVia) -> color_eyre::Result<()> {
+        if speed <= 0.0 {
+            bail!("replay speed must be positive");
+        }
+        match via {
+            ReplayVia::Sendevent => self.replay_sendevent(adb, speed),
+            ReplayVia::Input => self.replay_input(adb, speed),
+        }
+    }
+
+    fn replay_sendevent(&self, adb: &AdbOptions, speed: f64) -> color_eyre::Result<()> {
+        let mut device = adb.device();
+        for script in sendevent_scripts(&self.events, speed)? {
+            let output = shell_output(&mut device, &[&script]).wrap_err("sendevent failed")?;
+            let output = String::from_utf8_lossy(&output);
+            if !output.trim().is_empty() {
+                bail!("sendevent failed: {}", output.trim());
+            }
+        }
+        Ok(())
+    }
+
+    fn replay_input(&self, adb: &AdbOptions, speed: f64) -> color_eyre::Result<()> {
+        let mut device = adb.device();
+        let started = Instant::now();
+        for (at, action) in gestures(&self.events) {
+            let due = Duration::from_secs_f64(at / speed);
+            thread::sleep(due.saturating_sub(started.elapsed()));
+            let action = match action {
+                InputAction::Swipe {
+                    from,
+                    to,
+                    duration,
+                } => InputAction::Swipe {
+                    from,
+                    to,
+                    duration: duration.div_f64(speed),
+                },
+                action => action,
+            };
+            input::send(&mut device, &action).map_err(|err| eyre!("{action} failed: {err}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Shell scripts of `sendevent` and `sleep` commands, split between input frames so every script
+/// stays below [`SCRIPT_CHUNK`].
+fn sendevent_scripts(events: &[InputEvent], speed: f64) -> color_eyre::Result<Vec<String>> {
+    let mut scripts = Vec::new();
+    let mut script = String::new();
+    let mut last_time = events.first().map_or(0.0, |e| e.time);
+    for event in events {
+        let Some((kind, code)) = event.numeric() else {
+            bail!("cannot replay {} {} with sendevent", event.kind, event.code);
+        };
+        let gap = (event.time - last_time) / speed;
+        last_time = event.time;
+        if gap >= 0.001 {
+            let _ = write!(script, "sleep {gap:.3};");
+        }
+        let _ = write!(script, "sendevent {} {kind} {code} {};", event.device, event.value);
+        if script.len() >= SCRIPT_CHUNK && event.is("EV_SYN", "SYN_REPORT") {
+            scripts.push(std::mem::take(&mut script));
+        }
+    }
+    if !script.is_empty() {
+        scripts.push(script);
+    }
+    Ok(scripts)
+}
+
+/// The `input` key code for keys worth replaying.
+fn key_code(code: &str) -> Option<&'static str> {
+    match code {
+        "KEY_BACK" => Some("KEYCODE_BACK"),
+        "KEY_HOMEPAGE" => Some("KEYCODE_HOME"),
+        "KEY_POWER" => Some("KEYCODE_POWER"),
+        "KEY_VOLUMEUP" => Some("KEYCODE_VOLUME_UP"),
+        "KEY_VOLUMEDOWN" => Some("KEYCODE_VOLUME_DOWN"),
+        _ => None,
+    }
+}
+
+/// Reduce raw events to the first finger's taps and swipes plus key presses, each with its start
+/// time in seconds.
+fn gestures(events: &[InputEvent]) -> Vec<(f64, InputAction)> {
+    let mut gestures = Vec::new();
+    let (mut x, mut y) = (0, 0);
+    let mut slot = 0;
+    let mut touching = false;
+    // Start time and position of the current touch, set on the first frame after it went down.
+    let mut down: Option<(f64, (u32, u32))> = None;
+    let coordinate = |value: i32| u32::try_from(value).unwrap_or_default();
+    for event in events {
+        match (event.kind.as_str(), event.code.as_str()) {
+            ("EV_ABS", "ABS_MT_SLOT") => slot = event.value,
+            ("EV_ABS", "ABS_MT_POSITION_X" | "ABS_X") if slot == 0 => x = coordinate(event.value),
+            ("EV_ABS", "ABS_MT_POSITION_Y" | "ABS_Y") if slot == 0 => y = coordinate(event.value),
+            ("EV_ABS", "ABS_MT_TRACKING_ID") if slot == 0 => touching = event.value != -1,
+            ("EV_KEY", "BTN_TOUCH") => touching = event.value != 0,
+            ("EV_KEY", code) if event.value == 1 => {
+                if let Some(key) = key_code(code) {
+                    gestures.push((event.time, InputAction::Key(key.to_string())));
+                }
+            }
+            ("EV_SYN", "SYN_REPORT") => match (touching, down) {
+                (true, None) => down = Some((event.time, (x, y))),
+                (false, Some((started, from))) => {
+                    let duration = Duration::from_secs_f64((event.time - started).max(0.0));
+                    let action = if from.0.abs_diff(x) <= TAP_SLOP && from.1.abs_diff(y) <= TAP_SLOP {
+                        InputAction::Tap {
+                            x: from.0,
+                            y: from.1,
+                        }
+                    } else {
+                        InputAction::Swipe {
+                            from,
+                            to: (x, y),
+                            duration,
+                        }
+                    };
+                    gestures.push((started, action));
+                    down = None;
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    gestures
+}
+
+/// A `getevent` recording running on a worker thread.
+pub struct Recorder {
+    stop: Arc<AtomicBool>,
+    events: Arc<Mutex<Vec<InputEvent>>>,
+}
+
+impl Recorder {
+    pub fn start(adb: &AdbOptions) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let (adb, thread_stop, sink) = (adb.clone(), stop.clone(), events.clone());
+        thread::spawn(move || {
+            let result = getevent::stream(&adb, &thread_stop, |batch| {
+                sink.lock().unwrap_or_else(PoisonError::into_inner).extend(batch);
+                true
+            });
+            if let Err(err) = result {
+                tracing::debug!("macro recording ended: {err}");
+            }
+        });
+        Self {
+            stop,
+            events,
+        }
+    }
+
+    /// Stop recording and take what was captured. The worker exits on the next event.
+    pub fn finish(self) -> TouchMacro {
+        self.stop.store(true, Ordering::Relaxed);
+        TouchMacro::new(std::mem::take(&mut *self.events.lock().unwrap_or_else(PoisonError::into_inner)))
+    }
+}
+
+/// The newest macro in `dir`, by file name.
+pub fn latest_in(dir: &Path) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .max()
+}
+
+/// Entry point for `uma-auto-debug macro`.
+pub fn run_headless(args: &MacroArgs) -> color_eyre::Result<()> {
+    let adb = AdbOptions::default();
+    match &args.command {
+        MacroCommand::Record {
+            out,
+            duration,
+        } => {
+            let recorder = Recorder::start(&adb);
+            if let Some(secs) = duration {
+                eprintln!("recording for {secs}s…");
+                thread::sleep(Duration::from_secs(*secs));
+            } else {
+                eprintln!("recording, press Enter to stop…");
+                io::stdin().read_line(&mut String::new())?;
+            }
+            let touch_macro = recorder.finish();
+            touch_macro.save(out).wrap_err_with(|| format!("failed to write {}", out.display()))?;
+            eprintln!("recorded {} events to {}", touch_macro.events.len(), out.display());
+        }
+        MacroCommand::Replay {
+            file,
+            speed,
+            via,
+        } => {
+            let touch_macro = TouchMacro::load(file)?;
+            touch_macro.replay(&adb, *speed, *via)?;
+            eprintln!("replayed {} events from {}", touch_macro.events.len(), file.display());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,250 @@
+//! Live touch traces from `getevent`, drawn over the SCREEN tab image with a fading trail.
+//!
+//! Multi-touch devices are followed per slot using `ABS_MT_TRACKING_ID`; single-touch devices
+//! through `BTN_TOUCH` and `ABS_X`/`ABS_Y`. Raw coordinates are scaled to the screenshot using
+//! the ranges reported by `getevent -lp`, assuming the panel is not rotated against the screen.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use ratatui::{buffer::Buffer, style::Color};
+
+use crate::{
+    adb::AdbOptions,
+    app_event_sender::AppEventSender,
+    getevent::{self, InputEvent},
+    screen_view::ImagePlacement,
+    screenshot::Screenshot,
+    tui,
+};
+
+/// How long a trace stays visible after its last movement.
+const TRAIL: Duration = Duration::from_secs(3);
+/// Redraw interval while trails fade out.
+const FADE_STEP: Duration = Duration::from_millis(250);
+
+struct Trace {
+    id: u64,
+    device: String,
+    /// Raw touch coordinates and when they were reported.
+    points: Vec<((i32, i32), Instant)>,
+    ended: bool,
+}
+
+/// One finger: a multi-touch slot, or the only contact of a single-touch device.
+#[derive(Default)]
+struct Contact {
+    x: i32,
+    y: i32,
+    trace: Option<u64>,
+    moved: bool,
+    starting: bool,
+    ending: bool,
+}
+
+pub struct TouchTraces {
+    stop: Arc<AtomicBool>,
+    ranges: HashMap<String, (u32, u32)>,
+    /// Current `ABS_MT_SLOT` of every device.
+    slots: HashMap<String, i32>,
+    contacts: HashMap<(String, i32), Contact>,
+    traces: Vec<Trace>,
+    next_id: u64,
+    /// When fade-out frames were last scheduled, so bursts of events do not schedule them again.
+    fade_scheduled_at: Option<Instant>,
+}
+
+impl TouchTraces {
+    /// Start streaming `getevent` from the device.
+    pub fn start(adb: &AdbOptions, app_event_tx: &AppEventSender) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        getevent::spawn_stream(adb.clone(), app_event_tx.clone(), stop.clone());
+        Self {
+            stop,
+            ranges: HashMap::new(),
+            slots: HashMap::new(),
+            contacts: HashMap::new(),
+            traces: Vec::new(),
+            next_id: 0,
+            fade_scheduled_at: None,
+        }
+    }
+
+    pub fn set_ranges(&mut self, ranges: HashMap<String, (u32, u32)>) {
+        self.ranges = ranges;
+    }
+
+    pub fn feed(&mut self, tui: &tui::Tui, events: Vec<InputEvent>) {
+        let now = Instant::now();
+        for event in events {
+            self.apply(event, now);
+        }
+        self.traces
+            .retain(|trace| !trace.ended || trace.points.last().is_some_and(|(_, at)| now.duration_since(*at) < TRAIL));
+
+        let requester = tui.frame_requester();
+        requester.schedule_frame();
+        if self.fade_scheduled_at.is_none_or(|at| now.duration_since(at) >= FADE_STEP) {
+            self.fade_scheduled_at = Some(now);
+            for step in 1..=TRAIL.as_millis() / FADE_STEP.as_millis() + 1 {
+                requester.schedule_frame_in(FADE_STEP * u32::try_from(step).unwrap_or(u32::MAX));
+            }
+        }
+    }
+
+    fn apply(&mut self, event: InputEvent, now: Instant) {
+        let slot = self.slots.get(&event.device).copied().unwrap_or_default();
+        let contact = || (event.device.clone(), slot);
+        match (event.kind.as_str(), event.code.as_str()) {
+            ("EV_ABS", "ABS_MT_SLOT") => {
+                self.slots.insert(event.device, event.value);
+            }
+            ("EV_ABS", "ABS_MT_TRACKING_ID") | ("EV_KEY", "BTN_TOUCH") => {
+                let released = if event.code == "BTN_TOUCH" {
+                    event.value == 0
+                } else {
+                    event.value == -1
+                };
+                let contact = self.contacts.entry(contact()).or_default();
+                if released {
+                    contact.ending = true;
+                } else if contact.trace.is_none() {
+                    contact.starting = true;
+                }
+            }
+            ("EV_ABS", "ABS_MT_POSITION_X" | "ABS_X") => {
+                let contact = self.contacts.entry(contact()).or_default();
+                contact.x = event.value;
+                contact.moved = true;
+            }
+            ("EV_ABS", "ABS_MT_POSITION_Y" | "ABS_Y") => {
+                let contact = self.contacts.entry(contact()).or_default();
+                contact.y = event.value;
+                contact.moved = true;
+            }
+            ("EV_SYN", "SYN_REPORT") => self.end_frame(&event.device, now),
+            _ => {}
+        }
+    }
+
+    /// Apply everything reported for `device` since its previous `SYN_REPORT`.
+    fn end_frame(&mut self, device: &str, now: Instant) {
+        for ((contact_device, _), contact) in &mut self.contacts {
+            if contact_device != device {
+                continue;
+            }
+            let point = ((contact.x, contact.y), now);
+            if contact.starting {
+                self.next_id += 1;
+                self.traces.push(Trace {
+                    id: self.next_id,
+                    device: device.to_string(),
+                    points: vec![point],
+                    ended: false,
+                });
+                contact.trace = Some(self.next_id);
+            } else if contact.moved
+                && let Some(trace) = self.traces.iter_mut().find(|t| Some(t.id) == contact.trace)
+            {
+                trace.points.push(point);
+            }
+            if contact.ending {
+                if let Some(trace) = self.traces.iter_mut().find(|t| Some(t.id) == contact.trace) {
+                    trace.ended = true;
+                    trace.points.push(point);
+                }
+                contact.trace = None;
+            }
+            contact.moved = false;
+            contact.starting = false;
+            contact.ending = false;
+        }
+    }
+
+    /// Raw touch coordinates of `device` in screenshot pixels.
+    fn to_device(&self, device: &str, (x, y): (i32, i32), shot: &Screenshot) -> (u32, u32) {
+        let (x, y) = (u32::try_from(x).unwrap_or_default(), u32::try_from(y).unwrap_or_default());
+        match self.ranges.get(device) {
+            Some(&(max_x, max_y)) => (
+                u32::try_from(u64::from(x) * u64::from(shot.width) / u64::from(max_x + 1)).unwrap_or(u32::MAX),
+                u32::try_from(u64::from(y) * u64::from(shot.height) / u64::from(max_y + 1)).unwrap_or(u32::MAX),
+            ),
+            None => (x, y),
+        }
+    }
+
+    /// Draw every trace that is still fresh on a half-block rendering of `shot`: `◉` where the
+    /// finger went down, `·` along the path and `●` (still down) or `○` (lifted) at the end.
+    pub fn render(&self, placement: &ImagePlacement, shot: &Screenshot, buf: &mut Buffer) {
+        let now = Instant::now();
+        for trace in &self.traces {
+            let cells: Vec<((u16, u16), Duration)> = trace
+                .points
+                .iter()
+                .filter_map(|&(raw, at)| {
+                    let (x, y) = self.to_device(&trace.device, raw, shot);
+                    Some((placement.cell_at(x, y)?, now.duration_since(at)))
+                })
+                .collect();
+            let mut draw = |(col, row): (u16, u16), symbol: &str, age: Duration| {
+                if age >= TRAIL {
+                    return;
+                }
+                if let Some(cell) = buf.cell_mut((col, row)) {
+                    cell.set_symbol(symbol).set_fg(fade(age));
+                }
+            };
+            for pair in cells.windows(2) {
+                let ((from, _), (to, age)) = (pair[0], pair[1]);
+                for cell in line_cells(from, to) {
+                    draw(cell, "·", age);
+                }
+            }
+            if let Some(&(cell, age)) = cells.first() {
+                draw(cell, "◉", age);
+            }
+            match cells.last() {
+                Some(&(cell, age)) if trace.ended => draw(cell, "○", age),
+                // A finger that is still down stays bright however long it rests.
+                Some(&(cell, _)) if cells.len() > 1 => draw(cell, "●", Duration::ZERO),
+                Some(&(cell, _)) => draw(cell, "◉", Duration::ZERO),
+                None => {}
+            }
+        }
+    }
+
+    pub fn active_traces(&self) -> usize {
+        self.traces.iter().filter(|trace| !trace.ended).count()
+    }
+}
+
+impl Drop for TouchTraces {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Bright yellow for fresh input, fading to gray over [`TRAIL`].
+fn fade(age: Duration) -> Color {
+    let t = (age.as_secs_f64() / TRAIL.as_secs_f64()).clamp(0.0, 1.0);
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let mix = |from: u8, to: u8| (f64::from(to) - f64::from(from)).mul_add(t, f64::from(from)).round() as u8;
+    Color::Rgb(mix(255, 90), mix(215, 90), mix(0, 90))
+}
+
+/// Cells on the straight line from `from` to `to`, excluding `from`.
+fn line_cells(from: (u16, u16), to: (u16, u16)) -> impl Iterator<Item = (u16, u16)> {
+    let (dx, dy) = (i32::from(to.0) - i32::from(from.0), i32::from(to.1) - i32::from(from.1));
+    let steps = dx.abs().max(dy.abs());
+    (1..=steps).filter_map(move |i| {
+        let col = i32::from(from.0) + dx * i / steps;
+        let row = i32::from(from.1) + dy * i / steps;
+        Some((u16::try_from(col).ok()?, u16::try_from(row).ok()?))
+    })
+}
@@ -4,7 +4,6 @@ use std::sync::atomic::AtomicU8;
 use std::sync::atomic::AtomicU16;
 use std::{
     io::{Result, Stdout, stdout},
-    path::PathBuf,
     pin::Pin,
     sync::{
         Arc,
@@ -17,9 +16,8 @@ use crossterm::{
     Command, SynchronizedUpdate, cursor,
     cursor::MoveTo,
     event::{
-        DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event, KeyCode, KeyEvent,
-        KeyEventKind, KeyModifiers, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags,
-        PushKeyboardEnhancementFlags,
+        DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event, KeyEvent,
+        KeyboardEnhancementFlags, MouseEvent, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
     },
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, ScrollUp},
 };
@@ -30,12 +28,11 @@ use ratatui::{
         terminal::{disable_raw_mode, enable_raw_mode},
     },
     layout::Offset,
-    text::Line,
 };
 use tokio::select;
 use tokio_stream::Stream;
 
-use crate::{custom_terminal, custom_terminal::Terminal as CustomTerminal};
+use crate::{custom_terminal, custom_terminal::Terminal as CustomTerminal, terminal_graphics};
 
 /// A type alias for the terminal type used in this application
 pub type Terminal = CustomTerminal<CrosstermBackend<Stdout>>;
@@ -101,10 +98,38 @@ impl Command for DisableAlternateScroll {
     }
 }
 
+/// Put text on the system clipboard with OSC 52, which also works over SSH. Terminals that do not
+/// support it ignore the sequence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SetClipboard(String);
+
+impl Command for SetClipboard {
+    fn write_ansi(&self, f: &mut impl std::fmt::Write) -> std::fmt::Result {
+        use base64::Engine as _;
+        write!(f, "\x1b]52;c;{}\x07", base64::engine::general_purpose::STANDARD.encode(&self.0))
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> std::io::Result<()> {
+        Err(std::io::Error::other("tried to execute SetClipboard using WinAPI; use ANSI instead"))
+    }
+
+    #[cfg(windows)]
+    fn is_ansi_code_supported(&self) -> bool {
+        true
+    }
+}
+
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    execute!(stdout(), SetClipboard(text.to_string()))
+}
+
 /// Restore the terminal to its original state.
 /// Inverse of `set_modes`.
 pub fn restore() -> Result<()> {
-    execute!(stdout(), LeaveAlternateScreen);
+    // Kitty keeps images on their own layer; make sure none outlive the session.
+    let _ = terminal_graphics::purge_all(&mut stdout());
+    execute!(stdout(), LeaveAlternateScreen)?;
     execute!(stdout(), DisableMouseCapture)?;
     execute!(stdout(), DisableBracketedPaste)?;
     disable_raw_mode()?;
@@ -146,18 +171,20 @@ pub fn init() -> Result<Terminal> {
 pub enum TuiEvent {
     Key(KeyEvent),
     Paste(String),
+    Mouse(MouseEvent),
     Draw,
-    AttachImage {
-        path: PathBuf,
-        width: u32,
-        height: u32,
-        format_label: &'static str,
-    },
 }
 
 pub struct Tui {
+    frame_schedule_tx: tokio::sync::mpsc::UnboundedSender<Instant>,
+    draw_tx: tokio::sync::broadcast::Sender<()>,
     pub(crate) terminal: Terminal,
-    task: tokio::task::JoinHandle<()>,
+    #[cfg(unix)]
+    resume_pending: Arc<AtomicU8>,
+    #[cfg(unix)]
+    suspend_cursor_y: Arc<AtomicU16>,
+    /// The terminal has been on the alternate screen since [`init`].
+    alt_screen_active: Arc<AtomicBool>,
 }
 
 #[cfg(unix)]
@@ -170,6 +197,7 @@ enum ResumeAction {
 }
 
 #[cfg(unix)]
+#[derive(Clone, Copy)]
 enum PreparedResumeAction {
     RestoreAltScreen,
     RealignViewport(ratatui::layout::Rect),
@@ -200,21 +228,54 @@ impl FrameRequester {
 
 impl Tui {
     pub fn new(terminal: Terminal) -> Self {
-        let task = tokio::spawn(async {
-            event_loop.await;
+        let (frame_schedule_tx, mut frame_schedule_rx) = tokio::sync::mpsc::unbounded_channel::<Instant>();
+        let (draw_tx, _) = tokio::sync::broadcast::channel(1);
+
+        // Coalesce frame requests and emit a draw at the earliest requested deadline.
+        let scheduler_draw_tx = draw_tx.clone();
+        tokio::spawn(async move {
+            let mut next_deadline: Option<Instant> = None;
+            loop {
+                let sleep = async {
+                    match next_deadline {
+                        Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+                        None => std::future::pending().await,
+                    }
+                };
+                select! {
+                    requested = frame_schedule_rx.recv() => {
+                        let Some(at) = requested else {
+                            break;
+                        };
+                        if next_deadline.is_none_or(|deadline| at < deadline) {
+                            next_deadline = Some(at);
+                        }
+                    }
+                    () = sleep => {
+                        next_deadline = None;
+                        let _ = scheduler_draw_tx.send(());
+                    }
+                }
+            }
         });
 
         Self {
+            frame_schedule_tx,
+            draw_tx,
             terminal,
-            task,
+            #[cfg(unix)]
+            resume_pending: Arc::new(AtomicU8::new(ResumeAction::None as u8)),
+            #[cfg(unix)]
+            suspend_cursor_y: Arc::new(AtomicU16::new(0)),
+            alt_screen_active: Arc::new(AtomicBool::new(true)),
         }
     }
 
-    // pub fn frame_requester(&self) -> FrameRequester {
-    //     FrameRequester {
-    //         frame_schedule_tx: self.frame_schedule_tx.clone(),
-    //     }
-    // }
+    pub fn frame_requester(&self) -> FrameRequester {
+        FrameRequester {
+            frame_schedule_tx: self.frame_schedule_tx.clone(),
+        }
+    }
 
     pub fn event_stream(&self) -> Pin<Box<dyn Stream<Item = TuiEvent> + Send + 'static>> {
         use tokio_stream::StreamExt;
@@ -231,29 +292,6 @@ impl Tui {
                 select! {
                     Some(Ok(event)) = crossterm_events.next() => {
                         match event {
-                            // Detect Ctrl+V to attach an image from the clipboard.
-                            Event::Key(key_event @ KeyEvent {
-                                code: KeyCode::Char('v'),
-                                modifiers: KeyModifiers::CONTROL,
-                                kind: KeyEventKind::Press,
-                                ..
-                            }) => {
-                                // match paste_image_to_temp_png() {
-                                //     Ok((path, info)) => {
-                                //         yield TuiEvent::AttachImage {
-                                //             path,
-                                //             width: info.width,
-                                //             height: info.height,
-                                //             format_label: info.encoded_format.label(),
-                                //         };
-                                //     }
-                                //     Err(_) => {
-                                //         // Fall back to normal key handling if no image is available.
-                                //         yield TuiEvent::Key(key_event);
-                                //     }
-                                // }
-                            }
-
                             crossterm::event::Event::Key(key_event) => {
                                 #[cfg(unix)]
                                 if matches!(
@@ -280,7 +318,7 @@ impl Tui {
                                         let _ = execute!(stdout(), MoveTo(0, y));
                                     }
                                     let _ = execute!(stdout(), crossterm::cursor::Show);
-                                    let _ = Tui::suspend();
+                                    let _ = Self::suspend();
                                     yield TuiEvent::Draw;
                                     continue;
                                 }
@@ -292,12 +330,15 @@ impl Tui {
                             Event::Paste(pasted) => {
                                 yield TuiEvent::Paste(pasted);
                             }
+                            Event::Mouse(mouse_event) => {
+                                yield TuiEvent::Mouse(mouse_event);
+                            }
                             _ => {}
                         }
                     }
                     result = draw_rx.recv() => {
                         match result {
-                            Ok(_) => {
+                            Ok(()) => {
                                 yield TuiEvent::Draw;
                             }
                             Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {
@@ -330,17 +371,7 @@ impl Tui {
                 let cursor_pos = self.terminal.get_cursor_position()?;
                 Ok(Some(PreparedResumeAction::RealignViewport(ratatui::layout::Rect::new(0, cursor_pos.y, 0, 0))))
             }
-            ResumeAction::RestoreAlt => {
-                if let Ok(ratatui::layout::Position {
-                    y,
-                    ..
-                }) = self.terminal.get_cursor_position()
-                    && let Some(saved) = self.alt_saved_viewport.as_mut()
-                {
-                    saved.y = y;
-                }
-                Ok(Some(PreparedResumeAction::RestoreAltScreen))
-            }
+            ResumeAction::RestoreAlt => Ok(Some(PreparedResumeAction::RestoreAltScreen)),
             ResumeAction::None => Ok(None),
         }
     }
@@ -364,38 +395,6 @@ impl Tui {
         Ok(())
     }
 
-    /// Enter alternate screen and expand the viewport to full terminal size, saving the current
-    /// inline viewport for restoration when leaving.
-    pub fn enter_alt_screen(&mut self) -> Result<()> {
-        let _ = execute!(self.terminal.backend_mut(), EnterAlternateScreen);
-        // Enable "alternate scroll" so terminals may translate wheel to arrows
-        let _ = execute!(self.terminal.backend_mut(), EnableAlternateScroll);
-        if let Ok(size) = self.terminal.size() {
-            self.alt_saved_viewport = Some(self.terminal.viewport_area);
-            self.terminal.set_viewport_area(ratatui::layout::Rect::new(0, 0, size.width, size.height));
-            let _ = self.terminal.clear();
-        }
-        self.alt_screen_active.store(true, Ordering::Relaxed);
-        Ok(())
-    }
-
-    /// Leave alternate screen and restore the previously saved inline viewport, if any.
-    pub fn leave_alt_screen(&mut self) -> Result<()> {
-        // Disable alternate scroll when leaving alt-screen
-        let _ = execute!(self.terminal.backend_mut(), DisableAlternateScroll);
-        let _ = execute!(self.terminal.backend_mut(), LeaveAlternateScreen);
-        if let Some(saved) = self.alt_saved_viewport.take() {
-            self.terminal.set_viewport_area(saved);
-        }
-        self.alt_screen_active.store(false, Ordering::Relaxed);
-        Ok(())
-    }
-
-    pub fn insert_history_lines(&mut self, lines: Vec<Line<'static>>) {
-        self.pending_history_lines.extend(lines);
-        self.frame_requester().schedule_frame();
-    }
-
     pub fn draw(&mut self, height: u16, draw_fn: impl FnOnce(&mut custom_terminal::Frame)) -> Result<()> {
         // Precompute any viewport updates that need a cursor-position query before entering
         // the synchronized update, to avoid racing with the event reader.
@@ -410,7 +409,7 @@ impl Tui {
                 let cursor_pos = terminal.get_cursor_position()?;
                 let last_known_cursor_pos = terminal.last_known_cursor_pos;
                 if cursor_pos.y != last_known_cursor_pos.y {
-                    let cursor_delta = cursor_pos.y as i32 - last_known_cursor_pos.y as i32;
+                    let cursor_delta = i32::from(cursor_pos.y) - i32::from(last_known_cursor_pos.y);
                     let new_viewport_area = terminal.viewport_area.offset(Offset {
                         x: 0,
                         y: cursor_delta,
@@ -446,136 +445,15 @@ impl Tui {
                 terminal.clear()?;
                 terminal.set_viewport_area(area);
             }
-            // if !self.pending_history_lines.is_empty() {
-            //     crate::insert_history::insert_history_lines(terminal, self.pending_history_lines.clone());
-            //     self.pending_history_lines.clear();
-            // }
             // Update the y position for suspending so Ctrl-Z can place the cursor correctly.
             #[cfg(unix)]
-            {
-                let inline_area_bottom = if self.alt_screen_active.load(Ordering::Relaxed) {
-                    self.alt_saved_viewport
-                        .map(|r| r.bottom().saturating_sub(1))
-                        .unwrap_or_else(|| area.bottom().saturating_sub(1))
-                } else {
-                    area.bottom().saturating_sub(1)
-                };
-                self.suspend_cursor_y.store(inline_area_bottom, Ordering::Relaxed);
-            }
+            self.suspend_cursor_y.store(area.bottom().saturating_sub(1), Ordering::Relaxed);
             terminal.draw(|frame| {
                 draw_fn(frame);
             })?;
+            let image_updates = terminal.take_image_updates();
+            terminal_graphics::write_updates(terminal.backend_mut(), &image_updates)?;
             Ok(())
         })?
     }
 }
-
-// #[derive(Debug)]
-// pub enum AppEvent {
-//     UiEvent(Event),
-// }
-
-// pub fn start(tick_rate: Duration) -> Result<(), Box<dyn Error>> {
-//     // setup terminal
-//     enable_raw_mode()?;
-//     let mut stdout: io::Stdout = io::stdout();
-//     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-//     let backend: CrosstermBackend<io::Stdout> = CrosstermBackend::new(stdout);
-//     let mut terminal: Terminal<CrosstermBackend<io::Stdout>> = Terminal::new(backend)?;
-
-//     let (tx, rx) = mpsc::channel();
-//     let event_tx = tx;
-
-//     thread::spawn(move || input_thread(&event_tx));
-
-//     // create app and run it
-//     let mut app: App<'_> = App::new();
-//     app.setup_adb();
-
-//     let app_result: Result<(), Box<dyn Error>> = run_app(&mut terminal, &rx, &mut app, tick_rate);
-
-//     // restore terminal
-//     disable_raw_mode()?;
-//     execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
-//     terminal.show_cursor()?;
-
-//     if let Err(err) = app_result {
-//         println!("{err:?}");
-//     }
-
-//     Ok(())
-// }
-
-// fn run_app<B: Backend>(
-//     terminal: &mut Terminal<B>,
-//     rx: &mpsc::Receiver<AppEvent>,
-//     app: &mut App,
-//     tick_rate: Duration,
-// ) -> Result<(), Box<dyn Error>>
-// where
-//     B::Error: 'static,
-// {
-//     let mut last_tick = Instant::now();
-
-//     loop {
-//         // Wait for either input or the next tick.
-//         let timeout = tick_rate.saturating_sub(last_tick.elapsed());
-//         let mut should_draw = false;
-
-//         match rx.recv_timeout(timeout) {
-//             Ok(AppEvent::UiEvent(event)) => {
-//                 handle_ui_event(app, &event);
-//                 should_draw = true;
-
-//                 // Drain any queued events to avoid redundant redraws.
-//                 while let Ok(AppEvent::UiEvent(event)) = rx.try_recv() {
-//                     handle_ui_event(app, &event);
-//                 }
-//             }
-//             Err(mpsc::RecvTimeoutError::Timeout) => {
-//                 app.on_tick();
-//                 last_tick = Instant::now();
-//                 should_draw = true;
-//             }
-//             Err(mpsc::RecvTimeoutError::Disconnected) => break,
-//         }
-
-//         if app.should_quit {
-//             break;
-//         }
-
-//         if should_draw {
-//             terminal.draw(|frame| ui::render(frame, app))?;
-//         }
-//     }
-//     Ok(())
-// }
-
-// pub fn input_thread(tx_event: &mpsc::Sender<AppEvent>) -> anyhow::Result<()> {
-//     while let Ok(event) = event::read() {
-//         tx_event.send(AppEvent::UiEvent(event))?;
-//     }
-//     Ok(())
-// }
-
-// fn handle_ui_event(app: &mut App, event: &Event) {
-//     match event {
-//         Event::Key(key) => match key.code {
-//             KeyCode::Char('j') | KeyCode::Down => app.scroll_down(),
-//             KeyCode::Char('k') | KeyCode::Up => app.scroll_up(),
-//             KeyCode::Tab => app.on_right(),
-//             KeyCode::Char(c) => app.on_key(c),
-//             _ => {}
-//         },
-//         Event::Mouse(mouse) => match mouse.kind {
-//             MouseEventKind::ScrollUp => {
-//                 app.scroll_up();
-//             }
-//             MouseEventKind::ScrollDown => {
-//                 app.scroll_down();
-//             }
-//             _ => (),
-//         },
-//         _ => (),
-//     }
-// }
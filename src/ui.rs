@@ -1,18 +1,34 @@
+use std::collections::BTreeSet;
+
 use ratatui::{
-    Frame,
     layout::{Constraint, Layout, Rect},
-    style::{Color, Modifier, Style, Stylize},
+    style::{Color, Style, Stylize},
     text::{self, Line, Span},
-    widgets::{Block, Paragraph, Scrollbar, ScrollbarOrientation, Tabs, Wrap},
+    widgets::{Block, Paragraph, Scrollbar, ScrollbarOrientation, Tabs},
 };
 
-use crate::app::App;
+use crate::{
+    ansi::ansi_line,
+    app::{
+        App, DEVICE_TAB, FARM_TAB, FILES_TAB, LOGS_TAB, OCR_TAB, PERF_TAB, PORTS_TAB, RUNS_TAB, SCREEN_TAB, SHELL_TAB,
+        THERMAL_TAB,
+    },
+    custom_terminal::Frame,
+    screen_view::render_screenshot,
+    session::LogLine,
+    stack_trace::{Trace, is_app_frame, trace_at, visible_lines},
+};
 
 pub fn render(frame: &mut Frame, app: &mut App) {
-    let [top_area, main_panel_area] =
+    let [top_area, mut main_panel_area] =
         Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas::<2>(frame.area());
 
-    let [tab_area, _] = Layout::horizontal([Constraint::Length(19), Constraint::Min(0)]).areas::<2>(top_area);
+    // Titles are unpadded and separated by a divider, plus the borders, so all of them fit in 80
+    // columns.
+    let tabs_width: usize = app.tabs.titles.iter().map(|t| t.len() + 1).sum::<usize>() + 1;
+    let tabs_width = u16::try_from(tabs_width).unwrap_or(u16::MAX);
+    let [tab_area, device_area] =
+        Layout::horizontal([Constraint::Length(tabs_width), Constraint::Min(0)]).areas::<2>(top_area);
 
     let tabs: Tabs<'_> = app
         .tabs
@@ -21,12 +37,41 @@ pub fn render(frame: &mut Frame, app: &mut App) {
         .map(|t| text::Line::from(Span::styled(*t, Style::default().fg(Color::Green))))
         .collect::<Tabs>()
         .block(Block::bordered())
+        .padding("", "")
         .highlight_style(Style::default().fg(Color::Yellow))
         .select(app.tabs.index);
     frame.render_widget(tabs, tab_area);
+    // Vertically centered on the tab titles.
+    let device_line = Rect::new(device_area.x + 1, device_area.y + 1, device_area.width.saturating_sub(2), 1);
+    let mut device_line = device_line.intersection(device_area);
+    // The warning goes first so a long device line is what gets cut off.
+    if let Some(warning) = app.thermal.warning_line() {
+        let width = u16::try_from(warning.width()).unwrap_or(u16::MAX);
+        let [warning_area, rest] =
+            Layout::horizontal([Constraint::Max(width), Constraint::Min(0)]).spacing(2).areas(device_line);
+        frame.render_widget(warning, warning_area);
+        device_line = rest;
+    }
+    frame.render_widget(app.device.header_line().right_aligned(), device_line);
+    // New crashes and ANRs are announced on every tab.
+    if let Some(banner) = app.incidents.banner_line() {
+        let [banner_area, rest] = Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(main_panel_area);
+        frame.render_widget(banner, banner_area);
+        main_panel_area = rest;
+    }
     match app.tabs.index {
         0 => draw_first_tab(frame, app, main_panel_area),
-        1 => draw_second_tab(frame, app, main_panel_area),
+        LOGS_TAB => draw_second_tab(frame, app, main_panel_area),
+        RUNS_TAB => draw_runs_tab(frame, app, main_panel_area),
+        SCREEN_TAB => draw_screen_tab(frame, app, main_panel_area),
+        OCR_TAB => draw_ocr_tab(frame, app, main_panel_area),
+        SHELL_TAB => draw_shell_tab(frame, app, main_panel_area),
+        FILES_TAB => draw_files_tab(frame, app, main_panel_area),
+        DEVICE_TAB => draw_device_tab(frame, app, main_panel_area),
+        PERF_TAB => draw_perf_tab(frame, app, main_panel_area),
+        THERMAL_TAB => draw_thermal_tab(frame, app, main_panel_area),
+        PORTS_TAB => draw_ports_tab(frame, app, main_panel_area),
+        FARM_TAB => draw_farm_tab(frame, app, main_panel_area),
         _ => {}
     }
 }
@@ -36,32 +81,12 @@ fn draw_first_tab(frame: &mut Frame, app: &mut App, area: Rect) {
 }
 
 fn draw_logs(frame: &mut Frame, app: &mut App, area: Rect) {
-    // let total_lines = app.logs_buffer.len();
-    // app.vertical_scroll_state = app.vertical_scroll_state.content_length(total_lines);
-
-    // // Account for the bordered paragraph: inner height excludes the top/bottom borders
-    // let inner_height = area.height.saturating_sub(2) as usize;
-    // let max_scroll = total_lines.saturating_sub(inner_height);
-
-    // app.vertical_scroll = max_scroll;
-    // app.vertical_scroll_state = app.vertical_scroll_state.position(inner_height);
-
-    // #[allow(clippy::cast_possible_truncation)]
-    // let paragraph: Paragraph<'_> = Paragraph::new(app.logs_buffer.clone())
-    //     // .wrap(Wrap {
-    //     // trim: true,
-    //     // })
-    //     .gray()
-    //     .block(Block::bordered())
-    //     .scroll((app.vertical_scroll as u16, 0));
-    // frame.render_widget(paragraph, area);
-    // frame.render_stateful_widget(
-    //     Scrollbar::new(ScrollbarOrientation::VerticalRight).begin_symbol(Some("↑")).end_symbol(Some("↓")),
-    //     area,
-    //     &mut app.vertical_scroll_state,
-    // );
+    // Folded stack traces hide their frames, so scrolling and the cursor go by rows of `shown`.
+    let shown = visible_lines(app.logs_buffer.len(), &app.traces, &app.expanded_traces);
+    let cursor_row = shown.partition_point(|&index| index <= app.log_cursor).saturating_sub(1);
+
     // Update scrollbar with full content length
-    let total_lines = app.logs_buffer.len();
+    let total_lines = shown.len();
     app.vertical_scroll_state = app.vertical_scroll_state.content_length(total_lines);
 
     // Account for the bordered paragraph: inner height excludes the top/bottom borders
@@ -70,33 +95,32 @@ fn draw_logs(frame: &mut Frame, app: &mut App, area: Rect) {
 
     if app.follow_tail {
         app.vertical_scroll = max_scroll;
-        app.vertical_scroll_state = app.vertical_scroll_state.position(app.vertical_scroll);
-    } else if app.vertical_scroll > max_scroll {
-        // Clamp if content shrank or viewport grew
-        app.vertical_scroll = max_scroll;
-        app.vertical_scroll_state = app.vertical_scroll_state.position(app.vertical_scroll);
+    } else if cursor_row < app.vertical_scroll {
+        app.vertical_scroll = cursor_row;
+    } else if cursor_row >= app.vertical_scroll + inner_height {
+        // Keep the cursor on the last visible row when it moves past the bottom
+        app.vertical_scroll = cursor_row + 1 - inner_height;
     }
+    // Clamp if content shrank or viewport grew
+    app.vertical_scroll = app.vertical_scroll.min(max_scroll);
+    app.vertical_scroll_state = app.vertical_scroll_state.position(app.vertical_scroll);
 
     let start = app.vertical_scroll;
     let end = start.saturating_add(inner_height).min(total_lines);
-    let visible: Vec<Line> = app.logs_buffer.iter().skip(start).take(end.saturating_sub(start)).cloned().collect();
-    // let visible: Vec<Line> = app
-    //     .logs_buffer
-    //     .iter()
-    //     .skip(start)
-    //     .take(end.saturating_sub(start))
-    //     // Borrow the stored String; no extra allocation per draw
-    //     // .map(|s| Line::from(s.as_str()))
-    //     .map(|s| s.clone())
-    //     .collect();
-
-    let paragraph = Paragraph::new(visible)
-        // .wrap(Wrap {
-        //     trim: true,
-        // })
-        .gray()
-        .block(Block::bordered());
-    // .scroll((app.vertical_scroll as u16, 0));
+    let visible: Vec<Line> = shown[start..end]
+        .iter()
+        .enumerate()
+        .map(|(offset, &index)| {
+            let line = log_line(&app.logs_buffer, &app.traces, &app.expanded_traces, index);
+            if start + offset == cursor_row && !app.follow_tail {
+                line.reversed()
+            } else {
+                line
+            }
+        })
+        .collect();
+
+    let paragraph = Paragraph::new(visible).gray().block(Block::bordered());
     frame.render_widget(paragraph, area);
     frame.render_stateful_widget(
         Scrollbar::new(ScrollbarOrientation::VerticalRight).begin_symbol(Some("↑")).end_symbol(Some("↓")),
@@ -105,22 +129,107 @@ fn draw_logs(frame: &mut Frame, app: &mut App, area: Rect) {
     );
 }
 
-fn draw_second_tab(frame: &mut Frame, _app: &mut App, area: Rect) {
-    let [top, bottom] = Layout::vertical([Constraint::Length(31), Constraint::Min(0)]).areas::<2>(area);
+/// Line `index` of the log, with stack frames told apart as app or framework code and a marker on
+/// folded and unfolded traces.
+fn log_line(lines: &[LogLine], traces: &[Trace], expanded: &BTreeSet<usize>, index: usize) -> Line<'static> {
+    let text = &lines[index].text;
+    let Some(trace) = trace_at(traces, index) else {
+        return ansi_line(text);
+    };
+    let folded = !expanded.contains(&trace.header);
+    let mut line = if index < trace.frames.start {
+        ansi_line(text)
+    } else if is_app_frame(text) {
+        ansi_line(text).yellow()
+    } else {
+        ansi_line(text).dim()
+    };
+    if folded && index == trace.frames.start {
+        let hidden = &lines[trace.frames.start + 1..trace.frames.end];
+        let in_app = hidden.iter().filter(|line| is_app_frame(&line.text)).count();
+        line.push_span(Span::from(format!("  ▸ {} more frames, {in_app} in app code (enter)", hidden.len())).dim());
+    } else if !folded && index == trace.header {
+        line.push_span(Span::from("  ▾").dim());
+    }
+    line
+}
+
+fn draw_second_tab(frame: &mut Frame, app: &mut App, area: Rect) {
+    if app.incidents.is_pager_open() {
+        app.incidents.render_pager(frame, area);
+        return;
+    }
+    let [logs_area, timeline_area] =
+        Layout::horizontal([Constraint::Min(0), Constraint::Percentage(35)]).areas::<2>(area);
+    draw_logs(frame, app, logs_area);
+    draw_timeline(frame, app, timeline_area);
+}
+
+/// Side pane of the LOGS tab: the newest session screenshot taken before the cursor line.
+fn draw_timeline(frame: &mut Frame, app: &mut App, area: Rect) {
+    // Only the session's own device is screenshotted; a farm instance's log has nothing to line up with.
+    if let Some(serial) = app.farm.shown() {
+        let block = Block::bordered().title(" Timeline ");
+        let message = Paragraph::new(Line::from(format!("No screenshots of {serial}")).italic());
+        frame.render_widget(message, block.inner(area));
+        frame.render_widget(block, area);
+        return;
+    }
+    let Some(line_at) = app.logs_buffer.get(app.log_cursor).map(|l| l.received_at) else {
+        frame.render_widget(Block::bordered().title(" Timeline "), area);
+        return;
+    };
+    let shot = app.session.as_mut().and_then(|session| session.screenshot_before(line_at));
+    let Some(shot) = shot else {
+        let block = Block::bordered().title(" Timeline ");
+        let message = Paragraph::new(Line::from("No screenshot before this line").italic());
+        frame.render_widget(message, block.inner(area));
+        frame.render_widget(block, area);
+        return;
+    };
+    let lag = line_at.duration_since(shot.captured_at).unwrap_or_default();
+    let block = Block::bordered().title(format!(" Screen {:.1}s before line ", lag.as_secs_f64()));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    render_screenshot(frame, shot, inner);
+}
 
-    let block: Block<'_> = Block::bordered();
-    frame.render_widget(block, top);
-    draw_footer(frame, bottom);
+fn draw_runs_tab(frame: &mut Frame, app: &mut App, area: Rect) {
+    app.runs.render(area, frame.buffer_mut());
 }
 
-fn draw_footer(frame: &mut Frame, area: Rect) {
-    let text = vec![text::Line::from(
-        "This is a paragraph with several lines. You can change style your text the way you want",
-    )];
-    let block = Block::bordered()
-        .title(Span::styled("Footer", Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)));
-    let paragraph = Paragraph::new(text).block(block).wrap(Wrap {
-        trim: true,
-    });
-    frame.render_widget(paragraph, area);
+fn draw_screen_tab(frame: &mut Frame, app: &mut App, area: Rect) {
+    app.screen.render(frame, area);
+}
+
+fn draw_ocr_tab(frame: &mut Frame, app: &mut App, area: Rect) {
+    app.ocr.render(frame, area, app.session.as_mut());
+}
+
+fn draw_shell_tab(frame: &mut Frame, app: &mut App, area: Rect) {
+    app.shell.render(frame, area);
+}
+
+fn draw_files_tab(frame: &mut Frame, app: &mut App, area: Rect) {
+    app.files.render(frame, area);
+}
+
+fn draw_device_tab(frame: &mut Frame, app: &mut App, area: Rect) {
+    app.device.render(frame, area);
+}
+
+fn draw_perf_tab(frame: &mut Frame, app: &App, area: Rect) {
+    app.perf.render(frame, area);
+}
+
+fn draw_thermal_tab(frame: &mut Frame, app: &App, area: Rect) {
+    app.thermal.render(frame, area);
+}
+
+fn draw_ports_tab(frame: &mut Frame, app: &mut App, area: Rect) {
+    app.ports.render(frame, area);
+}
+
+fn draw_farm_tab(frame: &mut Frame, app: &mut App, area: Rect) {
+    app.farm.render(frame, area);
 }
@@ -0,0 +1,295 @@
+//! Detection of bots that stopped making progress, and the chain of remedies tried on them.
+//!
+//! Progress is the bot entering a different state or a new career turn, as read off its log by
+//! [`crate::farm`]. Devices that never reported either are not running a bot and are left alone.
+//! An instance is stuck when it made none for `stall_secs`, or reported the same
+//! state `max_repeats` times in a row, which is what a bot looping on a screen it does not
+//! recognize looks like. The remediation chain then runs its steps in order, giving the bot
+//! `settle_secs` after every step that acts on the device and stopping as soon as it moves again.
+//!
+//! Settings live in `<data dir>/watchdog.json`; missing keys take their defaults. Remediation is
+//! opt-in: by default stuck instances are only reported. A full chain looks like:
+//!
+//! ```json
+//! { "stall_secs": 300, "max_repeats": 30, "settle_secs": 20, "remediation": ["snapshot", "back", "restart"] }
+//! ```
+//!
+//! Snapshots save the screen and the instance's recent log as `<serial>.<unix millis>.png` and
+//! `.log` in the `watchdog` directory of the session.
+
+use std::{
+    fmt::{self, Write as _},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    adb::{AdbOptions, shell_output},
+    app_event::AppEvent,
+    app_event_sender::AppEventSender,
+    device_info::{GAME_PACKAGES, serial_path},
+    input::{self, InputAction},
+    screenshot,
+    session::{LogLine, unix_millis},
+};
+
+/// How often stuck instances are looked for.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    /// Save a screenshot and the recent log.
+    Snapshot,
+    /// Press the back key.
+    Back,
+    /// Force-stop the game and launch it again.
+    Restart,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Snapshot => "snapshot",
+            Self::Back => "back",
+            Self::Restart => "restart",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchdogConfig {
+    /// Seconds without progress after which an instance is stuck; 0 never.
+    pub stall_secs: u64,
+    /// Times in a row the same state may be reported before the instance is stuck; 0 any.
+    pub max_repeats: u32,
+    /// Seconds the bot gets to move again after a step that acts on the device.
+    pub settle_secs: u64,
+    /// Steps run on a stuck instance; none by default.
+    pub remediation: Vec<Step>,
+    /// Package restarted by the `restart` step; the running or installed game when omitted.
+    pub package: Option<String>,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            stall_secs: 300,
+            max_repeats: 30,
+            settle_secs: 20,
+            remediation: Vec::new(),
+            package: None,
+        }
+    }
+}
+
+impl WatchdogConfig {
+    /// The settings in the file at `path`, or the defaults if there is none.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read(path) {
+            Ok(json) => serde_json::from_slice(&json).map_err(io::Error::other),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Progress tracking of one instance.
+#[derive(Debug)]
+pub struct Watchdog {
+    last_progress: Instant,
+    state: Option<String>,
+    turn: Option<u32>,
+    repeats: u32,
+    /// The chain was asked for by hand.
+    requested: bool,
+    /// Set while a remediation chain runs; raised when the bot moves again.
+    progressed: Option<Arc<AtomicBool>>,
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self {
+            last_progress: Instant::now(),
+            state: None,
+            turn: None,
+            repeats: 0,
+            requested: false,
+            progressed: None,
+        }
+    }
+}
+
+impl Watchdog {
+    fn progress(&mut self) {
+        self.last_progress = Instant::now();
+        self.repeats = 0;
+        if let Some(progressed) = &self.progressed {
+            progressed.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// The bot reported being in `state`.
+    pub fn on_state(&mut self, state: &str) {
+        if self.state.as_deref() == Some(state) {
+            self.repeats += 1;
+        } else {
+            self.state = Some(state.to_string());
+            self.progress();
+            self.repeats = 1;
+        }
+    }
+
+    /// The bot read something on career turn `turn`.
+    pub fn on_turn(&mut self, turn: u32) {
+        if self.turn != Some(turn) {
+            self.turn = Some(turn);
+            self.progress();
+        }
+    }
+
+    /// Start over, e.g. after the device reconnected.
+    pub fn reset(&mut self) {
+        self.last_progress = Instant::now();
+        self.repeats = 0;
+    }
+
+    /// Run the remediation chain at the next check, stuck or not.
+    pub const fn request(&mut self) {
+        self.requested = true;
+    }
+
+    pub const fn is_remediating(&self) -> bool {
+        self.progressed.is_some()
+    }
+
+    /// Why the instance is stuck, if it is and nothing is being done about it yet.
+    pub fn check(&self, config: &WatchdogConfig) -> Option<String> {
+        if self.is_remediating() {
+            return None;
+        }
+        if self.requested {
+            return Some("requested from the FARM tab".to_string());
+        }
+        if self.state.is_none() && self.turn.is_none() {
+            return None;
+        }
+        let idle = self.last_progress.elapsed();
+        if config.stall_secs > 0 && idle >= Duration::from_secs(config.stall_secs) {
+            return Some(format!("no progress for {}s", idle.as_secs()));
+        }
+        let state = self.state.as_deref().unwrap_or_default();
+        (config.max_repeats > 0 && self.repeats >= config.max_repeats)
+            .then(|| format!("state {state} reported {} times in a row", self.repeats))
+    }
+
+    /// Mark a remediation chain as started, returning the flag it watches for progress.
+    pub fn start_remediation(&mut self) -> Arc<AtomicBool> {
+        self.requested = false;
+        self.progressed.insert(Arc::new(AtomicBool::new(false))).clone()
+    }
+
+    /// The chain ended; the instance gets a fresh `stall_secs` before it can be stuck again.
+    pub fn finish_remediation(&mut self) {
+        self.progressed = None;
+        self.reset();
+    }
+}
+
+/// Save the screen and `log` of the device to `dir`.
+fn snapshot(adb: &AdbOptions, serial: &str, dir: &Path, log: &[LogLine]) -> Result<String, String> {
+    fs::create_dir_all(dir).map_err(|err| format!("{}: {err}", dir.display()))?;
+    let at = unix_millis(SystemTime::now());
+    let log_path = serial_path(dir, serial, &format!("{at}.log"));
+    let mut text = String::new();
+    for line in log {
+        let _ = writeln!(text, "{}\t{}", unix_millis(line.received_at), line.text);
+    }
+    fs::write(&log_path, text).map_err(|err| format!("{}: {err}", log_path.display()))?;
+    let shot = screenshot::capture(adb).map_err(|err| format!("saved the log, but {err:#}"))?;
+    let png_path = serial_path(dir, serial, &format!("{at}.png"));
+    fs::write(&png_path, &shot.png).map_err(|err| format!("{}: {err}", png_path.display()))?;
+    Ok(format!("saved snapshot {}", png_path.display()))
+}
+
+/// Force-stop the game and start its launcher activity again.
+fn restart(adb: &AdbOptions, package: Option<&str>) -> Result<String, String> {
+    let packages = package.map_or_else(|| GAME_PACKAGES.join(" "), ToString::to_string);
+    // The running package first, then whichever is installed.
+    let script = format!(
+        "for p in {packages}; do pidof $p >/dev/null && break; p=; done; \
+         [ -z \"$p\" ] && for p in {packages}; do pm path $p >/dev/null 2>&1 && break; p=; done; \
+         [ -n \"$p\" ] || {{ echo 'Error: game not installed'; exit; }}; \
+         am force-stop $p; echo \"@@package $p\"; \
+         am start -n \"$(cmd package resolve-activity --brief $p | tail -n 1)\" 2>&1"
+    );
+    let output = shell_output(&mut adb.device(), &[&script]).map_err(|err| err.to_string())?;
+    let output = String::from_utf8_lossy(&output);
+    if let Some(error) = output.lines().find(|line| line.starts_with("Error")) {
+        return Err(format!("restart failed: {}", error.trim()));
+    }
+    let package = output.lines().find_map(|line| line.strip_prefix("@@package ")).unwrap_or_default();
+    Ok(format!("restarted {}", package.trim()))
+}
+
+/// Run `config`'s remediation chain on `serial` on a worker thread, delivering an
+/// [`AppEvent::WatchdogAction`] per step and [`AppEvent::WatchdogFinished`] at the end.
+pub fn spawn_remediation(
+    adb: &AdbOptions,
+    app_event_tx: AppEventSender,
+    serial: String,
+    config: WatchdogConfig,
+    snapshot_dir: PathBuf,
+    log: Vec<LogLine>,
+    progressed: Arc<AtomicBool>,
+) {
+    let adb = adb.for_serial(&serial);
+    thread::spawn(move || {
+        let action = |result: Result<String, String>| {
+            app_event_tx.send(AppEvent::WatchdogAction {
+                serial: serial.clone(),
+                result,
+            });
+        };
+        for step in &config.remediation {
+            let result = match step {
+                Step::Snapshot => snapshot(&adb, &serial, &snapshot_dir, &log),
+                Step::Back => input::send(&mut adb.device(), &InputAction::Key("KEYCODE_BACK".to_string()))
+                    .map(|()| "pressed back".to_string()),
+                Step::Restart => restart(&adb, config.package.as_deref()),
+            };
+            action(result.map_err(|err| format!("{step}: {err}")));
+            if *step == Step::Snapshot {
+                continue;
+            }
+            let settled = Instant::now();
+            while settled.elapsed() < Duration::from_secs(config.settle_secs) && !progressed.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_secs(1));
+            }
+            if progressed.load(Ordering::Relaxed) {
+                action(Ok(format!("moving again after {step}")));
+                break;
+            }
+        }
+        app_event_tx.send(AppEvent::WatchdogFinished(serial));
+    });
+}
+
+/// Deliver [`AppEvent::WatchdogTick`] every [`CHECK_INTERVAL`] until the app shuts down.
+pub fn spawn_ticker(app_event_tx: AppEventSender) {
+    thread::spawn(move || {
+        while !app_event_tx.app_event_tx.is_closed() {
+            thread::sleep(CHECK_INTERVAL);
+            app_event_tx.send(AppEvent::WatchdogTick);
+        }
+    });
+}